@@ -0,0 +1,83 @@
+use syn::{Attribute, Expr, Type};
+
+/// Attributes on the deriving type, e.g. `#[serial(tag = u16)]`.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    /// The integer type the enum discriminant is written as. Defaults to `u8`.
+    pub tag: Option<Type>,
+}
+
+impl ContainerAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut this = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serial")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    this.tag = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown serial attribute, expected `tag`"))
+                }
+            })?;
+        }
+
+        Ok(this)
+    }
+}
+
+/// Attributes on a single field, e.g. `#[serial(prefix = u16, bound = 1024)]`.
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// Encodes the field through `PrefixedRead`/`PrefixedWrite` with this prefix type.
+    pub prefix: Option<Type>,
+    /// The maximum length accepted for a prefixed field.
+    pub bound: Option<Expr>,
+    /// Leaves the field out of the encoding and fills it with `Default` on read.
+    pub skip: bool,
+    /// Encodes a number field in big-endian byte order through `serial::Be`.
+    pub big_endian: bool,
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut this = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serial")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("prefix") {
+                    this.prefix = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("bound") {
+                    this.bound = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("skip") {
+                    this.skip = true;
+                } else if meta.path.is_ident("big_endian") {
+                    this.big_endian = true;
+                } else {
+                    return Err(meta.error(
+                        "unknown serial attribute, expected `prefix`, `bound`, `skip` or `big_endian`",
+                    ));
+                }
+                Ok(())
+            })?;
+
+            if this.bound.is_some() && this.prefix.is_none() {
+                return Err(syn::Error::new_spanned(attr, "`bound` requires a `prefix`"));
+            }
+            if this.skip && this.prefix.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`skip` cannot be combined with `prefix`",
+                ));
+            }
+            if this.big_endian && (this.skip || this.prefix.is_some()) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`big_endian` cannot be combined with `skip` or `prefix`, use a `Be<_>` prefix instead",
+                ));
+            }
+        }
+
+        Ok(this)
+    }
+}
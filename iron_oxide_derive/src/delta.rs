@@ -0,0 +1,106 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Index};
+
+use crate::attr::FieldAttrs;
+use crate::read::read_field;
+use crate::write::write_field;
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let serial = crate::serial_path();
+    let name = &input.ident;
+    let generics = crate::add_bound(&input.generics, &quote!(#serial::WriteTo));
+    let generics = crate::add_bound(&generics, &quote!(#serial::ReadFrom));
+    let generics = crate::add_bound(&generics, &quote!(::core::cmp::PartialEq));
+    let generics = crate::add_bound(&generics, &quote!(::core::clone::Clone));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "Delta can only be derived for structs",
+        ));
+    };
+
+    let mut bit = 0u32;
+    let mut compares = Vec::new();
+    let mut writes = Vec::new();
+    let mut reads = Vec::new();
+
+    for (i, field) in data.fields.iter().enumerate() {
+        let (member, label) = match &field.ident {
+            Some(ident) => (quote!(#ident), format!("{name}.{ident}")),
+            None => {
+                let index = Index::from(i);
+                (quote!(#index), format!("{name}.{i}"))
+            }
+        };
+
+        if FieldAttrs::parse(&field.attrs)?.skip {
+            reads.push(quote!(#member: ::core::default::Default::default()));
+            continue;
+        }
+        if bit == u64::BITS {
+            return Err(syn::Error::new_spanned(
+                field,
+                "Delta supports at most 64 encoded fields",
+            ));
+        }
+
+        let flag = quote!((1u64 << #bit));
+        let write = write_field(quote!(&self.#member), field, label.clone())?;
+        let read = read_field(field, label)?;
+        compares.push(quote! {
+            if self.#member != baseline.#member {
+                mask |= #flag;
+            }
+        });
+        writes.push(quote! {
+            if mask & #flag != 0 {
+                #write
+            }
+        });
+        reads.push(quote! {
+            #member: if mask & #flag != 0 {
+                #read
+            } else {
+                ::core::clone::Clone::clone(&baseline.#member)
+            }
+        });
+        bit += 1;
+    }
+
+    let unknown = if bit == u64::BITS {
+        quote!(0u64)
+    } else {
+        quote!(u64::MAX << #bit)
+    };
+    let message = format!("change mask {{:#b}} has bits for fields {name} does not have");
+
+    Ok(quote! {
+        impl #impl_generics #serial::Delta for #name #ty_generics #where_clause {
+            fn write_delta(
+                &self,
+                baseline: &Self,
+                writer: &mut impl ::std::io::Write,
+            ) -> ::std::io::Result<()> {
+                #[allow(unused_mut)]
+                let mut mask = 0u64;
+                #(#compares)*
+                #serial::WriteTo::write(&#serial::VarU64(mask), writer)?;
+                #(#writes)*
+                Ok(())
+            }
+
+            fn read_delta(baseline: &Self, data: &mut impl ::std::io::Read) -> ::std::io::Result<Self> {
+                let mask = <#serial::VarU64 as #serial::ReadFrom>::read(data)?.0;
+                if mask & #unknown != 0 {
+                    return Err(#serial::SerialError::new(#serial::SerialErrorKind::InvalidTag)
+                        .with_source(format!(#message, mask))
+                        .into());
+                }
+                Ok(Self { #(#reads),* })
+            }
+        }
+    })
+}
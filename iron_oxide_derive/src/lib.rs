@@ -0,0 +1,115 @@
+//! Derive macros for the traits in `iron_oxide::serial`.
+//!
+//! The generated code refers to the traits through `::iron_oxide::serial`, so
+//! the derives are meant to be used through the re-exports in that module.
+
+mod attr;
+mod delta;
+mod prefixed;
+mod read;
+mod write;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    DataEnum, DeriveInput, Expr, ExprLit, ExprUnary, Generics, Lit, UnOp, parse_macro_input,
+};
+
+/// Derives `ReadFrom`, decoding all fields in declaration order.
+#[proc_macro_derive(ReadFrom, attributes(serial))]
+pub fn derive_read_from(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    read::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `WriteTo`, encoding all fields in declaration order.
+#[proc_macro_derive(WriteTo, attributes(serial))]
+pub fn derive_write_to(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    write::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `PrefixedRead` for a type that also implements `ReadFrom`,
+/// reading it as a frame prefixed with its length in bytes.
+#[proc_macro_derive(PrefixedRead)]
+pub fn derive_prefixed_read(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    prefixed::expand_read(&input).into()
+}
+
+/// Derives `PrefixedWrite` for a type that also implements `WriteTo`,
+/// writing it as a frame prefixed with its length in bytes.
+#[proc_macro_derive(PrefixedWrite)]
+pub fn derive_prefixed_write(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    prefixed::expand_write(&input).into()
+}
+
+/// Derives `Delta` for a struct that also implements `ReadFrom` and `WriteTo`,
+/// encoding the fields that differ from the baseline behind a bitmask.
+#[proc_macro_derive(Delta, attributes(serial))]
+pub fn derive_delta(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    delta::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+pub(crate) fn serial_path() -> TokenStream2 {
+    quote!(::iron_oxide::serial)
+}
+
+/// Adds `bound` to every type parameter of `generics`.
+pub(crate) fn add_bound(generics: &Generics, bound: &TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(#bound));
+    }
+    generics
+}
+
+/// Computes the tag of every variant the same way rustc assigns discriminants:
+/// explicit integer discriminants are used as is, all others count up from the
+/// previous variant.
+pub(crate) fn discriminants(data: &DataEnum) -> syn::Result<Vec<TokenStream2>> {
+    let mut next: i128 = 0;
+    let mut tags = Vec::with_capacity(data.variants.len());
+
+    for variant in &data.variants {
+        if let Some((_, expr)) = &variant.discriminant {
+            next = parse_discriminant(expr)?;
+        }
+
+        let literal = Literal::i128_unsuffixed(next.unsigned_abs() as i128);
+        tags.push(if next < 0 {
+            quote!(-#literal)
+        } else {
+            quote!(#literal)
+        });
+        next += 1;
+    }
+
+    Ok(tags)
+}
+
+fn parse_discriminant(expr: &Expr) -> syn::Result<i128> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => parse_discriminant(expr).map(|value| -value),
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "only integer literal discriminants are supported",
+        )),
+    }
+}
@@ -0,0 +1,40 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+pub fn expand_read(input: &DeriveInput) -> TokenStream {
+    let serial = crate::serial_path();
+    let name = &input.ident;
+    let generics = crate::add_bound(&input.generics, &quote!(#serial::ReadFrom));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #serial::PrefixedRead for #name #ty_generics #where_clause {
+            fn read_prefixed_bound<P: TryInto<usize> + #serial::ReadFrom>(
+                data: &mut impl ::std::io::Read,
+                bound: usize,
+            ) -> ::std::io::Result<Self> {
+                #serial::read_framed::<P, Self>(data, bound)
+            }
+        }
+    }
+}
+
+pub fn expand_write(input: &DeriveInput) -> TokenStream {
+    let serial = crate::serial_path();
+    let name = &input.ident;
+    let generics = crate::add_bound(&input.generics, &quote!(#serial::WriteTo));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #serial::PrefixedWrite for #name #ty_generics #where_clause {
+            fn write_prefixed_bound<P: TryFrom<usize> + #serial::WriteTo>(
+                &self,
+                writer: &mut impl ::std::io::Write,
+                bound: usize,
+            ) -> ::std::io::Result<()> {
+                #serial::write_framed::<P, Self>(self, writer, bound)
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, DeriveInput, Field, Fields};
+
+use crate::attr::{ContainerAttrs, FieldAttrs};
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let serial = crate::serial_path();
+    let name = &input.ident;
+    let generics = crate::add_bound(&input.generics, &quote!(#serial::ReadFrom));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let value = read_fields(quote!(Self), &data.fields, &name.to_string())?;
+            quote!(Ok(#value))
+        }
+        Data::Enum(data) => read_enum(input, data)?,
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "ReadFrom cannot be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics #serial::ReadFrom for #name #ty_generics #where_clause {
+            fn read(data: &mut impl ::std::io::Read) -> ::std::io::Result<Self> {
+                #body
+            }
+        }
+    })
+}
+
+fn read_enum(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
+    let serial = crate::serial_path();
+    let attrs = ContainerAttrs::parse(&input.attrs)?;
+    let tag_ty = attrs.tag.unwrap_or_else(|| syn::parse_quote!(u8));
+    let tags = crate::discriminants(data)?;
+    let message = format!("{{}} is not a variant of {}", input.ident);
+
+    let arms = data
+        .variants
+        .iter()
+        .zip(tags)
+        .map(|(variant, tag)| {
+            let ident = &variant.ident;
+            let owner = format!("{}::{}", input.ident, ident);
+            let value = read_fields(quote!(Self::#ident), &variant.fields, &owner)?;
+            Ok(quote!(#tag => Ok(#value),))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        let tag = <#tag_ty as #serial::ReadFrom>::read(data)?;
+        match tag {
+            #(#arms)*
+            _ => Err(#serial::SerialError::new(#serial::SerialErrorKind::InvalidTag)
+                .with_source(format!(#message, tag))
+                .into()),
+        }
+    })
+}
+
+/// Reads `fields` into the struct or variant at `path`. `owner` names it in error paths.
+fn read_fields(path: TokenStream, fields: &Fields, owner: &str) -> syn::Result<TokenStream> {
+    match fields {
+        Fields::Named(named) => {
+            let values = named
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    let value = read_field(field, format!("{owner}.{ident}"))?;
+                    Ok(quote!(#ident: #value))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(quote!(#path { #(#values),* }))
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = unnamed
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(i, field)| read_field(field, format!("{owner}.{i}")))
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(quote!(#path(#(#values),*)))
+        }
+        Fields::Unit => Ok(path),
+    }
+}
+
+pub fn read_field(field: &Field, label: String) -> syn::Result<TokenStream> {
+    let serial = crate::serial_path();
+    let attrs = FieldAttrs::parse(&field.attrs)?;
+    let ty = &field.ty;
+
+    if attrs.skip {
+        return Ok(quote!(::core::default::Default::default()));
+    }
+
+    let read = if let Some(prefix) = &attrs.prefix {
+        match &attrs.bound {
+            Some(bound) => quote! {
+                <#ty as #serial::PrefixedRead>::read_prefixed_bound::<#prefix>(data, #bound)
+            },
+            None => quote!(<#ty as #serial::PrefixedRead>::read_prefixed::<#prefix>(data)),
+        }
+    } else if attrs.big_endian {
+        quote!(<#serial::Be<#ty> as #serial::ReadFrom>::read(data).map(|value| value.0))
+    } else {
+        quote!(<#ty as #serial::ReadFrom>::read(data))
+    };
+
+    Ok(quote!(#read.map_err(|e| #serial::SerialError::in_field(e, #label))?))
+}
@@ -0,0 +1,134 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Data, DataEnum, DeriveInput, Field, Fields, Ident, Index};
+
+use crate::attr::{ContainerAttrs, FieldAttrs};
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let serial = crate::serial_path();
+    let name = &input.ident;
+    let generics = crate::add_bound(&input.generics, &quote!(#serial::WriteTo));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let writes = data
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    let (member, label) = match &field.ident {
+                        Some(ident) => (quote!(#ident), format!("{name}.{ident}")),
+                        None => {
+                            let index = Index::from(i);
+                            (quote!(#index), format!("{name}.{i}"))
+                        }
+                    };
+                    write_field(quote!(&self.#member), field, label)
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote!(#(#writes)*)
+        }
+        Data::Enum(data) => write_enum(input, data)?,
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "WriteTo cannot be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics #serial::WriteTo for #name #ty_generics #where_clause {
+            fn write(&self, writer: &mut impl ::std::io::Write) -> ::std::io::Result<()> {
+                #body
+                Ok(())
+            }
+        }
+    })
+}
+
+fn write_enum(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
+    let serial = crate::serial_path();
+    let attrs = ContainerAttrs::parse(&input.attrs)?;
+    let tag_ty = attrs.tag.unwrap_or_else(|| syn::parse_quote!(u8));
+    let tags = crate::discriminants(data)?;
+
+    let arms = data
+        .variants
+        .iter()
+        .zip(tags)
+        .map(|(variant, tag)| {
+            let ident = &variant.ident;
+            let bindings: Vec<Ident> = (0..variant.fields.len())
+                .map(|i| format_ident!("field{}", i, span = Span::call_site()))
+                .collect();
+
+            let mut patterns = Vec::with_capacity(bindings.len());
+            let mut writes = Vec::with_capacity(bindings.len());
+            for (i, (field, binding)) in variant.fields.iter().zip(&bindings).enumerate() {
+                let skip = FieldAttrs::parse(&field.attrs)?.skip;
+                patterns.push(match (&field.ident, skip) {
+                    (Some(ident), true) => quote!(#ident: _),
+                    (None, true) => quote!(_),
+                    (Some(ident), false) => quote!(#ident: #binding),
+                    (None, false) => quote!(#binding),
+                });
+                let label = match &field.ident {
+                    Some(field) => format!("{}::{}.{}", input.ident, ident, field),
+                    None => format!("{}::{}.{}", input.ident, ident, i),
+                };
+                writes.push(write_field(quote!(#binding), field, label)?);
+            }
+
+            let pattern = match &variant.fields {
+                Fields::Named(_) => quote!(Self::#ident { #(#patterns),* }),
+                Fields::Unnamed(_) => quote!(Self::#ident(#(#patterns),*)),
+                Fields::Unit => quote!(Self::#ident),
+            };
+
+            Ok(quote! {
+                #pattern => {
+                    let tag: #tag_ty = #tag;
+                    #serial::WriteTo::write(&tag, writer)?;
+                    #(#writes)*
+                }
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        match self {
+            #(#arms)*
+        }
+    })
+}
+
+/// Writes the field `value` refers to. `label` names it in error paths.
+pub fn write_field(value: TokenStream, field: &Field, label: String) -> syn::Result<TokenStream> {
+    let serial = crate::serial_path();
+    let attrs = FieldAttrs::parse(&field.attrs)?;
+    let ty = &field.ty;
+
+    if attrs.skip {
+        return Ok(TokenStream::new());
+    }
+
+    // Fully qualified, so that inherent or other trait methods of the field type are not picked.
+    let write = if let Some(prefix) = &attrs.prefix {
+        match &attrs.bound {
+            Some(bound) => quote! {
+                <#ty as #serial::PrefixedWrite>::write_prefixed_bound::<#prefix>(#value, writer, #bound)
+            },
+            None => {
+                quote!(<#ty as #serial::PrefixedWrite>::write_prefixed::<#prefix>(#value, writer))
+            }
+        }
+    } else if attrs.big_endian {
+        quote!(<#serial::Be<#ty> as #serial::WriteTo>::write(&#serial::Be(*#value), writer))
+    } else {
+        quote!(<#ty as #serial::WriteTo>::write(#value, writer))
+    };
+
+    Ok(quote!(#write.map_err(|e| #serial::SerialError::in_field(e, #label))?;))
+}
@@ -11,6 +11,8 @@ use std::{
 
 use rand;
 
+use crate::serial::{self, ReadFrom, SerialError, SerialErrorKind, WriteTo};
+
 pub struct Matrix {
     data: NonNull<f32>, // Rohspeicher für die Matrixdaten
     rows: usize,
@@ -539,4 +541,45 @@ impl Debug for Matrix {
     }
 }
 
+/// Written as `rows` and `cols` as `u32`, followed by the elements row by row.
+impl WriteTo for Matrix {
+    fn write(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        // Empty matrices cannot be read back.
+        flat_len(&[self.rows, self.cols])?;
+        write_dim(self.rows, writer)?;
+        write_dim(self.cols, writer)?;
+        serial::write_f32s(self.as_slice(), writer)
+    }
+}
+
+impl ReadFrom for Matrix {
+    fn read(data: &mut impl std::io::Read) -> std::io::Result<Self> {
+        let rows = u32::read(data)? as usize;
+        let cols = u32::read(data)? as usize;
+        let len = flat_len(&[rows, cols])?;
+
+        let values = serial::read_f32s(data, len)?;
+        Ok(Self::from_box(values.into_boxed_slice(), rows, cols))
+    }
+}
+
+pub(super) fn write_dim(dim: usize, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+    let dim = u32::try_from(dim)
+        .map_err(|e| SerialError::new(SerialErrorKind::OutOfRange).with_source(e))?;
+    dim.write(writer)
+}
+
+/// The number of elements of a shape that is read or written, which has to be non-empty.
+pub(super) fn flat_len(dims: &[usize]) -> std::io::Result<usize> {
+    let len = dims
+        .iter()
+        .try_fold(1usize, |len, dim| len.checked_mul(*dim))
+        .ok_or(SerialErrorKind::OutOfRange)?;
+
+    if len == 0 {
+        Err(SerialError::new(SerialErrorKind::InvalidLength).with_source("empty shape"))?;
+    }
+    Ok(len)
+}
+
 unsafe impl Send for Matrix {}
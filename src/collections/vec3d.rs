@@ -6,6 +6,9 @@ use std::{
     ptr::NonNull,
 };
 
+use super::matrix::{flat_len, write_dim};
+use crate::serial::{self, ReadFrom, WriteTo};
+
 pub struct Vec3D {
     data: NonNull<f32>, // Rohspeicher für die Matrixdaten
     depth: usize,
@@ -86,6 +89,38 @@ impl IndexMut<(usize, usize, usize)> for Vec3D {
     }
 }
 
+/// Written as `depth`, `rows` and `cols` as `u32`, followed by the elements.
+impl WriteTo for Vec3D {
+    fn write(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        flat_len(&[self.depth, self.rows, self.cols])?;
+        write_dim(self.depth, writer)?;
+        write_dim(self.rows, writer)?;
+        write_dim(self.cols, writer)?;
+
+        let len = self.depth * self.rows * self.cols;
+        let values = unsafe { std::slice::from_raw_parts(self.data.as_ptr(), len) };
+        serial::write_f32s(values, writer)
+    }
+}
+
+impl ReadFrom for Vec3D {
+    fn read(data: &mut impl std::io::Read) -> std::io::Result<Self> {
+        let depth = u32::read(data)? as usize;
+        let rows = u32::read(data)? as usize;
+        let cols = u32::read(data)? as usize;
+        let len = flat_len(&[depth, rows, cols])?;
+
+        let values = serial::read_f32s(data, len)?;
+        let this = Self::new(depth, rows, cols);
+        unsafe {
+            values
+                .as_ptr()
+                .copy_to_nonoverlapping(this.data.as_ptr(), len);
+        }
+        Ok(this)
+    }
+}
+
 impl Drop for Vec3D {
     fn drop(&mut self) {
         let size = self.depth * self.rows * self.cols;
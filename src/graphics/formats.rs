@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy)]
+use crate::serial::{ReadFrom, WriteTo};
+
+#[derive(Debug, Clone, Copy, ReadFrom, WriteTo)]
 pub struct RGB {
     pub r: u8,
     pub g: u8,
@@ -54,7 +56,7 @@ impl RGB {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ReadFrom, WriteTo)]
 pub struct RGBA {
     pub r: u8,
     pub g: u8,
@@ -232,7 +234,7 @@ macro_rules! hex_rgba {
     }};
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, ReadFrom, WriteTo)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -0,0 +1,91 @@
+use std::{error::Error, fmt};
+
+/// What is wrong with a JSON document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonErrorKind {
+    /// The document ended inside a value.
+    UnexpectedEnd,
+    /// A character that cannot appear at this point.
+    UnexpectedChar(char),
+    /// Something that starts like `true`, `false` or `null` but is not.
+    InvalidLiteral,
+    /// A number that does not follow the JSON grammar or is out of range for `f64`.
+    InvalidNumber,
+    /// An unknown escape sequence in a string.
+    InvalidEscape,
+    /// A `\u` escape that is not a valid unicode scalar value.
+    InvalidUnicode,
+    /// An unescaped control character in a string.
+    ControlCharacter,
+    /// An object contains the same key twice.
+    DuplicateKey(String),
+    /// Something other than whitespace follows the value.
+    TrailingCharacters,
+    /// Arrays and objects are nested too deep.
+    TooDeep,
+    /// The document is not valid UTF-8.
+    InvalidUtf8,
+}
+
+impl fmt::Display for JsonErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => f.write_str("unexpected end of input"),
+            Self::UnexpectedChar(char) => write!(f, "unexpected character {char:?}"),
+            Self::InvalidLiteral => f.write_str("invalid literal"),
+            Self::InvalidNumber => f.write_str("invalid number"),
+            Self::InvalidEscape => f.write_str("invalid escape sequence"),
+            Self::InvalidUnicode => f.write_str("invalid unicode escape"),
+            Self::ControlCharacter => f.write_str("control character in string"),
+            Self::DuplicateKey(key) => write!(f, "duplicate key {key:?}"),
+            Self::TrailingCharacters => f.write_str("trailing characters"),
+            Self::TooDeep => f.write_str("nesting too deep"),
+            Self::InvalidUtf8 => f.write_str("invalid UTF-8"),
+        }
+    }
+}
+
+/// An error while parsing JSON, with the 1-based line and column it occurred at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    kind: JsonErrorKind,
+    line: usize,
+    column: usize,
+}
+
+impl JsonError {
+    /// Creates an error at byte `pos` of `text`. Columns count characters.
+    pub(super) fn at(kind: JsonErrorKind, text: &str, pos: usize) -> Self {
+        let before = &text[..pos];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            kind,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    pub fn kind(&self) -> &JsonErrorKind {
+        &self.kind
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.line, self.column
+        )
+    }
+}
+
+impl Error for JsonError {}
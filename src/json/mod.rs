@@ -0,0 +1,293 @@
+mod error;
+mod parse;
+mod write;
+
+use std::{ops::Index, str::FromStr};
+
+pub use error::{JsonError, JsonErrorKind};
+
+/// A JSON document.
+///
+/// Objects keep their entries in the order they were parsed or inserted in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum JsonValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+static NULL: JsonValue = JsonValue::Null;
+
+impl JsonValue {
+    /// Parses a complete JSON document. Only whitespace may follow the value.
+    pub fn parse(text: &str) -> Result<Self, JsonError> {
+        parse::Parser::new(text).parse()
+    }
+
+    /// Parses a complete JSON document from UTF-8 bytes.
+    pub fn parse_bytes(data: &[u8]) -> Result<Self, JsonError> {
+        match std::str::from_utf8(data) {
+            Ok(text) => Self::parse(text),
+            Err(e) => {
+                let valid = std::str::from_utf8(&data[..e.valid_up_to()]).unwrap();
+                Err(JsonError::at(
+                    JsonErrorKind::InvalidUtf8,
+                    valid,
+                    valid.len(),
+                ))
+            }
+        }
+    }
+
+    /// Serializes the value with two spaces of indentation per level.
+    pub fn to_string_pretty(&self) -> String {
+        let mut out = String::new();
+        write::write_pretty(self, &mut out, 0);
+        out
+    }
+
+    /// The value of `key` if this is an object that contains it.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets `key` in an object, replacing an existing entry.
+    ///
+    /// # Panics
+    /// If this is not an object.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<JsonValue>) {
+        let Self::Object(entries) = self else {
+            panic!("insert called on a JSON value that is not an object");
+        };
+        let key = key.into();
+        let value = value.into();
+
+        match entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => *existing = value,
+            None => entries.push((key, value)),
+        }
+    }
+
+    pub const fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub const fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        self.as_f64().map(|value| value as f32)
+    }
+
+    /// The number as an integer, if it has no fractional part and fits into an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        let value = self.as_f64()?;
+        (value.fract() == 0.0 && value >= i64::MIN as f64 && value < i64::MAX as f64)
+            .then_some(value as i64)
+    }
+
+    /// The number as an integer, if it has no fractional part and fits into a `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        let value = self.as_f64()?;
+        (value.fract() == 0.0 && value >= 0.0 && value < u64::MAX as f64).then_some(value as u64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            Self::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for JsonValue {
+    type Err = JsonError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+/// Looks up a key of an object, giving `Null` if it is missing or this is not an object.
+impl Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, key: &str) -> &Self::Output {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Looks up an element of an array, giving `Null` if it is missing or this is not an array.
+impl Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.as_array()
+            .and_then(|items| items.get(index))
+            .unwrap_or(&NULL)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for JsonValue {
+            fn from(value: $ty) -> Self {
+                Self::Number(value as f64)
+            }
+        }
+    )*};
+}
+
+impl_from_number!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+impl From<bool> for JsonValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(value: Vec<T>) -> Self {
+        Self::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_document() {
+        let value = JsonValue::parse(
+            r#" {
+                "name": "ferris", "age": 8, "ratio": -1.5e-3,
+                "tags": ["a", "\u00e4\n\"", "\ud83e\udd80"],
+                "nested": {"ok": true, "none": null, "empty": []}
+            } "#,
+        )
+        .unwrap();
+
+        assert_eq!(value["name"].as_str(), Some("ferris"));
+        assert_eq!(value["age"].as_u64(), Some(8));
+        assert_eq!(value["ratio"].as_f64(), Some(-0.0015));
+        assert_eq!(value["tags"][1].as_str(), Some("ä\n\""));
+        assert_eq!(value["tags"][2].as_str(), Some("🦀"));
+        assert_eq!(value["nested"]["ok"].as_bool(), Some(true));
+        assert!(value["nested"]["none"].is_null());
+        assert!(value["missing"][3]["deep"].is_null());
+        assert_eq!(value["nested"]["empty"].as_array(), Some(&[][..]));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut value = JsonValue::Object(Vec::new());
+        value.insert("text", "tab\there \u{1} \"quoted\" \\");
+        value.insert("numbers", vec![0.0, 1.0, -2.5, 1e21, 0.1]);
+        value.insert("flag", false);
+        value.insert("nothing", JsonValue::Null);
+        value.insert("flag", true);
+
+        let compact = value.to_string();
+        assert_eq!(
+            compact,
+            r#"{"text":"tab\there \u0001 \"quoted\" \\","numbers":[0,1,-2.5,1e21,0.1],"flag":true,"nothing":null}"#
+        );
+        assert_eq!(JsonValue::parse(&compact).unwrap(), value);
+
+        let pretty = value.to_string_pretty();
+        assert!(pretty.starts_with("{\n  \"text\": \"tab\\there"));
+        assert!(pretty.contains("  \"numbers\": [\n    0,\n    1,"));
+        assert_eq!(JsonValue::parse(&pretty).unwrap(), value);
+        assert_eq!(JsonValue::Array(Vec::new()).to_string_pretty(), "[]");
+    }
+
+    #[test]
+    fn strict_errors() {
+        let error = |text: &str| JsonValue::parse(text).unwrap_err();
+
+        let e = error("{\n  \"a\": 1,\n  \"b\": tru\n}");
+        assert_eq!(
+            (e.kind(), e.line(), e.column()),
+            (&JsonErrorKind::InvalidLiteral, 3, 8)
+        );
+        assert_eq!(e.to_string(), "invalid literal at line 3, column 8");
+
+        assert_eq!(error("[1, 2,]").kind(), &JsonErrorKind::UnexpectedChar(']'));
+        assert_eq!(
+            error("{\"a\":1,}").kind(),
+            &JsonErrorKind::UnexpectedChar('}')
+        );
+        assert_eq!(error("01").kind(), &JsonErrorKind::InvalidNumber);
+        assert_eq!(error("1.").kind(), &JsonErrorKind::InvalidNumber);
+        assert_eq!(error("-").kind(), &JsonErrorKind::InvalidNumber);
+        assert_eq!(error("1e400").kind(), &JsonErrorKind::InvalidNumber);
+        assert_eq!(error("\"\\x\"").kind(), &JsonErrorKind::InvalidEscape);
+        assert_eq!(error("\"\\ud800\"").kind(), &JsonErrorKind::InvalidUnicode);
+        assert_eq!(error("\"a\tb\"").kind(), &JsonErrorKind::ControlCharacter);
+        assert_eq!(error("\"open").kind(), &JsonErrorKind::UnexpectedEnd);
+        assert_eq!(error("1 2").kind(), &JsonErrorKind::TrailingCharacters);
+        assert_eq!(error("").kind(), &JsonErrorKind::UnexpectedEnd);
+        assert_eq!(error("'a'").kind(), &JsonErrorKind::UnexpectedChar('\''));
+        assert_eq!(
+            error("{\"a\":1,\"a\":2}").kind(),
+            &JsonErrorKind::DuplicateKey("a".into())
+        );
+        assert_eq!(error(&"[".repeat(200)).kind(), &JsonErrorKind::TooDeep);
+
+        let e = JsonValue::parse_bytes(b"[\"ok\",\n\"\xff\"]").unwrap_err();
+        assert_eq!(
+            (e.kind(), e.line(), e.column()),
+            (&JsonErrorKind::InvalidUtf8, 2, 2)
+        );
+    }
+}
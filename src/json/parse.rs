@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+
+use super::{JsonError, JsonErrorKind, JsonValue};
+
+/// How deep arrays and objects can be nested, so parsing cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// A recursive descent parser for RFC 8259 JSON.
+pub(super) struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            bytes: text.as_bytes(),
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    pub fn parse(mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+        let value = self.value()?;
+        self.skip_whitespace();
+
+        if self.pos != self.bytes.len() {
+            return Err(self.error(JsonErrorKind::TrailingCharacters));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, kind: JsonErrorKind) -> JsonError {
+        self.error_at(kind, self.pos)
+    }
+
+    fn error_at(&self, kind: JsonErrorKind, pos: usize) -> JsonError {
+        JsonError::at(kind, self.text, pos)
+    }
+
+    /// An error for the character at the current position, or for the end of input.
+    fn unexpected(&self) -> JsonError {
+        match self.text[self.pos..].chars().next() {
+            Some(char) => self.error(JsonErrorKind::UnexpectedChar(char)),
+            None => self.error(JsonErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, JsonError> {
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(JsonValue::String),
+            Some(b't') => self.literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.literal("false", JsonValue::Bool(false)),
+            Some(b'n') => self.literal("null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.error(JsonErrorKind::InvalidLiteral));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn enter(&mut self) -> Result<(), JsonError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(JsonErrorKind::TooDeep));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        Ok(())
+    }
+
+    /// Consumes the `,` or the closing bracket after an element. Returns whether more
+    /// elements follow.
+    fn separator(&mut self, close: u8) -> Result<bool, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                Ok(true)
+            }
+            Some(byte) if byte == close => {
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(false)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, JsonError> {
+        self.enter()?;
+        let mut items = Vec::new();
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            self.depth -= 1;
+            return Ok(JsonValue::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            if !self.separator(b']')? {
+                return Ok(JsonValue::Array(items));
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, JsonError> {
+        self.enter()?;
+        let mut entries = Vec::new();
+        let mut keys = HashSet::new();
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            self.depth -= 1;
+            return Ok(JsonValue::Object(entries));
+        }
+
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.unexpected());
+            }
+            let key_pos = self.pos;
+            let key = self.string()?;
+            if !keys.insert(key.clone()) {
+                return Err(self.error_at(JsonErrorKind::DuplicateKey(key), key_pos));
+            }
+
+            self.skip_whitespace();
+            if self.peek() != Some(b':') {
+                return Err(self.unexpected());
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+
+            entries.push((key, self.value()?));
+            if !self.separator(b'}')? {
+                return Ok(JsonValue::Object(entries));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut out = String::new();
+
+        loop {
+            let start = self.pos;
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            out.push_str(&self.text[start..self.pos]);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => out.push(self.escape()?),
+                Some(_) => return Err(self.error(JsonErrorKind::ControlCharacter)),
+                None => return Err(self.error(JsonErrorKind::UnexpectedEnd)),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, JsonError> {
+        let start = self.pos;
+        self.pos += 2;
+
+        Ok(match self.bytes.get(start + 1) {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                let high = self.hex(start)?;
+                let code = match high {
+                    0xd800..=0xdbff => {
+                        if !self.text[self.pos..].starts_with("\\u") {
+                            return Err(self.error_at(JsonErrorKind::InvalidUnicode, start));
+                        }
+                        self.pos += 2;
+                        let low = self.hex(start)?;
+                        if !(0xdc00..=0xdfff).contains(&low) {
+                            return Err(self.error_at(JsonErrorKind::InvalidUnicode, start));
+                        }
+                        0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                    }
+                    code => code,
+                };
+                char::from_u32(code)
+                    .ok_or_else(|| self.error_at(JsonErrorKind::InvalidUnicode, start))?
+            }
+            Some(_) => return Err(self.error_at(JsonErrorKind::InvalidEscape, start)),
+            None => return Err(self.error_at(JsonErrorKind::UnexpectedEnd, start)),
+        })
+    }
+
+    /// Reads the four hex digits of a `\u` escape starting at `start`.
+    fn hex(&mut self, start: usize) -> Result<u32, JsonError> {
+        let digits = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error_at(JsonErrorKind::InvalidEscape, start))?;
+
+        let mut code = 0;
+        for &digit in digits {
+            let value = (digit as char)
+                .to_digit(16)
+                .ok_or_else(|| self.error_at(JsonErrorKind::InvalidEscape, start))?;
+            code = code * 16 + value;
+        }
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        let invalid = |this: &Self| this.error_at(JsonErrorKind::InvalidNumber, start);
+
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => {
+                self.pos += 1;
+                if let Some(b'0'..=b'9') = self.peek() {
+                    return Err(invalid(self));
+                }
+            }
+            Some(b'1'..=b'9') => {
+                self.digits();
+            }
+            _ => return Err(invalid(self)),
+        }
+
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if self.digits() == 0 {
+                return Err(invalid(self));
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(invalid(self));
+            }
+        }
+
+        match self.text[start..self.pos].parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(JsonValue::Number(value)),
+            _ => Err(invalid(self)),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::{self, Write};
+
+use super::JsonValue;
+
+/// Writes the value without any whitespace.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Number(value) => write_number(*value, f),
+            Self::String(value) => write_string(value, f),
+            Self::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(entries) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(key, f)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+pub(super) fn write_pretty(value: &JsonValue, out: &mut String, indent: usize) {
+    let newline = |out: &mut String, indent: usize| {
+        out.push('\n');
+        out.extend(std::iter::repeat_n("  ", indent));
+    };
+
+    match value {
+        JsonValue::Array(items) if !items.is_empty() => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+                newline(out, indent + 1);
+                write_pretty(item, out, indent + 1);
+            }
+            newline(out, indent);
+            out.push(']');
+        }
+        JsonValue::Object(entries) if !entries.is_empty() => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+                newline(out, indent + 1);
+                let _ = write_string(key, out);
+                out.push_str(": ");
+                write_pretty(value, out, indent + 1);
+            }
+            newline(out, indent);
+            out.push('}');
+        }
+        _ => {
+            let _ = write!(out, "{value}");
+        }
+    }
+}
+
+/// JSON has no representation for NaN and infinity, so they are written as `null`.
+fn write_number(value: f64, out: &mut impl Write) -> fmt::Result {
+    if !value.is_finite() {
+        return out.write_str("null");
+    }
+    if value.abs() >= 1e21 {
+        return write!(out, "{value:e}");
+    }
+    write!(out, "{value}")
+}
+
+fn write_string(value: &str, out: &mut impl Write) -> fmt::Result {
+    out.write_char('"')?;
+    for char in value.chars() {
+        match char {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{8}' => out.write_str("\\b")?,
+            '\u{c}' => out.write_str("\\f")?,
+            char if (char as u32) < 0x20 => write!(out, "\\u{:04x}", char as u32)?,
+            char => out.write_char(char)?,
+        }
+    }
+    out.write_char('"')
+}
@@ -1,4 +1,7 @@
+extern crate self as iron_oxide;
+
 pub mod collections;
+pub mod json;
 pub mod net;
 pub mod physics;
 pub mod physics2d;
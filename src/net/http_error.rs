@@ -0,0 +1,93 @@
+use std::{error::Error, fmt, io};
+
+/// An error while reading an HTTP request.
+#[derive(Debug)]
+pub enum HttpError {
+    /// The connection closed before the first byte of a request.
+    ConnectionClosed,
+    /// The connection closed in the middle of a request.
+    UnexpectedEof,
+    Io(io::Error),
+    InvalidRequestLine,
+    InvalidMethod,
+    InvalidTarget,
+    InvalidVersion,
+    /// A version other than HTTP/1.0 and HTTP/1.1.
+    UnsupportedVersion,
+    InvalidHeader,
+    /// An HTTP/1.1 request without a `Host` header.
+    MissingHost,
+    InvalidPercentEncoding,
+    InvalidContentLength,
+    InvalidChunk,
+    /// A transfer coding other than `chunked`.
+    UnsupportedTransferEncoding,
+    /// A line of the request is longer than allowed.
+    LineTooLong,
+    /// The header section is larger than allowed.
+    HeadersTooLarge,
+    /// The body is larger than allowed.
+    BodyTooLarge,
+    /// The request was started but not sent in time.
+    Timeout,
+}
+
+impl HttpError {
+    /// The status code to answer the request with.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::ConnectionClosed | Self::UnexpectedEof | Self::Io(_) => 400,
+            Self::UnsupportedVersion => 505,
+            Self::UnsupportedTransferEncoding => 501,
+            Self::LineTooLong => 414,
+            Self::HeadersTooLarge => 431,
+            Self::BodyTooLarge => 413,
+            Self::Timeout => 408,
+            _ => 400,
+        }
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ConnectionClosed => "connection closed",
+            Self::UnexpectedEof => "connection closed in the middle of a request",
+            Self::Io(e) => return write!(f, "io error: {e}"),
+            Self::InvalidRequestLine => "invalid request line",
+            Self::InvalidMethod => "invalid method",
+            Self::InvalidTarget => "invalid request target",
+            Self::InvalidVersion => "invalid HTTP version",
+            Self::UnsupportedVersion => "unsupported HTTP version",
+            Self::InvalidHeader => "invalid header field",
+            Self::MissingHost => "missing Host header",
+            Self::InvalidPercentEncoding => "invalid percent-encoding",
+            Self::InvalidContentLength => "invalid Content-Length",
+            Self::InvalidChunk => "invalid chunked encoding",
+            Self::UnsupportedTransferEncoding => "unsupported transfer encoding",
+            Self::LineTooLong => "line too long",
+            Self::HeadersTooLarge => "header section too large",
+            Self::BodyTooLarge => "body too large",
+            Self::Timeout => "request timeout",
+        })
+    }
+}
+
+impl Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HttpError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            Self::UnexpectedEof
+        } else {
+            Self::Io(error)
+        }
+    }
+}
@@ -1,48 +1,709 @@
-#[allow(unused)]
+use std::{fmt, io::BufRead};
+
+use super::{Headers, HttpError};
+
+/// How many empty lines may precede the request line.
+const MAX_EMPTY_LINES: usize = 8;
+
+/// The method of a request. Extension methods are kept as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+    GET,
+    HEAD,
+    POST,
+    PUT,
+    DELETE,
+    CONNECT,
+    OPTIONS,
+    TRACE,
+    PATCH,
+    Other(String),
+}
+
+impl Method {
+    fn parse(token: &str) -> Result<Self, HttpError> {
+        Ok(match token {
+            "GET" => Self::GET,
+            "HEAD" => Self::HEAD,
+            "POST" => Self::POST,
+            "PUT" => Self::PUT,
+            "DELETE" => Self::DELETE,
+            "CONNECT" => Self::CONNECT,
+            "OPTIONS" => Self::OPTIONS,
+            "TRACE" => Self::TRACE,
+            "PATCH" => Self::PATCH,
+            _ if is_token(token) => Self::Other(token.to_string()),
+            _ => return Err(HttpError::InvalidMethod),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::GET => "GET",
+            Self::HEAD => "HEAD",
+            Self::POST => "POST",
+            Self::PUT => "PUT",
+            Self::DELETE => "DELETE",
+            Self::CONNECT => "CONNECT",
+            Self::OPTIONS => "OPTIONS",
+            Self::TRACE => "TRACE",
+            Self::PATCH => "PATCH",
+            Self::Other(method) => method,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Version {
+    Http10,
+    Http11,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Http10 => "HTTP/1.0",
+            Self::Http11 => "HTTP/1.1",
+        })
+    }
+}
+
+/// Size limits for reading a request.
+#[derive(Debug, Clone)]
+pub struct HttpLimits {
+    /// The longest request line or chunk size line.
+    pub max_line: usize,
+    /// The largest header section, including trailers of a chunked body.
+    pub max_header_bytes: usize,
+    pub max_headers: usize,
+    pub max_body: usize,
+}
+
+impl Default for HttpLimits {
+    fn default() -> Self {
+        Self {
+            max_line: 8 * 1024,
+            max_header_bytes: 64 * 1024,
+            max_headers: 100,
+            max_body: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// An HTTP/1.x request.
+#[derive(Debug, Clone)]
 pub struct HTTPRequest {
-    pub request: RequestType,
+    pub method: Method,
+    /// The request target as it was sent.
+    pub target: String,
+    /// The percent-decoded path of the target.
+    pub path: String,
+    /// The non-empty segments of the path, each decoded on its own, so an encoded `/` does not
+    /// split a segment.
+    pub segments: Vec<String>,
+    /// The percent-decoded query parameters in the order they were sent.
+    pub query: Vec<(String, String)>,
+    pub version: Version,
+    pub headers: Headers,
+    /// The host from an absolute target or the `Host` header.
     pub host: Option<String>,
+    pub body: Vec<u8>,
 }
 
 impl HTTPRequest {
-    pub fn parse(buf: &[u8]) -> Option<Self> {
-        let text_form = String::from_utf8_lossy(buf);
-
-        let mut request = RequestType::GET(String::with_capacity(0), None);
-
-        for line in text_form.lines() {
-            if let Some(stripped) = line.strip_prefix("GET ") {
-                let req = stripped
-                    .split_whitespace()
-                    .find(|x| x.starts_with("/"))
-                    .unwrap_or("")
-                    .replace("%20", " ");
-                let req = req.split_once("?").unwrap_or((&req, ""));
-                let path = req.0.to_string();
-
-                let query = if req.1.is_empty() {
-                    None
-                } else {
-                    Some(req.1.to_string())
-                };
-
-                request = RequestType::GET(path, query);
-            } else if let Some(stripped) = line.strip_prefix("Sec-WebSocket-Key") {
-                request = RequestType::UpgradeWs(stripped.to_string());
-                break;
+    /// Parses a complete request from `buf` with the default limits.
+    pub fn parse(buf: &[u8]) -> Result<Self, HttpError> {
+        Self::read(&mut &buf[..], &HttpLimits::default())
+    }
+
+    /// Reads one request, including its body, from a stream.
+    ///
+    /// Returns [`HttpError::ConnectionClosed`] if the stream ends before the request starts, so
+    /// a keep-alive connection can be closed quietly.
+    pub fn read(reader: &mut impl BufRead, limits: &HttpLimits) -> Result<Self, HttpError> {
+        let mut request = Self::read_head(reader, limits)?;
+        request.body = read_body(reader, &request.headers, limits)?;
+        Ok(request)
+    }
+
+    /// Reads the request line and the headers, but leaves the body in the stream.
+    pub(super) fn read_head(
+        reader: &mut impl BufRead,
+        limits: &HttpLimits,
+    ) -> Result<Self, HttpError> {
+        // A few empty lines before the request line are ignored, see RFC 9112 section 2.2.
+        let mut empty_lines = 0;
+        let line = loop {
+            match read_line(reader, limits.max_line)? {
+                None => return Err(HttpError::ConnectionClosed),
+                Some(line) if line.is_empty() && empty_lines < MAX_EMPTY_LINES => {
+                    empty_lines += 1;
+                }
+                Some(line) if line.is_empty() => return Err(HttpError::InvalidRequestLine),
+                Some(line) => break line,
             }
+        };
+        let line = String::from_utf8(line).map_err(|_| HttpError::InvalidRequestLine)?;
+
+        let mut parts = line.split(' ');
+        let (Some(method), Some(target), Some(version), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(HttpError::InvalidRequestLine);
+        };
+
+        let method = Method::parse(method)?;
+        let version = parse_version(version)?;
+        let Target {
+            path,
+            segments,
+            query,
+            mut host,
+        } = parse_target(&method, target)?;
+
+        let headers = read_headers(reader, limits)?;
+        match headers.get_all("host").collect::<Vec<_>>()[..] {
+            [] if version == Version::Http11 && host.is_none() => {
+                return Err(HttpError::MissingHost);
+            }
+            [] => {}
+            [header] => {
+                host.get_or_insert_with(|| header.to_string());
+            }
+            _ => return Err(HttpError::InvalidHeader),
         }
 
-        Some(Self {
-            request,
-            host: None,
+        Ok(Self {
+            method,
+            target: target.to_string(),
+            path,
+            segments,
+            query,
+            version,
+            headers,
+            host,
+            body: Vec::new(),
         })
     }
+
+    /// The first value of the header `name`.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// The first value of the query parameter `key`.
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the connection stays open after the response.
+    pub fn keep_alive(&self) -> bool {
+        match self.version {
+            Version::Http11 => !self.headers.has_token("connection", "close"),
+            Version::Http10 => self.headers.has_token("connection", "keep-alive"),
+        }
+    }
+
+    /// The `Sec-WebSocket-Key` if this is a WebSocket upgrade request.
+    pub fn websocket_key(&self) -> Option<&str> {
+        let upgrade = self.method == Method::GET
+            && self.headers.has_token("upgrade", "websocket")
+            && self.headers.has_token("connection", "upgrade");
+        upgrade.then(|| self.header("sec-websocket-key")).flatten()
+    }
 }
 
-#[allow(unused)]
-pub enum RequestType {
-    GET(String, Option<String>),
-    POST,
-    UpgradeWs(String),
+pub(super) fn is_token(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+/// Reads a line ending in LF or CRLF without the line ending. Returns `None` if the stream
+/// ends before the first byte.
+fn read_line(reader: &mut impl BufRead, max: usize) -> Result<Option<Vec<u8>>, HttpError> {
+    let mut line = Vec::new();
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return if line.is_empty() {
+                Ok(None)
+            } else {
+                Err(HttpError::UnexpectedEof)
+            };
+        }
+
+        let (used, done) = match buf.iter().position(|&byte| byte == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (buf.len(), false),
+        };
+        if line.len() + used > max + 2 {
+            return Err(HttpError::LineTooLong);
+        }
+        line.extend_from_slice(&buf[..used]);
+        reader.consume(used);
+
+        if done {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            return Ok(Some(line));
+        }
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, HttpError> {
+    match version {
+        "HTTP/1.1" => Ok(Version::Http11),
+        "HTTP/1.0" => Ok(Version::Http10),
+        _ => match version.strip_prefix("HTTP/").map(str::as_bytes) {
+            Some([major, b'.', minor]) if major.is_ascii_digit() && minor.is_ascii_digit() => {
+                Err(HttpError::UnsupportedVersion)
+            }
+            _ => Err(HttpError::InvalidVersion),
+        },
+    }
+}
+
+/// The parts of a request target.
+#[derive(Default)]
+struct Target {
+    path: String,
+    segments: Vec<String>,
+    query: Vec<(String, String)>,
+    /// The host of an absolute target.
+    host: Option<String>,
+}
+
+/// Splits a request target into the decoded path, its segments, the query and the host.
+fn parse_target(method: &Method, target: &str) -> Result<Target, HttpError> {
+    if target.is_empty()
+        || target
+            .bytes()
+            .any(|byte| byte <= b' ' || byte == 0x7f || byte == b'#')
+    {
+        return Err(HttpError::InvalidTarget);
+    }
+
+    if *method == Method::CONNECT {
+        return Ok(Target {
+            host: Some(target.to_string()),
+            ..Target::default()
+        });
+    }
+    if target == "*" && *method == Method::OPTIONS {
+        return Ok(Target {
+            path: "*".to_string(),
+            ..Target::default()
+        });
+    }
+
+    let (host, origin) = if target.starts_with('/') {
+        (None, target)
+    } else {
+        let (scheme, rest) = target.split_once("://").ok_or(HttpError::InvalidTarget)?;
+        if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+            return Err(HttpError::InvalidTarget);
+        }
+        let end = rest.find(['/', '?']).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(HttpError::InvalidTarget);
+        }
+        (Some(rest[..end].to_string()), &rest[end..])
+    };
+
+    let (path, query) = origin.split_once('?').unwrap_or((origin, ""));
+    let path = if path.is_empty() { "/" } else { path };
+    // Splitting before decoding keeps `%2F` inside its segment.
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect::<Result<_, _>>()?;
+    Ok(Target {
+        path: percent_decode(path)?,
+        segments,
+        query: parse_query(query)?,
+        host,
+    })
+}
+
+fn read_headers(reader: &mut impl BufRead, limits: &HttpLimits) -> Result<Headers, HttpError> {
+    let mut headers = Headers::new();
+    let mut size = 0;
+
+    loop {
+        let line = read_line(reader, limits.max_header_bytes - size)
+            .map_err(|e| match e {
+                HttpError::LineTooLong => HttpError::HeadersTooLarge,
+                e => e,
+            })?
+            .ok_or(HttpError::UnexpectedEof)?;
+        if line.is_empty() {
+            return Ok(headers);
+        }
+
+        size += line.len() + 2;
+        if size > limits.max_header_bytes || headers.len() == limits.max_headers {
+            return Err(HttpError::HeadersTooLarge);
+        }
+
+        let line = String::from_utf8(line).map_err(|_| HttpError::InvalidHeader)?;
+        let (name, value) = line.split_once(':').ok_or(HttpError::InvalidHeader)?;
+        let value = value.trim_matches([' ', '\t']);
+
+        // Also rejects whitespace before the colon and obsolete line folding.
+        if !is_token(name) || value.chars().any(|c| c.is_control() && c != '\t') {
+            return Err(HttpError::InvalidHeader);
+        }
+        headers.append(name, value);
+    }
+}
+
+pub(super) fn read_body(
+    reader: &mut impl BufRead,
+    headers: &Headers,
+    limits: &HttpLimits,
+) -> Result<Vec<u8>, HttpError> {
+    if headers.contains("transfer-encoding") {
+        // A request with both framing headers is a request smuggling attempt, RFC 9112 6.1.
+        if headers.contains("content-length") {
+            return Err(HttpError::InvalidContentLength);
+        }
+        let mut codings = headers
+            .get_all("transfer-encoding")
+            .flat_map(|value| value.split(','))
+            .map(str::trim);
+        return match (codings.next(), codings.next()) {
+            (Some(coding), None) if coding.eq_ignore_ascii_case("chunked") => {
+                read_chunked(reader, limits)
+            }
+            _ => Err(HttpError::UnsupportedTransferEncoding),
+        };
+    }
+
+    let mut len = None;
+    for value in headers
+        .get_all("content-length")
+        .flat_map(|value| value.split(','))
+    {
+        let value = value.trim();
+        if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(HttpError::InvalidContentLength);
+        }
+        let value: u64 = value.parse().map_err(|_| HttpError::InvalidContentLength)?;
+        if len.is_some_and(|len| len != value) {
+            return Err(HttpError::InvalidContentLength);
+        }
+        len = Some(value);
+    }
+
+    let Some(len) = len else {
+        return Ok(Vec::new());
+    };
+    if len > limits.max_body as u64 {
+        return Err(HttpError::BodyTooLarge);
+    }
+
+    let mut body = vec![0; len as usize];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn read_chunked(reader: &mut impl BufRead, limits: &HttpLimits) -> Result<Vec<u8>, HttpError> {
+    let mut body = Vec::new();
+
+    loop {
+        let line = read_line(reader, limits.max_line)?.ok_or(HttpError::UnexpectedEof)?;
+        let size = line
+            .split(|&byte| byte == b';')
+            .next()
+            .unwrap_or_default()
+            .trim_ascii();
+        if size.is_empty() || size.len() > 16 || !size.iter().all(u8::is_ascii_hexdigit) {
+            return Err(HttpError::InvalidChunk);
+        }
+        let size = u64::from_str_radix(std::str::from_utf8(size).unwrap(), 16)
+            .map_err(|_| HttpError::InvalidChunk)?;
+
+        if size == 0 {
+            // The trailer fields are read to find the end of the request, but not kept.
+            read_headers(reader, limits)?;
+            return Ok(body);
+        }
+        if body.len() as u64 + size > limits.max_body as u64 {
+            return Err(HttpError::BodyTooLarge);
+        }
+
+        let start = body.len();
+        body.resize(start + size as usize, 0);
+        reader.read_exact(&mut body[start..])?;
+
+        let mut end = [0; 2];
+        reader.read_exact(&mut end)?;
+        if end != *b"\r\n" {
+            return Err(HttpError::InvalidChunk);
+        }
+    }
+}
+
+/// Decodes `%XX` escapes. The decoded bytes have to be valid UTF-8.
+pub fn percent_decode(input: &str) -> Result<String, HttpError> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+                .ok_or(HttpError::InvalidPercentEncoding)?;
+            out.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out).map_err(|_| HttpError::InvalidPercentEncoding)
+}
+
+/// Escapes every byte except unreserved characters and `/` as `%XX`.
+pub fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// Parses an `application/x-www-form-urlencoded` query into decoded key/value pairs.
+pub fn parse_query(query: &str) -> Result<Vec<(String, String)>, HttpError> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((
+                percent_decode(&key.replace('+', " "))?,
+                percent_decode(&value.replace('+', " "))?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_request() {
+        let request = HTTPRequest::parse(
+            b"\r\nPOST /caf%C3%A9/a%20b?x=1&name=J%C3%BCrgen+M&flag&x=2 HTTP/1.1\r\n\
+              Host: example.com\r\n\
+              content-type: text/plain \r\n\
+              Content-Length: 5\r\n\
+              \r\n\
+              hello",
+        )
+        .unwrap();
+
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.path, "/café/a b");
+        assert_eq!(request.segments, ["café", "a b"]);
+        assert_eq!(request.query("name"), Some("Jürgen M"));
+        assert_eq!(request.query("x"), Some("1"));
+        assert_eq!(request.query("flag"), Some(""));
+        assert_eq!(request.query.len(), 4);
+        assert_eq!(request.version, Version::Http11);
+        assert_eq!(request.host.as_deref(), Some("example.com"));
+        assert_eq!(request.header("Content-Type"), Some("text/plain"));
+        assert_eq!(request.body, b"hello");
+        assert!(request.keep_alive());
+        assert_eq!(request.websocket_key(), None);
+    }
+
+    #[test]
+    fn parses_chunked_and_pipelined() {
+        let data = b"PUT http://host:8080/upload HTTP/1.1\r\n\
+                     Transfer-Encoding: chunked\r\n\
+                     \r\n\
+                     4;ext=1\r\nWiki\r\n5\r\npedia\r\n0\r\nTrailer: x\r\n\r\n\
+                     PATCH /next HTTP/1.0\r\nConnection: keep-alive\r\n\r\n";
+        let mut reader = &data[..];
+        let limits = HttpLimits::default();
+
+        let first = HTTPRequest::read(&mut reader, &limits).unwrap();
+        assert_eq!(first.method, Method::PUT);
+        assert_eq!(first.host.as_deref(), Some("host:8080"));
+        assert_eq!(first.path, "/upload");
+        assert_eq!(first.body, b"Wikipedia");
+
+        let second = HTTPRequest::read(&mut reader, &limits).unwrap();
+        assert_eq!(second.method, Method::PATCH);
+        assert_eq!(second.version, Version::Http10);
+        assert!(second.keep_alive());
+        assert!(matches!(
+            HTTPRequest::read(&mut reader, &limits),
+            Err(HttpError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn websocket_upgrade() {
+        let request = HTTPRequest::parse(
+            b"GET /chat HTTP/1.1\r\nHost: a\r\nUpgrade: websocket\r\n\
+              Connection: keep-alive, Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(request.websocket_key(), Some("dGhlIHNhbXBsZSBub25jZQ=="));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let error = |data: &[u8]| HTTPRequest::parse(data).unwrap_err();
+
+        assert!(matches!(error(b""), HttpError::ConnectionClosed));
+        assert!(matches!(
+            error(
+                &[
+                    b"\r\n".repeat(9),
+                    b"GET / HTTP/1.1\r\nHost: a\r\n\r\n".to_vec()
+                ]
+                .concat()
+            ),
+            HttpError::InvalidRequestLine
+        ));
+        assert!(matches!(
+            error(b"GET / HTTP/1.1\r\nHost: a\r\n"),
+            HttpError::UnexpectedEof
+        ));
+        assert!(matches!(
+            error(b"GET /  HTTP/1.1\r\n\r\n"),
+            HttpError::InvalidRequestLine
+        ));
+        assert!(matches!(
+            error(b"G(T / HTTP/1.1\r\n\r\n"),
+            HttpError::InvalidMethod
+        ));
+        assert!(matches!(
+            error(b"GET x HTTP/1.1\r\n\r\n"),
+            HttpError::InvalidTarget
+        ));
+        assert!(matches!(
+            error(b"GET / HTTP/2.0\r\n\r\n"),
+            HttpError::UnsupportedVersion
+        ));
+        assert!(matches!(
+            error(b"GET / HTTQ/1.1\r\n\r\n"),
+            HttpError::InvalidVersion
+        ));
+        assert!(matches!(
+            error(b"GET / HTTP/1.1\r\n\r\n"),
+            HttpError::MissingHost
+        ));
+        assert!(matches!(
+            error(b"GET /%zz HTTP/1.0\r\n\r\n"),
+            HttpError::InvalidPercentEncoding
+        ));
+        assert!(matches!(
+            error(b"GET /%ff HTTP/1.0\r\n\r\n"),
+            HttpError::InvalidPercentEncoding
+        ));
+
+        let headers = |headers: &str| error(format!("GET / HTTP/1.0\r\n{headers}\r\n").as_bytes());
+        assert!(matches!(
+            headers("Bad Name: x\r\n"),
+            HttpError::InvalidHeader
+        ));
+        assert!(matches!(headers("Name : x\r\n"), HttpError::InvalidHeader));
+        assert!(matches!(
+            headers("A: x\r\n folded\r\n"),
+            HttpError::InvalidHeader
+        ));
+        assert!(matches!(
+            headers("Host: a\r\nHost: b\r\n"),
+            HttpError::InvalidHeader
+        ));
+        assert!(matches!(
+            headers("Content-Length: -1\r\n"),
+            HttpError::InvalidContentLength
+        ));
+        assert!(matches!(
+            headers("Content-Length: 1\r\nContent-Length: 2\r\n"),
+            HttpError::InvalidContentLength
+        ));
+        assert!(matches!(
+            headers("Content-Length: 1\r\nTransfer-Encoding: chunked\r\n"),
+            HttpError::InvalidContentLength
+        ));
+        assert!(matches!(
+            headers("Transfer-Encoding: gzip, chunked\r\n"),
+            HttpError::UnsupportedTransferEncoding
+        ));
+        assert!(matches!(
+            headers("Content-Length: 3\r\n"),
+            HttpError::UnexpectedEof
+        ));
+        assert!(matches!(
+            error(b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\nz\r\n"),
+            HttpError::InvalidChunk
+        ));
+        assert!(matches!(
+            error(b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nab\r\n0\r\n\r\n"),
+            HttpError::InvalidChunk
+        ));
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let limits = HttpLimits {
+            max_line: 16,
+            max_header_bytes: 32,
+            max_headers: 2,
+            max_body: 4,
+        };
+        let read = |data: &[u8]| HTTPRequest::read(&mut &data[..], &limits).unwrap_err();
+
+        assert!(matches!(
+            read(b"GET /a-very-long-path HTTP/1.0\r\n\r\n"),
+            HttpError::LineTooLong
+        ));
+        assert!(matches!(
+            read(b"GET / HTTP/1.0\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n"),
+            HttpError::HeadersTooLarge
+        ));
+        assert!(matches!(
+            read(b"GET / HTTP/1.0\r\nA: 0123456789012345678901234567890\r\n\r\n"),
+            HttpError::HeadersTooLarge
+        ));
+        assert!(matches!(
+            read(b"GET / HTTP/1.0\r\nContent-Length: 5\r\n\r\nhello"),
+            HttpError::BodyTooLarge
+        ));
+        assert!(matches!(
+            read(b"GET / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n"),
+            HttpError::BodyTooLarge
+        ));
+        assert_eq!(HttpError::BodyTooLarge.status(), 413);
+    }
 }
@@ -1,7 +1,13 @@
-use std::{fs, io::Cursor, path::PathBuf};
+use std::{
+    fs,
+    io::{Cursor, ErrorKind},
+    path::PathBuf,
+};
 use zip::ZipWriter;
 use zip::write::FileOptions;
 
+use super::{MimeTypes, Response};
+
 pub struct HTTPS {
     pub http_verion: Option<String>,
     pub host: Option<String>,
@@ -9,71 +15,52 @@ pub struct HTTPS {
 }
 
 impl HTTPS {
+    /// Formats a `200 OK` response. Text content types are sent as UTF-8.
     pub fn format(content_type: &[u8], content: &[u8]) -> Vec<u8> {
-        let content_len = content.len();
-        let mut format = Vec::with_capacity(content_len + 100);
-        format.extend_from_slice(b"HTTP/1.1 200 OK\r\n");
-        format.extend_from_slice(b"Content-Type: ");
-        format.extend_from_slice(content_type);
-        format.extend_from_slice(b"\r\ncharset=UTF-8\r\nContent-Length: ");
-        format.extend_from_slice(content_len.to_string().as_bytes());
-        format.extend_from_slice(b"\r\n\r\n");
-        format.extend_from_slice(content);
-        format
+        let content_type = String::from_utf8_lossy(content_type);
+        let content_type = if content_type.starts_with("text/") && !content_type.contains(';') {
+            format!("{content_type}; charset=UTF-8")
+        } else {
+            content_type.into_owned()
+        };
+
+        let response = Response::ok().content_type(&content_type).body(content);
+        response.to_bytes().unwrap()
     }
 
-    pub fn format_content(path: PathBuf) -> Option<Vec<u8>> {
+    /// Answers with the file at `path`, or a zip archive if it is a directory.
+    pub fn format_content(path: PathBuf) -> Response {
         if path.is_dir() {
-            match Self::zip_directory(&path) {
-                Ok(zip_data) => {
-                    let zip_filename = format!("{}.zip", path.file_name()?.to_string_lossy());
-                    let headers = format!(
-                        "HTTP/1.1 200 OK\r\n\
-                        Content-Type: application/zip\r\n\
-                        Content-Disposition: attachment; filename=\"{}\"\r\n\
-                        Content-Length: {}\r\n\r\n",
-                        zip_filename,
-                        zip_data.len()
-                    );
-
-                    let mut response = headers.into_bytes();
-                    response.extend_from_slice(&zip_data);
-                    return Some(response);
-                }
-                Err(_) => return None,
-            }
+            let Some(name) = path.file_name() else {
+                return Response::not_found();
+            };
+            return match Self::zip_directory(&path) {
+                Ok(zip_data) => Response::ok()
+                    .content_type("application/zip")
+                    .header(
+                        "Content-Disposition",
+                        format!("attachment; filename=\"{}.zip\"", name.to_string_lossy()),
+                    )
+                    .body(zip_data),
+                Err(_) => Response::internal_error(),
+            };
         }
 
-        let file_content = fs::read(&path);
-        match file_content {
-            Ok(content) => {
-                let content_type: &[u8] = match path.extension() {
-                    Some(extention) => match extention.as_encoded_bytes() {
-                        b"apng" => b"image/apng",
-                        b"png" => b"image/png",
-                        b"webp" => b"image/webp",
-                        b"gif" => b"image/gif",
-                        b"jpeg" => b"image/jpeg",
-                        b"svh" => b"image/svg+xml",
-                        b"avif" => b"image/avif",
-                        b"zip" => b"application/zip",
-                        b"json" => b"text/json",
-                        b"js" => b"text/javascript",
-                        b"wasm" => b"application/wasm",
-                        b"html" => b"text/html",
-                        b"pdf" => b"application/pdf",
-                        b"mp3" => b"audio/mpeg",
-                        b"mp4" => b"audio/mp4",
-                        b"ogg" => b"audio/ogg",
-                        b"wav" => b"audio/wav",
-                        b"ico" => b"image/vnd.microsoft.icon",
-                        _ => b"text/plain",
-                    },
-                    None => b"text/html",
+        let file = fs::File::open(&path).and_then(|file| Ok((file.metadata()?.len(), file)));
+        match file {
+            Ok((len, file)) => {
+                let content_type = match path.extension() {
+                    Some(extension) => MimeTypes::default()
+                        .get(&extension.to_string_lossy())
+                        .unwrap_or("text/plain")
+                        .to_string(),
+                    None => "text/html".to_string(),
                 };
-                Some(HTTPS::format(content_type, &content))
+                Response::ok().content_type(&content_type).file(file, len)
             }
-            Err(_) => None,
+            Err(e) if e.kind() == ErrorKind::NotFound => Response::not_found(),
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => Response::new(403),
+            Err(_) => Response::internal_error(),
         }
     }
 
@@ -1,14 +1,32 @@
 #![cfg(feature = "net")]
+mod headers;
+mod http_error;
 mod http_request;
 mod https;
+mod response;
+mod router;
+mod server;
+mod static_files;
 mod web_socket;
 
-pub use http_request::HTTPRequest;
-pub use http_request::RequestType;
+pub use headers::Headers;
+pub use http_error::HttpError;
+pub use http_request::{
+    HTTPRequest, HttpLimits, Method, Version, parse_query, percent_decode, percent_encode,
+};
 pub use https::HTTPS;
+pub use response::{Body, Cookie, Response, SameSite, reason_phrase};
+pub use router::{Handler, Params, Router};
+pub use server::{HttpServer, ServerHandle};
+pub use static_files::{MimeTypes, StaticFiles};
 pub use web_socket::MessageDataType;
 pub use web_socket::WebSocket;
 pub use web_socket::WebSocketInterface;
+pub use web_socket::{
+    CloseCode, DeflateConfig, Frame, FrameReader, MAX_CONTROL_PAYLOAD, OpCode, WebSocketError,
+    WebSocketLimits, WebSocketStats, WebSocketTimeouts,
+};
+pub use web_socket::{ConnectionId, Message, WebSocketHandler, WebSocketHub, WebSocketPoller};
 
 mod tests {
 
@@ -0,0 +1,571 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    fs::File,
+    io::{self, Read, Write},
+};
+
+use super::{Headers, HttpError, Version, http_request::is_token};
+use crate::json::JsonValue;
+
+/// The body of a [`Response`].
+pub enum Body {
+    Empty,
+    Bytes(Vec<u8>),
+    /// `len` bytes read from the current position of the file.
+    File {
+        file: File,
+        len: u64,
+    },
+    /// Chunks of unknown total length, sent with chunked transfer encoding, or delimited by
+    /// closing the connection for HTTP/1.0 clients.
+    Stream(Box<dyn Iterator<Item = io::Result<Vec<u8>>> + Send>),
+}
+
+impl Body {
+    /// The length of the body, unless it is streamed.
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Self::Empty => Some(0),
+            Self::Bytes(bytes) => Some(bytes.len() as u64),
+            Self::File { len, .. } => Some(*len),
+            Self::Stream(_) => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("Empty"),
+            Self::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            Self::File { len, .. } => write!(f, "File({len} bytes)"),
+            Self::Stream(_) => f.write_str("Stream"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// A cookie set with a `Set-Cookie` header.
+///
+/// Control characters and `;` are removed from the value and the attributes, so they cannot
+/// end the header or add attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<i64>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// # Panics
+    /// If `name` is not a token.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        assert!(is_token(&name), "invalid cookie name {name:?}");
+        Self {
+            name,
+            value: cookie_text(value.into()),
+            path: None,
+            domain: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(cookie_text(path.into()));
+        self
+    }
+
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(cookie_text(domain.into()));
+        self
+    }
+
+    /// Seconds until the cookie expires. Zero or less deletes it.
+    pub fn max_age(mut self, seconds: i64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+}
+
+/// Formats the cookie as the value of a `Set-Cookie` header.
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={max_age}")?;
+        }
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={same_site:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes control characters but tabs, which could end a header line.
+fn strip_controls(mut text: String) -> String {
+    text.retain(|c| c == '\t' || !c.is_ascii_control());
+    text
+}
+
+fn cookie_text(text: String) -> String {
+    let mut text = strip_controls(text);
+    text.retain(|c| c != ';');
+    text
+}
+
+/// The reason phrase registered for a status code.
+pub fn reason_phrase(status: u16) -> Option<&'static str> {
+    Some(match status {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Content Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        505 => "HTTP Version Not Supported",
+        _ => return None,
+    })
+}
+
+/// An HTTP/1.1 response.
+///
+/// `Content-Length` or `Transfer-Encoding` is set from the body when the response is written,
+/// replacing values set by hand.
+#[derive(Debug)]
+pub struct Response {
+    status: u16,
+    reason: Cow<'static, str>,
+    headers: Headers,
+    body: Body,
+    omit_body: bool,
+    version: Version,
+}
+
+impl Response {
+    /// A response with the registered reason phrase of `status`.
+    ///
+    /// # Panics
+    /// If `status` is not a three digit number.
+    pub fn new(status: u16) -> Self {
+        assert!(
+            (100..=999).contains(&status),
+            "invalid status code {status}"
+        );
+        Self {
+            status,
+            reason: Cow::Borrowed(reason_phrase(status).unwrap_or("")),
+            headers: Headers::new(),
+            body: Body::Empty,
+            omit_body: false,
+            version: Version::Http11,
+        }
+    }
+
+    pub fn ok() -> Self {
+        Self::new(200)
+    }
+
+    pub fn moved_permanently(location: &str) -> Self {
+        Self::new(301).header("Location", location)
+    }
+
+    pub fn not_modified() -> Self {
+        Self::new(304)
+    }
+
+    pub fn bad_request(message: &str) -> Self {
+        Self::new(400).text(message)
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(404).text("Not Found")
+    }
+
+    pub fn internal_error() -> Self {
+        Self::new(500).text("Internal Server Error")
+    }
+
+    /// Replaces the reason phrase. Control characters are removed.
+    pub fn reason(mut self, reason: impl Into<Cow<'static, str>>) -> Self {
+        let reason = reason.into();
+        self.reason = if reason.contains(|c: char| c.is_ascii_control()) {
+            Cow::Owned(strip_controls(reason.into_owned()))
+        } else {
+            reason
+        };
+        self
+    }
+
+    /// Sets a header, replacing existing values. Control characters but tabs are removed from
+    /// the value.
+    ///
+    /// # Panics
+    /// If `name` is not a token.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        assert!(is_token(&name), "invalid header name {name:?}");
+        self.headers.insert(name, strip_controls(value.into()));
+        self
+    }
+
+    pub fn content_type(self, content_type: &str) -> Self {
+        self.header("Content-Type", content_type)
+    }
+
+    pub fn cookie(mut self, cookie: Cookie) -> Self {
+        self.headers.append("Set-Cookie", cookie.to_string());
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Body::Bytes(body.into());
+        self
+    }
+
+    pub fn text(self, text: &str) -> Self {
+        self.content_type("text/plain; charset=utf-8").body(text)
+    }
+
+    pub fn html(self, html: &str) -> Self {
+        self.content_type("text/html; charset=utf-8").body(html)
+    }
+
+    pub fn json(self, json: &JsonValue) -> Self {
+        self.content_type("application/json").body(json.to_string())
+    }
+
+    /// Sends `len` bytes from the current position of `file` without loading them into memory.
+    pub fn file(mut self, file: File, len: u64) -> Self {
+        self.body = Body::File { file, len };
+        self
+    }
+
+    /// Sends the chunks with chunked transfer encoding, or until the connection is closed for
+    /// HTTP/1.0 clients.
+    pub fn stream(
+        mut self,
+        chunks: impl Iterator<Item = io::Result<Vec<u8>>> + Send + 'static,
+    ) -> Self {
+        self.body = Body::Stream(Box::new(chunks));
+        self
+    }
+
+    /// Keeps the headers of the body but leaves the body out, as the answer to a `HEAD` request.
+    pub fn without_body(mut self) -> Self {
+        self.omit_body = true;
+        self
+    }
+
+    /// The version of the request this answers. Defaults to HTTP/1.1.
+    pub fn version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Whether the body is delimited by closing the connection, because an HTTP/1.0 client
+    /// does not understand chunked transfer encoding.
+    pub fn closes_connection(&self) -> bool {
+        self.version == Version::Http10
+            && self.allows_body()
+            && !self.omit_body
+            && self.body.len().is_none()
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    pub fn body_ref(&self) -> &Body {
+        &self.body
+    }
+
+    /// 1xx, 204 and 304 responses never have a body.
+    fn allows_body(&self) -> bool {
+        !(self.status < 200 || self.status == 204 || self.status == 304)
+    }
+
+    /// Writes the response, streaming file and stream bodies.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if a header set through
+    /// [`headers_mut`](Self::headers_mut) has an invalid name or a line break in its value.
+    pub fn write_to(self, writer: &mut impl Write) -> io::Result<()> {
+        let allows_body = self.allows_body();
+        let close_delimited = self.closes_connection();
+        let mut headers = self.headers;
+
+        if headers
+            .iter()
+            .any(|(name, value)| !is_token(name) || value.contains(['\r', '\n']))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid header",
+            ));
+        }
+
+        // The framing only follows from the body that is actually sent.
+        headers.remove("Content-Length");
+        headers.remove("Transfer-Encoding");
+        if close_delimited {
+            headers.insert("Connection", "close");
+        } else if allows_body {
+            match self.body.len() {
+                Some(len) => headers.insert("Content-Length", len.to_string()),
+                None => headers.insert("Transfer-Encoding", "chunked"),
+            }
+        }
+
+        let head = format!("HTTP/1.1 {} {}\r\n{headers}\r\n", self.status, self.reason);
+        if !allows_body || self.omit_body {
+            writer.write_all(head.as_bytes())?;
+            return writer.flush();
+        }
+
+        match self.body {
+            Body::Empty => writer.write_all(head.as_bytes())?,
+            Body::Bytes(bytes) => {
+                let mut buf = head.into_bytes();
+                buf.extend_from_slice(&bytes);
+                writer.write_all(&buf)?;
+            }
+            Body::File { file, len } => {
+                writer.write_all(head.as_bytes())?;
+                let copied = io::copy(&mut file.take(len), writer)?;
+                if copied != len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+            Body::Stream(chunks) if close_delimited => {
+                writer.write_all(head.as_bytes())?;
+                for chunk in chunks {
+                    writer.write_all(&chunk?)?;
+                }
+            }
+            Body::Stream(chunks) => {
+                writer.write_all(head.as_bytes())?;
+                for chunk in chunks {
+                    let chunk = chunk?;
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    write!(writer, "{:x}\r\n", chunk.len())?;
+                    writer.write_all(&chunk)?;
+                    writer.write_all(b"\r\n")?;
+                }
+                writer.write_all(b"0\r\n\r\n")?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Writes the response into a new buffer.
+    pub fn to_bytes(self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// Answers a request that could not be read with the matching status code.
+impl From<&HttpError> for Response {
+    fn from(error: &HttpError) -> Self {
+        Self::new(error.status())
+            .text(&error.to_string())
+            .header("Connection", "close")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(response: Response) -> String {
+        String::from_utf8(response.to_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn serializes_responses() {
+        let response = Response::new(201)
+            .header("X-Id", "7")
+            .cookie(
+                Cookie::new("session", "abc")
+                    .path("/")
+                    .max_age(60)
+                    .http_only(),
+            )
+            .cookie(
+                Cookie::new("theme", "dark")
+                    .same_site(SameSite::Lax)
+                    .secure(),
+            )
+            .json(&JsonValue::from(vec![1, 2]));
+        assert_eq!(
+            text(response),
+            "HTTP/1.1 201 Created\r\nX-Id: 7\r\n\
+             Set-Cookie: session=abc; Path=/; Max-Age=60; HttpOnly\r\n\
+             Set-Cookie: theme=dark; Secure; SameSite=Lax\r\n\
+             Content-Type: application/json\r\nContent-Length: 5\r\n\r\n[1,2]"
+        );
+
+        assert_eq!(
+            text(Response::new(299).reason("Custom")),
+            "HTTP/1.1 299 Custom\r\nContent-Length: 0\r\n\r\n"
+        );
+        assert_eq!(
+            text(Response::moved_permanently("/new")),
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n"
+        );
+        assert_eq!(
+            text(Response::not_modified().header("ETag", "\"1\"")),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"1\"\r\n\r\n"
+        );
+        assert_eq!(
+            text(Response::not_found().without_body()),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: 9\r\n\r\n"
+        );
+        assert!(text(Response::bad_request("no")).ends_with("Content-Length: 2\r\n\r\nno"));
+        assert!(text(Response::internal_error()).starts_with("HTTP/1.1 500 Internal Server Error"));
+        assert!(text(Response::from(&HttpError::BodyTooLarge)).starts_with("HTTP/1.1 413 "));
+    }
+
+    #[test]
+    fn streams_bodies() {
+        let chunks = ["Wiki", "", "pedia"].map(|chunk| Ok(chunk.as_bytes().to_vec()));
+        assert_eq!(
+            text(Response::ok().stream(chunks.into_iter())),
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n"
+        );
+
+        let path = std::env::temp_dir().join(format!("iron_oxide_response_{}", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+        let file = File::open(&path).unwrap();
+        let response = text(Response::ok().file(file, 4));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(response, "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n0123");
+
+        let chunks = ["Wiki", "pedia"].map(|chunk| Ok(chunk.as_bytes().to_vec()));
+        let response = Response::ok()
+            .version(Version::Http10)
+            .stream(chunks.into_iter());
+        assert!(response.closes_connection());
+        assert_eq!(
+            text(response),
+            "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nWikipedia"
+        );
+    }
+
+    #[test]
+    fn sanitizes_headers() {
+        assert_eq!(
+            text(
+                Response::moved_permanently("/a\r\nSet-Cookie: x=1")
+                    .reason("Moved\r\nX: 1")
+                    .cookie(Cookie::new("id", "1\r\n; Domain=evil").path("/\n"))
+            ),
+            "HTTP/1.1 301 MovedX: 1\r\nLocation: /aSet-Cookie: x=1\r\n\
+             Set-Cookie: id=1 Domain=evil; Path=/\r\nContent-Length: 0\r\n\r\n"
+        );
+
+        // The framing headers follow the body.
+        assert_eq!(
+            text(
+                Response::ok()
+                    .header("Content-Length", "100")
+                    .header("Transfer-Encoding", "chunked")
+                    .body("hi")
+            ),
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi"
+        );
+
+        let mut response = Response::ok();
+        response.headers_mut().insert("X-A", "1\r\nX-B: 2");
+        let error = response.to_bytes().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        assert!(std::panic::catch_unwind(|| Response::ok().header("X A", "1")).is_err());
+        assert!(std::panic::catch_unwind(|| Cookie::new("a=b", "1")).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use super::{HTTPRequest, Method, Response};
+
+/// A request handler. Gets the request and the parameters of the matched route.
+pub type Handler = Arc<dyn Fn(&HTTPRequest, &Params) -> Response + Send + Sync>;
+
+/// The values of the `:name` and `*name` segments of a matched route.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    values: Vec<(String, String)>,
+}
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+enum Segment {
+    Literal(String),
+    /// `:name` matches one segment.
+    Param(String),
+    /// `*name` or `*` matches the rest of the path, including nothing.
+    Wildcard(String),
+}
+
+struct Route {
+    method: Option<Method>,
+    segments: Vec<Segment>,
+    handler: Handler,
+}
+
+impl Route {
+    /// Matches the decoded segments of a request, which has no empty ones, so `/a//b/` matches
+    /// `/a/b`.
+    fn matches(&self, parts: &[String]) -> Option<Params> {
+        let mut params = Params::default();
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => {
+                    if parts.get(i) != Some(literal) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.values.push((name.clone(), parts.get(i)?.clone()));
+                }
+                Segment::Wildcard(name) => {
+                    if !name.is_empty() {
+                        let rest = parts.get(i..).unwrap_or_default().join("/");
+                        params.values.push((name.clone(), rest));
+                    }
+                    return Some(params);
+                }
+            }
+        }
+
+        (parts.len() == self.segments.len()).then_some(params)
+    }
+}
+
+/// Dispatches requests to handlers by method and path pattern.
+///
+/// Patterns are matched in the order they were added. `/users/:id` captures one segment as
+/// `id`, `/files/*path` captures the rest of the path as `path`. A path that matches but not for
+/// the method is answered with `405 Method Not Allowed`, and `HEAD` falls back to `GET`.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a route for `method`, or for every method if it is `None`.
+    pub fn route<F>(mut self, method: Option<Method>, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(&HTTPRequest, &Params) -> Response + Send + Sync + 'static,
+    {
+        let segments = pattern
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Wildcard(name.to_string())
+                } else {
+                    Segment::Literal(segment.to_string())
+                }
+            })
+            .collect();
+
+        self.routes.push(Route {
+            method,
+            segments,
+            handler: Arc::new(handler),
+        });
+        self
+    }
+
+    pub fn get<F>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(&HTTPRequest, &Params) -> Response + Send + Sync + 'static,
+    {
+        self.route(Some(Method::GET), pattern, handler)
+    }
+
+    pub fn post<F>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(&HTTPRequest, &Params) -> Response + Send + Sync + 'static,
+    {
+        self.route(Some(Method::POST), pattern, handler)
+    }
+
+    pub fn put<F>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(&HTTPRequest, &Params) -> Response + Send + Sync + 'static,
+    {
+        self.route(Some(Method::PUT), pattern, handler)
+    }
+
+    pub fn delete<F>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(&HTTPRequest, &Params) -> Response + Send + Sync + 'static,
+    {
+        self.route(Some(Method::DELETE), pattern, handler)
+    }
+
+    pub fn any<F>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(&HTTPRequest, &Params) -> Response + Send + Sync + 'static,
+    {
+        self.route(None, pattern, handler)
+    }
+
+    /// Runs the handler of the first matching route.
+    pub fn handle(&self, request: &HTTPRequest) -> Response {
+        let mut allowed: Vec<&str> = Vec::new();
+        let mut head_fallback = None;
+
+        for route in &self.routes {
+            let Some(params) = route.matches(&request.segments) else {
+                continue;
+            };
+            match &route.method {
+                None => return (route.handler)(request, &params),
+                Some(method) if *method == request.method => {
+                    return (route.handler)(request, &params);
+                }
+                Some(Method::GET) if request.method == Method::HEAD => {
+                    head_fallback.get_or_insert((route, params));
+                }
+                Some(method) => allowed.push(method.as_str()),
+            }
+        }
+
+        if let Some((route, params)) = head_fallback {
+            return (route.handler)(request, &params).without_body();
+        }
+        if allowed.is_empty() {
+            return Response::not_found();
+        }
+        allowed.sort_unstable();
+        allowed.dedup();
+        Response::new(405)
+            .header("Allow", allowed.join(", "))
+            .text("Method Not Allowed")
+    }
+}
@@ -0,0 +1,636 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, Read, Write},
+    net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, TrySendError},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{
+    CloseCode, DeflateConfig, HTTPRequest, HttpError, HttpLimits, Method, Response, Router,
+    Version, WebSocket, WebSocketTimeouts, http_request::read_body,
+};
+
+type Before = Box<dyn Fn(&mut HTTPRequest) -> Option<Response> + Send + Sync>;
+type After = Box<dyn Fn(&HTTPRequest, Response) -> Response + Send + Sync>;
+type Upgrade = Box<dyn Fn(WebSocket, HTTPRequest) + Send + Sync>;
+
+/// An HTTP/1.1 server that answers requests with a [`Router`] on a fixed number of worker
+/// threads.
+///
+/// Each worker serves one connection at a time, including all requests sent over it while it
+/// is kept alive. Upgraded WebSocket connections get a thread of their own. Connections that
+/// arrive while the queue is full are answered with `503 Service Unavailable`.
+pub struct HttpServer {
+    router: Router,
+    before: Vec<Before>,
+    after: Vec<After>,
+    on_upgrade: Option<Upgrade>,
+    deflate: Option<DeflateConfig>,
+    websocket_timeouts: WebSocketTimeouts,
+    workers: usize,
+    queue: usize,
+    limits: HttpLimits,
+    keep_alive: Duration,
+    head_timeout: Duration,
+    live: Arc<Live>,
+}
+
+impl HttpServer {
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            before: Vec::new(),
+            after: Vec::new(),
+            on_upgrade: None,
+            deflate: None,
+            websocket_timeouts: WebSocketTimeouts::default(),
+            workers: 4,
+            queue: 64,
+            limits: HttpLimits::default(),
+            keep_alive: Duration::from_secs(5),
+            head_timeout: Duration::from_secs(10),
+            live: Arc::default(),
+        }
+    }
+
+    /// The number of worker threads. Defaults to 4.
+    pub fn workers(mut self, workers: usize) -> Self {
+        assert!(workers > 0, "a server needs at least one worker");
+        self.workers = workers;
+        self
+    }
+
+    /// How many accepted connections can wait for a worker. Defaults to 64.
+    pub fn queue(mut self, queue: usize) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    pub fn limits(mut self, limits: HttpLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// How long an idle connection is kept open for the next request. Defaults to 5 seconds.
+    pub fn keep_alive(mut self, timeout: Duration) -> Self {
+        self.keep_alive = timeout;
+        self
+    }
+
+    /// How long a client may take to send the request line and headers, counted from their
+    /// first byte. Defaults to 10 seconds.
+    pub fn head_timeout(mut self, timeout: Duration) -> Self {
+        self.head_timeout = timeout;
+        self
+    }
+
+    /// Runs `hook` before routing. Returning a response skips the router.
+    pub fn before<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut HTTPRequest) -> Option<Response> + Send + Sync + 'static,
+    {
+        self.before.push(Box::new(hook));
+        self
+    }
+
+    /// Runs `hook` on every response, in the order the hooks were added.
+    pub fn after<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&HTTPRequest, Response) -> Response + Send + Sync + 'static,
+    {
+        self.after.push(Box::new(hook));
+        self
+    }
+
+    /// Completes WebSocket upgrade requests with [`WebSocket::accept_with`] and hands the
+    /// connection to `handler` on a new thread, so it does not hold up a worker.
+    pub fn on_websocket<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(WebSocket, HTTPRequest) + Send + Sync + 'static,
+    {
+        self.on_upgrade = Some(Box::new(handler));
+        self
+    }
+
+    /// Accepts `permessage-deflate` for WebSocket connections that offer it.
+    pub fn websocket_deflate(mut self, config: DeflateConfig) -> Self {
+        self.deflate = Some(config);
+        self
+    }
+
+    /// The timeouts of WebSocket connections, including their opening handshake.
+    pub fn websocket_timeouts(mut self, timeouts: WebSocketTimeouts) -> Self {
+        self.websocket_timeouts = timeouts;
+        self
+    }
+
+    pub fn bind(self, addr: impl ToSocketAddrs) -> std::io::Result<ServerHandle> {
+        self.serve(TcpListener::bind(addr)?)
+    }
+
+    /// Starts accepting connections from `listener` on a background thread.
+    pub fn serve(self, listener: TcpListener) -> std::io::Result<ServerHandle> {
+        let mut addr = listener.local_addr()?;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(Ipv4Addr::LOCALHOST.into());
+        }
+
+        let server = Arc::new(self);
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(server.queue);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers: Vec<JoinHandle<()>> = (0..server.workers)
+            .map(|_| {
+                let server = server.clone();
+                let receiver = receiver.clone();
+                thread::spawn(move || {
+                    loop {
+                        let stream = receiver.lock().unwrap().recv();
+                        match stream {
+                            Ok(stream) => server.serve_connection(stream),
+                            Err(_) => break,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let running = Arc::new(AtomicBool::new(true));
+        let acceptor = {
+            let running = running.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if !running.load(Ordering::Acquire) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    if let Err(TrySendError::Full(mut stream)) = sender.try_send(stream) {
+                        let _ = Response::new(503)
+                            .header("Connection", "close")
+                            .text("Service Unavailable")
+                            .write_to(&mut stream);
+                    }
+                }
+
+                drop(sender);
+                for worker in workers {
+                    let _ = worker.join();
+                }
+            })
+        };
+
+        Ok(ServerHandle {
+            addr,
+            running,
+            acceptor,
+            live: server.live.clone(),
+        })
+    }
+
+    fn respond(&self, request: &mut HTTPRequest) -> Response {
+        let response = match self.before.iter().find_map(|hook| hook(request)) {
+            Some(response) => response,
+            None => self.router.handle(request),
+        };
+        self.after
+            .iter()
+            .fold(response, |response, hook| hook(request, response))
+    }
+
+    /// Reads a request, giving the head at most `head_timeout` from its first byte.
+    ///
+    /// A head that times out after it started fails with [`HttpError::Timeout`], so it is
+    /// answered with `408 Request Timeout`. An idle connection is closed quietly.
+    fn read_request(&self, reader: &mut BufReader<Deadline>) -> Result<HTTPRequest, HttpError> {
+        reader.get_mut().start(self.head_timeout);
+        let head = HTTPRequest::read_head(reader, &self.limits);
+        let started = reader.get_ref().started();
+        reader.get_mut().stop();
+        let mut request = match head {
+            Err(HttpError::Io(e))
+                if started
+                    && matches!(
+                        e.kind(),
+                        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                    ) =>
+            {
+                return Err(HttpError::Timeout);
+            }
+            head => head?,
+        };
+        request.body = read_body(reader, &request.headers, &self.limits)?;
+        Ok(request)
+    }
+
+    fn serve_connection(self: &Arc<Self>, stream: TcpStream) {
+        let Some(id) = self.live.add(&stream) else {
+            return;
+        };
+        self.serve_requests(stream);
+        self.live.remove(id);
+    }
+
+    fn serve_requests(self: &Arc<Self>, stream: TcpStream) {
+        let Ok(read_half) = stream.try_clone() else {
+            return;
+        };
+        let mut reader = BufReader::new(Deadline::new(read_half, self.keep_alive));
+        let mut writer = stream;
+
+        loop {
+            let mut request = match self.read_request(&mut reader) {
+                Ok(request) => request,
+                // The client closed the connection or it timed out while idle.
+                Err(HttpError::ConnectionClosed | HttpError::Io(_)) => return,
+                Err(error) => {
+                    let _ = Response::from(&error).write_to(&mut writer);
+                    // Drain what is left for a while so that unread input doesn't reset the
+                    // connection before the client sees the response.
+                    let _ = writer.shutdown(Shutdown::Write);
+                    reader.get_mut().start(self.head_timeout);
+                    let _ = io::copy(&mut reader, &mut io::sink());
+                    return;
+                }
+            };
+
+            if self.on_upgrade.is_some() && request.websocket_key().is_some() {
+                if let Ok(websocket) = WebSocket::accept_with(
+                    writer,
+                    &request,
+                    self.deflate.as_ref(),
+                    &self.websocket_timeouts,
+                ) {
+                    let server = self.clone();
+                    self.live.spawn(websocket, move |websocket| {
+                        if let Some(upgrade) = &server.on_upgrade {
+                            upgrade(websocket, request);
+                        }
+                    });
+                }
+                return;
+            }
+
+            let result = catch_unwind(AssertUnwindSafe(|| self.respond(&mut request)));
+            let keep_alive = request.keep_alive() && result.is_ok();
+            let mut response = result.unwrap_or_else(|_| Response::internal_error());
+
+            if request.method == Method::HEAD {
+                response = response.without_body();
+            }
+            response = response.version(request.version);
+            let keep_alive = keep_alive && !response.closes_connection();
+            if !keep_alive {
+                response = response.header("Connection", "close");
+            } else if request.version == Version::Http10 {
+                response = response.header("Connection", "keep-alive");
+            }
+
+            if response.write_to(&mut writer).is_err() || !keep_alive {
+                let _ = writer.flush();
+                return;
+            }
+        }
+    }
+}
+
+/// Reads with an idle timeout and, while started, an overall deadline that begins with the
+/// first byte.
+struct Deadline {
+    stream: TcpStream,
+    idle: Duration,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl Deadline {
+    fn new(stream: TcpStream, idle: Duration) -> Self {
+        Self {
+            stream,
+            idle,
+            timeout: None,
+            deadline: None,
+        }
+    }
+
+    fn start(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+        self.deadline = None;
+    }
+
+    /// Whether the first byte arrived since [`start`](Self::start).
+    fn started(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    fn stop(&mut self) {
+        self.timeout = None;
+        self.deadline = None;
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = match self.deadline {
+            Some(deadline) => deadline
+                .checked_duration_since(Instant::now())
+                .filter(|left| !left.is_zero())
+                .ok_or(io::ErrorKind::TimedOut)?
+                .min(self.idle),
+            None => self.idle,
+        };
+        self.stream.set_read_timeout(Some(timeout))?;
+        let len = self.stream.read(buf)?;
+        if let Some(timeout) = self.timeout
+            && self.deadline.is_none()
+            && len > 0
+        {
+            self.deadline = Some(Instant::now() + timeout);
+        }
+        Ok(len)
+    }
+}
+
+/// The open connections of a server, so that shutting down can close them.
+#[derive(Default)]
+struct Live {
+    state: Mutex<LiveState>,
+}
+
+#[derive(Default)]
+struct LiveState {
+    closed: bool,
+    next_id: u64,
+    streams: HashMap<u64, TcpStream>,
+    websockets: HashMap<u64, WebSocket>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Live {
+    /// Tracks an HTTP connection. Returns `None` once the server shuts down.
+    fn add(&self, stream: &TcpStream) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return None;
+        }
+        let stream = stream.try_clone().ok()?;
+        state.next_id += 1;
+        let id = state.next_id;
+        state.streams.insert(id, stream);
+        Some(id)
+    }
+
+    fn remove(&self, id: u64) {
+        self.state.lock().unwrap().streams.remove(&id);
+    }
+
+    /// Runs `handler` on a new thread and tracks the connection until it returns.
+    fn spawn(
+        self: &Arc<Self>,
+        mut websocket: WebSocket,
+        handler: impl FnOnce(WebSocket) + Send + 'static,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            websocket.abort(CloseCode::GoingAway, "");
+            return;
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        state.websockets.insert(id, websocket.clone());
+
+        let live = self.clone();
+        state.threads.retain(|thread| !thread.is_finished());
+        state.threads.push(thread::spawn(move || {
+            handler(websocket);
+            live.state.lock().unwrap().websockets.remove(&id);
+        }));
+    }
+
+    /// Closes WebSocket connections and the read side of HTTP connections, so that requests in
+    /// progress are still answered. Returns the threads of the WebSocket handlers.
+    fn close(&self) -> Vec<JoinHandle<()>> {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        for stream in state.streams.values() {
+            let _ = stream.shutdown(Shutdown::Read);
+        }
+        for websocket in state.websockets.values_mut() {
+            websocket.abort(CloseCode::GoingAway, "");
+        }
+        std::mem::take(&mut state.threads)
+    }
+}
+
+/// A running [`HttpServer`].
+pub struct ServerHandle {
+    addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    acceptor: JoinHandle<()>,
+    live: Arc<Live>,
+}
+
+impl ServerHandle {
+    /// The address the server listens on, with `127.0.0.1` for an unspecified address.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stops accepting connections and closes the open ones.
+    ///
+    /// Requests in progress are still answered and WebSocket connections are closed with
+    /// [`CloseCode::GoingAway`]. Returns once the workers and the WebSocket handlers finished.
+    pub fn shutdown(self) {
+        self.running.store(false, Ordering::Release);
+        // Wakes up the acceptor, which is blocked in `accept`.
+        let _ = TcpStream::connect(self.addr);
+        let threads = self.live.close();
+        let _ = self.acceptor.join();
+        for thread in threads {
+            let _ = thread.join();
+        }
+    }
+
+    /// Blocks until the server stops.
+    pub fn join(self) {
+        let _ = self.acceptor.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Read};
+
+    use super::*;
+    use crate::net::Params;
+
+    /// Reads one response with a `Content-Length` body and returns its head and body.
+    fn read_response(reader: &mut impl BufRead) -> (String, String) {
+        let mut head = String::new();
+        loop {
+            let len = head.len();
+            reader.read_line(&mut head).unwrap();
+            if head[len..] == *"\r\n" {
+                break;
+            }
+        }
+        let len = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map_or(0, |len| len.parse().unwrap());
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).unwrap();
+        (head, String::from_utf8(body).unwrap())
+    }
+
+    fn server() -> ServerHandle {
+        let router = Router::new()
+            .get("/users/:id", |_, params: &Params| {
+                Response::ok().text(&format!("user {}", params.get("id").unwrap()))
+            })
+            .get("/files/*path", |_, params: &Params| {
+                Response::ok().text(params.get("path").unwrap())
+            })
+            .post("/echo", |request: &HTTPRequest, _: &Params| {
+                Response::ok().body(request.body.clone())
+            })
+            .get("/panic", |_, _: &Params| panic!("handler failed"))
+            .put("/items/:id", |_, _: &Params| Response::ok())
+            .post("/items/*rest", |_, _: &Params| Response::ok())
+            .put("/items/*rest", |_, _: &Params| Response::ok());
+
+        HttpServer::new(router)
+            .workers(2)
+            .keep_alive(Duration::from_secs(2))
+            .head_timeout(Duration::from_millis(300))
+            .before(|request| {
+                (request.header("authorization") == Some("nope")).then(|| Response::new(401))
+            })
+            .after(|_, response| response.header("X-Server", "iron_oxide"))
+            .on_websocket(|mut websocket, request| {
+                websocket
+                    .send(request.path.as_bytes(), crate::net::MessageDataType::Text)
+                    .unwrap();
+            })
+            .bind("127.0.0.1:0")
+            .unwrap()
+    }
+
+    #[test]
+    fn serves_keep_alive_connection() {
+        let server = server();
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+
+        writer
+            .write_all(
+                b"GET /users/42 HTTP/1.1\r\nHost: a\r\n\r\n\
+                  GET /users/a%2Fb HTTP/1.1\r\nHost: a\r\n\r\n\
+                  POST /echo HTTP/1.1\r\nHost: a\r\nContent-Length: 4\r\n\r\nping\
+                  GET /files/css/site.css HTTP/1.1\r\nHost: a\r\n\r\n",
+            )
+            .unwrap();
+
+        let (head, body) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("X-Server: iron_oxide\r\n"));
+        assert_eq!(body, "user 42");
+        assert_eq!(read_response(&mut reader).1, "user a/b");
+        assert_eq!(read_response(&mut reader).1, "ping");
+        assert_eq!(read_response(&mut reader).1, "css/site.css");
+
+        writer
+            .write_all(b"DELETE /echo HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 405 "));
+        assert!(head.contains("Allow: POST\r\n"));
+
+        writer
+            .write_all(b"DELETE /items/1 HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        let (head, _) = read_response(&mut reader);
+        assert!(head.contains("Allow: POST, PUT\r\n"));
+
+        writer
+            .write_all(b"HEAD /users/1 HTTP/1.1\r\nHost: a\r\nAuthorization: nope\r\n\r\n")
+            .unwrap();
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 401 "));
+
+        writer
+            .write_all(b"GET /missing HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 404 "));
+        assert!(head.contains("Connection: close\r\n"));
+        assert_eq!(reader.read(&mut [0; 1]).unwrap(), 0);
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn answers_errors_and_upgrades() {
+        let server = server();
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let (head, _) = read_response(&mut BufReader::new(&stream));
+        assert!(head.starts_with("HTTP/1.1 400 "));
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream
+            .write_all(b"GET /panic HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        let (head, _) = read_response(&mut BufReader::new(&stream));
+        assert!(head.starts_with("HTTP/1.1 500 "));
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream
+            .write_all(
+                b"GET /chat HTTP/1.1\r\nHost: a\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        let mut reader = BufReader::new(&stream);
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 101 "));
+        assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn limits_the_time_for_the_head() {
+        let server = server();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        // Every byte arrives well within the keep-alive timeout, but the head takes too long.
+        let start = std::time::Instant::now();
+        let _ = stream.write_all(b"GET / HTTP/1.1\r\n");
+        for _ in 0..20 {
+            if stream.write_all(b"X").is_err() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let (head, _) = read_response(&mut BufReader::new(&stream));
+        assert!(head.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+        assert!(head.contains("Connection: close\r\n"));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        server.shutdown();
+    }
+}
@@ -0,0 +1,579 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs::{self, File, Metadata},
+    io::{self, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use super::{HTTPRequest, Method, Params, Response, percent_encode};
+use crate::primitives::Date;
+
+/// Maps file extensions to content types.
+#[derive(Debug, Clone)]
+pub struct MimeTypes {
+    types: HashMap<String, String>,
+}
+
+impl MimeTypes {
+    /// A table without any types.
+    pub fn empty() -> Self {
+        Self {
+            types: HashMap::new(),
+        }
+    }
+
+    /// Adds or replaces the content type of `extension`, which is matched case-insensitively.
+    pub fn insert(&mut self, extension: &str, content_type: &str) {
+        self.types
+            .insert(extension.to_ascii_lowercase(), content_type.to_string());
+    }
+
+    pub fn get(&self, extension: &str) -> Option<&str> {
+        self.types
+            .get(&extension.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// The content type of `path`, or `application/octet-stream` if the extension is unknown.
+    pub fn for_path(&self, path: &Path) -> &str {
+        path.extension()
+            .and_then(|extension| self.get(&extension.to_string_lossy()))
+            .unwrap_or("application/octet-stream")
+    }
+}
+
+impl Default for MimeTypes {
+    fn default() -> Self {
+        let mut types = Self::empty();
+        for (extension, content_type) in [
+            ("html", "text/html; charset=utf-8"),
+            ("htm", "text/html; charset=utf-8"),
+            ("css", "text/css; charset=utf-8"),
+            ("js", "text/javascript; charset=utf-8"),
+            ("mjs", "text/javascript; charset=utf-8"),
+            ("txt", "text/plain; charset=utf-8"),
+            ("csv", "text/csv; charset=utf-8"),
+            ("md", "text/markdown; charset=utf-8"),
+            ("xml", "application/xml"),
+            ("json", "application/json"),
+            ("wasm", "application/wasm"),
+            ("pdf", "application/pdf"),
+            ("zip", "application/zip"),
+            ("apng", "image/apng"),
+            ("png", "image/png"),
+            ("jpg", "image/jpeg"),
+            ("jpeg", "image/jpeg"),
+            ("gif", "image/gif"),
+            ("webp", "image/webp"),
+            ("avif", "image/avif"),
+            ("svg", "image/svg+xml"),
+            ("ico", "image/vnd.microsoft.icon"),
+            ("mp3", "audio/mpeg"),
+            ("ogg", "audio/ogg"),
+            ("wav", "audio/wav"),
+            ("mp4", "video/mp4"),
+            ("webm", "video/webm"),
+            ("woff", "font/woff"),
+            ("woff2", "font/woff2"),
+            ("ttf", "font/ttf"),
+            ("otf", "font/otf"),
+        ] {
+            types.insert(extension, content_type);
+        }
+        types
+    }
+}
+
+/// Serves the files below a root directory.
+///
+/// Paths that leave the root, also through symlinks, are answered with `404 Not Found`. Files
+/// are streamed from disk, support single `Range` requests and are validated with `ETag` and
+/// `Last-Modified`.
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    root: PathBuf,
+    mime_types: MimeTypes,
+    index: Option<String>,
+    listing: bool,
+}
+
+impl StaticFiles {
+    pub fn new(root: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            root: root.as_ref().canonicalize()?,
+            mime_types: MimeTypes::default(),
+            index: Some("index.html".to_string()),
+            listing: false,
+        })
+    }
+
+    pub fn mime_types(mut self, mime_types: MimeTypes) -> Self {
+        self.mime_types = mime_types;
+        self
+    }
+
+    /// The file served for a directory. Defaults to `index.html`.
+    pub fn index(mut self, index: Option<&str>) -> Self {
+        self.index = index.map(str::to_string);
+        self
+    }
+
+    /// Renders an HTML listing for directories without an index file.
+    pub fn directory_listing(mut self, enabled: bool) -> Self {
+        self.listing = enabled;
+        self
+    }
+
+    /// Turns this into a router handler that serves the `path` parameter of a route like
+    /// `/static/*path`, or the whole request path if the route has none.
+    pub fn handler(self) -> impl Fn(&HTTPRequest, &Params) -> Response + Send + Sync + 'static {
+        move |request, params| {
+            let path = params.get("path").unwrap_or(&request.path);
+            self.serve(request, path)
+        }
+    }
+
+    /// Answers `request` with the file at `path`, relative to the root.
+    pub fn serve(&self, request: &HTTPRequest, path: &str) -> Response {
+        if request.method != Method::GET && request.method != Method::HEAD {
+            return Response::new(405)
+                .header("Allow", "GET, HEAD")
+                .text("Method Not Allowed");
+        }
+
+        let Some(file_path) = self.resolve(path) else {
+            return Response::not_found();
+        };
+        let Ok(metadata) = fs::metadata(&file_path) else {
+            return Response::not_found();
+        };
+
+        if metadata.is_dir() {
+            if !request.path.ends_with('/') {
+                return Response::moved_permanently(&directory_location(request));
+            }
+            if let Some(index) = self.index.as_ref().map(|index| file_path.join(index))
+                && index.is_file()
+            {
+                return self.serve_file(request, &index);
+            }
+            if self.listing {
+                return self.listing(request, &file_path);
+            }
+            return Response::not_found();
+        }
+
+        self.serve_file(request, &file_path)
+    }
+
+    /// Maps a request path onto the file system, refusing anything outside the root.
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let mut resolved = self.root.clone();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return None,
+                _ => {
+                    // A segment must stay a single normal component on every platform.
+                    let mut components = Path::new(segment).components();
+                    match (components.next(), components.next()) {
+                        (Some(Component::Normal(_)), None) if !segment.contains(['\\', '\0']) => {
+                            resolved.push(segment)
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+        }
+
+        let resolved = resolved.canonicalize().ok()?;
+        resolved.starts_with(&self.root).then_some(resolved)
+    }
+
+    fn serve_file(&self, request: &HTTPRequest, path: &Path) -> Response {
+        let file = File::open(path).and_then(|file| Ok((file.metadata()?, file)));
+        let Ok((metadata, mut file)) = file else {
+            return Response::not_found();
+        };
+
+        let len = metadata.len();
+        let (etag, last_modified) = validators(&metadata);
+        let response = |status: u16| {
+            let mut response = Response::new(status)
+                .header("ETag", etag.clone())
+                .header("Accept-Ranges", "bytes");
+            if let Some(last_modified) = &last_modified {
+                response = response.header("Last-Modified", last_modified.to_http_date());
+            }
+            response
+        };
+
+        if not_modified(request, &etag, last_modified.as_ref()) {
+            return response(304);
+        }
+
+        let content_type = self.mime_types.for_path(path);
+        let range = request
+            .header("range")
+            .filter(|_| request.method == Method::GET)
+            .filter(|_| if_range_matches(request, &etag, last_modified.as_ref()));
+
+        match range.map(|range| parse_range(range, len)) {
+            Some(Some(Ok((start, end)))) => {
+                if file.seek(SeekFrom::Start(start)).is_err() {
+                    return Response::internal_error();
+                }
+                response(206)
+                    .content_type(content_type)
+                    .header("Content-Range", format!("bytes {start}-{end}/{len}"))
+                    .file(file, end - start + 1)
+            }
+            Some(Some(Err(()))) => response(416)
+                .header("Content-Range", format!("bytes */{len}"))
+                .text("Range Not Satisfiable"),
+            // No range or one that is not supported, so the whole file is sent.
+            _ => response(200).content_type(content_type).file(file, len),
+        }
+    }
+
+    fn listing(&self, request: &HTTPRequest, dir: &Path) -> Response {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Response::internal_error();
+        };
+
+        let mut names: Vec<String> = entries
+            .filter_map(Result::ok)
+            .map(|entry| {
+                let mut name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                    name.push('/');
+                }
+                name
+            })
+            .collect();
+        names.sort();
+
+        let title = escape_html(&request.path);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Index of {title}</title>\
+             </head><body><h1>Index of {title}</h1><ul>\n"
+        );
+        if request.path != "/" {
+            html.push_str("<li><a href=\"../\">../</a></li>\n");
+        }
+        for name in names {
+            let _ = writeln!(
+                html,
+                "<li><a href=\"{}\">{}</a></li>",
+                escape_html(&percent_encode(&name)),
+                escape_html(&name)
+            );
+        }
+        html.push_str("</ul></body></html>\n");
+        Response::ok().html(&html)
+    }
+}
+
+/// The path of `request` with a trailing slash and its query. Built from the segments, so the
+/// location cannot start with `//` and point to another host.
+fn directory_location(request: &HTTPRequest) -> String {
+    let mut location = String::new();
+    for segment in &request.segments {
+        location.push('/');
+        location.push_str(&percent_encode(segment).replace('/', "%2F"));
+    }
+    location.push('/');
+    if let Some((_, query)) = request.target.split_once('?') {
+        location.push('?');
+        location.push_str(query);
+    }
+    location
+}
+
+/// Builds the `ETag` from size and modification time, and the modification date.
+fn validators(metadata: &Metadata) -> (String, Option<Date>) {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+    let etag = format!(
+        "\"{:x}-{:x}\"",
+        metadata.len(),
+        modified.map_or(0, |time| time.as_nanos())
+    );
+    (
+        etag,
+        modified.map(|time| Date::from_unix_secs(time.as_secs())),
+    )
+}
+
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').any(|tag| {
+        let tag = tag.trim();
+        tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag
+    })
+}
+
+/// Evaluates `If-None-Match`, or `If-Modified-Since` if it is absent.
+fn not_modified(request: &HTTPRequest, etag: &str, last_modified: Option<&Date>) -> bool {
+    if let Some(header) = request.header("if-none-match") {
+        return etag_matches(header, etag);
+    }
+    match (request.header("if-modified-since"), last_modified) {
+        (Some(since), Some(modified)) => Date::parse_http_date(since)
+            .is_some_and(|since| modified.to_unix_secs() <= since.to_unix_secs()),
+        _ => false,
+    }
+}
+
+/// A range is only applied if the `If-Range` validator, if any, still matches the file.
+fn if_range_matches(request: &HTTPRequest, etag: &str, last_modified: Option<&Date>) -> bool {
+    match request.header("if-range") {
+        None => true,
+        Some(value) if value.starts_with('"') => value == etag,
+        Some(value) => match (Date::parse_http_date(value), last_modified) {
+            (Some(date), Some(modified)) => date.to_unix_secs() == modified.to_unix_secs(),
+            _ => false,
+        },
+    }
+}
+
+/// Parses a single byte range into inclusive bounds.
+///
+/// Returns `None` for ranges that are ignored, like multiple ranges or other units, and
+/// `Some(Err(()))` for ranges that cannot be satisfied.
+fn parse_range(header: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let range = header.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.trim().split_once('-')?;
+    let number = |text: &str| {
+        (!text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit()))
+            .then(|| text.parse::<u64>().ok())
+            .flatten()
+    };
+
+    let bounds = if start.is_empty() {
+        let suffix = number(end)?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start = number(start)?;
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            number(end)?.min(len.saturating_sub(1))
+        };
+        if start >= len || start > end {
+            return Some(Err(()));
+        }
+        (start, end)
+    };
+    Some(Ok(bounds))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            char => out.push(char),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "iron_oxide_{name}_{}_{:?}",
+                std::process::id(),
+                std::thread::current().id()
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn get(files: &StaticFiles, path: &str, headers: &str) -> (String, Vec<u8>) {
+        let request = HTTPRequest::parse(
+            format!(
+                "GET {} HTTP/1.1\r\nHost: a\r\n{headers}\r\n",
+                percent_encode(path)
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        let bytes = files.serve(&request, &request.path).to_bytes().unwrap();
+        let split = bytes.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        (
+            String::from_utf8(bytes[..split].to_vec()).unwrap(),
+            bytes[split..].to_vec(),
+        )
+    }
+
+    fn header<'a>(head: &'a str, name: &str) -> &'a str {
+        head.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+            .unwrap()
+    }
+
+    fn site() -> (TempDir, StaticFiles) {
+        let dir = TempDir::new("static");
+        fs::create_dir_all(dir.0.join("site/docs")).unwrap();
+        fs::write(dir.0.join("site/index.html"), "<h1>home</h1>").unwrap();
+        fs::write(dir.0.join("site/logo.svg"), "<svg/>").unwrap();
+        fs::write(dir.0.join("site/docs/a <b>.txt"), "0123456789").unwrap();
+        fs::write(dir.0.join("secret.txt"), "secret").unwrap();
+        let files = StaticFiles::new(dir.0.join("site"))
+            .unwrap()
+            .directory_listing(true);
+        (dir, files)
+    }
+
+    #[test]
+    fn serves_files_and_directories() {
+        let (_dir, files) = site();
+
+        let (head, body) = get(&files, "/", "");
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(body, b"<h1>home</h1>");
+        assert_eq!(header(&head, "Content-Type"), "text/html; charset=utf-8");
+
+        let (head, _) = get(&files, "/logo.svg", "");
+        assert_eq!(header(&head, "Content-Type"), "image/svg+xml");
+
+        let (head, _) = get(&files, "/docs", "");
+        assert!(head.starts_with("HTTP/1.1 301 "));
+        assert_eq!(header(&head, "Location"), "/docs/");
+
+        let (head, _) = get(&files, "//docs", "");
+        assert_eq!(header(&head, "Location"), "/docs/");
+        let request =
+            HTTPRequest::parse(b"GET /docs?sort=name&x=%2F HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        let response = files.serve(&request, &request.path);
+        assert_eq!(
+            response.headers().get("Location"),
+            Some("/docs/?sort=name&x=%2F")
+        );
+
+        let (head, body) = get(&files, "/docs/", "");
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.contains("<a href=\"a%20%3Cb%3E.txt\">a &lt;b&gt;.txt</a>"));
+        assert!(body.contains("<a href=\"../\">"));
+
+        let (head, body) = get(&files, "/docs/a <b>.txt", "");
+        assert_eq!(body, b"0123456789");
+        assert_eq!(header(&head, "Accept-Ranges"), "bytes");
+    }
+
+    #[test]
+    fn rejects_escapes() {
+        let (dir, files) = site();
+
+        for path in [
+            "/../secret.txt",
+            "/docs/../../secret.txt",
+            "/missing",
+            "/a\\..\\b",
+        ] {
+            let (head, _) = get(&files, path, "");
+            assert!(head.starts_with("HTTP/1.1 404 "), "{path}");
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.0.join("secret.txt"), dir.0.join("site/link")).unwrap();
+            let (head, _) = get(&files, "/link", "");
+            assert!(head.starts_with("HTTP/1.1 404 "));
+        }
+        #[cfg(not(unix))]
+        let _ = dir;
+    }
+
+    #[test]
+    fn ranges() {
+        let (_dir, files) = site();
+        let path = "/docs/a <b>.txt";
+
+        let (head, body) = get(&files, path, "Range: bytes=2-4\r\n");
+        assert!(head.starts_with("HTTP/1.1 206 Partial Content"));
+        assert_eq!(header(&head, "Content-Range"), "bytes 2-4/10");
+        assert_eq!(body, b"234");
+
+        assert_eq!(get(&files, path, "Range: bytes=7-\r\n").1, b"789");
+        assert_eq!(get(&files, path, "Range: bytes=-2\r\n").1, b"89");
+        assert_eq!(get(&files, path, "Range: bytes=5-100\r\n").1, b"56789");
+        assert_eq!(
+            get(&files, path, "Range: bytes=0-1,4-5\r\n").1,
+            b"0123456789"
+        );
+
+        let (head, _) = get(&files, path, "Range: bytes=10-\r\n");
+        assert!(head.starts_with("HTTP/1.1 416 "));
+        assert_eq!(header(&head, "Content-Range"), "bytes */10");
+
+        let (head, _) = get(&files, path, "If-Range: \"stale\"\r\nRange: bytes=0-0\r\n");
+        assert!(head.starts_with("HTTP/1.1 200 "));
+    }
+
+    #[test]
+    fn conditional_requests() {
+        let (_dir, files) = site();
+
+        let (head, _) = get(&files, "/logo.svg", "");
+        let etag = header(&head, "ETag").to_string();
+        let last_modified = header(&head, "Last-Modified").to_string();
+        let date = Date::parse_http_date(&last_modified).unwrap();
+        assert_eq!(date.to_http_date(), last_modified);
+
+        let (head, body) = get(&files, "/logo.svg", &format!("If-None-Match: W/{etag}\r\n"));
+        assert!(head.starts_with("HTTP/1.1 304 "));
+        assert!(body.is_empty());
+
+        let (head, _) = get(&files, "/logo.svg", "If-None-Match: \"other\"\r\n");
+        assert!(head.starts_with("HTTP/1.1 200 "));
+
+        let since = format!("If-Modified-Since: {last_modified}\r\n");
+        assert!(
+            get(&files, "/logo.svg", &since)
+                .0
+                .starts_with("HTTP/1.1 304 ")
+        );
+
+        let old = Date::from_unix_secs(date.to_unix_secs() - 1).to_http_date();
+        let since = format!("If-Modified-Since: {old}\r\n");
+        assert!(
+            get(&files, "/logo.svg", &since)
+                .0
+                .starts_with("HTTP/1.1 200 ")
+        );
+        assert_eq!(
+            Date::from_unix_secs(784111777).to_http_date(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+    }
+}
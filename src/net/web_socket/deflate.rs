@@ -0,0 +1,336 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use super::WebSocketError;
+
+/// The parameters of the `permessage-deflate` extension (RFC 7692).
+///
+/// Passed to a handshake, it is what this side offers or accepts. After the handshake,
+/// [`WebSocket::deflate`](super::WebSocket::deflate) holds what both sides agreed on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeflateConfig {
+    /// The server starts every message with an empty compression window.
+    pub server_no_context_takeover: bool,
+    /// The client starts every message with an empty compression window.
+    pub client_no_context_takeover: bool,
+    /// The base-2 logarithm of the server's compression window, from 9 to 15.
+    pub server_max_window_bits: u8,
+    /// The base-2 logarithm of the client's compression window, from 9 to 15.
+    pub client_max_window_bits: u8,
+    /// Messages shorter than this are sent uncompressed.
+    pub min_size: usize,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        Self {
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            server_max_window_bits: 15,
+            client_max_window_bits: 15,
+            min_size: 32,
+        }
+    }
+}
+
+impl DeflateConfig {
+    /// The `Sec-WebSocket-Extensions` value a client sends.
+    pub(super) fn offer(&self) -> String {
+        let mut offer = "permessage-deflate; client_max_window_bits".to_string();
+        let client_bits = window_bits(self.client_max_window_bits);
+        if client_bits < 15 {
+            offer += &format!("={client_bits}");
+        }
+        let server_bits = window_bits(self.server_max_window_bits);
+        if server_bits < 15 {
+            offer += &format!("; server_max_window_bits={server_bits}");
+        }
+        self.push_flags(&mut offer);
+        offer
+    }
+
+    /// Picks the first acceptable offer of a client and returns the agreed parameters with
+    /// the value to answer with.
+    pub(super) fn accept(&self, offers: &str) -> Option<(DeflateConfig, String)> {
+        parse_extensions(offers)
+            .into_iter()
+            .filter(|(name, _)| *name == "permessage-deflate")
+            .find_map(|(_, params)| {
+                let offer = Params::parse(&params, true)?;
+                // Compression windows of 8 bits are not supported, so such offers are declined.
+                let server_bits = offer.server_max_window_bits.unwrap_or(15);
+                if server_bits < 9 {
+                    return None;
+                }
+
+                let agreed = DeflateConfig {
+                    server_no_context_takeover: offer.server_no_context_takeover
+                        || self.server_no_context_takeover,
+                    client_no_context_takeover: offer.client_no_context_takeover
+                        || self.client_no_context_takeover,
+                    server_max_window_bits: server_bits
+                        .min(window_bits(self.server_max_window_bits)),
+                    // The client window can only be limited if the client offered to.
+                    client_max_window_bits: match offer.client_max_window_bits {
+                        Some(bits) => bits
+                            .unwrap_or(15)
+                            .min(window_bits(self.client_max_window_bits)),
+                        None => 15,
+                    },
+                    min_size: self.min_size,
+                };
+
+                let mut response = "permessage-deflate".to_string();
+                agreed.push_flags(&mut response);
+                if offer.server_max_window_bits.is_some() || agreed.server_max_window_bits < 15 {
+                    response +=
+                        &format!("; server_max_window_bits={}", agreed.server_max_window_bits);
+                }
+                if agreed.client_max_window_bits < 15 {
+                    response +=
+                        &format!("; client_max_window_bits={}", agreed.client_max_window_bits);
+                }
+                Some((agreed, response))
+            })
+    }
+
+    /// Checks the answer of a server to [`offer`](Self::offer) and returns the agreed
+    /// parameters.
+    pub(super) fn confirm(&self, response: &str) -> Result<DeflateConfig, &'static str> {
+        let extensions = parse_extensions(response);
+        let [(name, params)] = extensions.as_slice() else {
+            return Err("unexpected extensions");
+        };
+        if *name != "permessage-deflate" {
+            return Err("unexpected extension");
+        }
+
+        let params = Params::parse(params, false).ok_or("invalid permessage-deflate parameters")?;
+        let server_bits = params.server_max_window_bits.unwrap_or(15);
+        if server_bits > window_bits(self.server_max_window_bits) {
+            return Err("server_max_window_bits larger than offered");
+        }
+        let client_bits = match params.client_max_window_bits {
+            Some(bits) => bits.ok_or("client_max_window_bits without a value")?,
+            None => window_bits(self.client_max_window_bits),
+        };
+        // Compressing with a window of 8 bits is not supported, like in `accept`.
+        if client_bits < 9 {
+            return Err("client_max_window_bits of 8 is not supported");
+        }
+
+        Ok(DeflateConfig {
+            server_no_context_takeover: params.server_no_context_takeover,
+            client_no_context_takeover: params.client_no_context_takeover
+                || self.client_no_context_takeover,
+            server_max_window_bits: server_bits,
+            client_max_window_bits: client_bits.min(window_bits(self.client_max_window_bits)),
+            min_size: self.min_size,
+        })
+    }
+
+    fn push_flags(&self, value: &mut String) {
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+    }
+}
+
+/// The window sizes supported for compression.
+fn window_bits(bits: u8) -> u8 {
+    bits.clamp(9, 15)
+}
+
+/// An extension parameter with its value, if any.
+type Param<'a> = (&'a str, Option<&'a str>);
+
+/// The parameters of one `permessage-deflate` offer or response.
+#[derive(Default)]
+struct Params {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: Option<u8>,
+    /// The value is optional in an offer.
+    client_max_window_bits: Option<Option<u8>>,
+}
+
+impl Params {
+    /// Returns `None` for unknown, repeated or invalid parameters.
+    fn parse(params: &[Param<'_>], offer: bool) -> Option<Self> {
+        let bits = |value: Option<&str>| {
+            let value = value?.trim_matches('"');
+            let bits: u8 = value.parse().ok()?;
+            (value.bytes().all(|byte| byte.is_ascii_digit()) && (8..=15).contains(&bits))
+                .then_some(bits)
+        };
+
+        let mut parsed = Self::default();
+        let mut seen = Vec::new();
+        for &(name, value) in params {
+            if seen.contains(&name) {
+                return None;
+            }
+            seen.push(name);
+
+            match (name, value) {
+                ("server_no_context_takeover", None) => parsed.server_no_context_takeover = true,
+                ("client_no_context_takeover", None) => parsed.client_no_context_takeover = true,
+                ("server_max_window_bits", value) => {
+                    parsed.server_max_window_bits = Some(bits(value)?);
+                }
+                ("client_max_window_bits", None) if offer => {
+                    parsed.client_max_window_bits = Some(None);
+                }
+                ("client_max_window_bits", value) => {
+                    parsed.client_max_window_bits = Some(Some(bits(value)?));
+                }
+                _ => return None,
+            }
+        }
+        Some(parsed)
+    }
+}
+
+/// Splits a `Sec-WebSocket-Extensions` value into extensions and their parameters.
+fn parse_extensions(value: &str) -> Vec<(&str, Vec<Param<'_>>)> {
+    value
+        .split(',')
+        .filter(|extension| !extension.trim().is_empty())
+        .map(|extension| {
+            let mut parts = extension.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let params = parts
+                .filter(|param| !param.is_empty())
+                .map(|param| match param.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim())),
+                    None => (param, None),
+                })
+                .collect();
+            (name, params)
+        })
+        .collect()
+}
+
+/// The compressor and decompressor of a connection.
+#[derive(Debug)]
+pub(super) struct Deflate {
+    config: DeflateConfig,
+    compress: Compress,
+    decompress: Decompress,
+    reset_compress: bool,
+    reset_decompress: bool,
+}
+
+impl Deflate {
+    pub(super) fn new(config: DeflateConfig, client: bool) -> Self {
+        let (bits, reset_compress, reset_decompress) = if client {
+            let reset = config.client_no_context_takeover;
+            (
+                config.client_max_window_bits,
+                reset,
+                config.server_no_context_takeover,
+            )
+        } else {
+            let reset = config.server_no_context_takeover;
+            (
+                config.server_max_window_bits,
+                reset,
+                config.client_no_context_takeover,
+            )
+        };
+
+        // The handshake never agrees on an 8 bit window for this side.
+        debug_assert!((9..=15).contains(&bits), "unsupported window bits {bits}");
+        Self {
+            compress: Compress::new_with_window_bits(Compression::default(), false, bits),
+            // The largest window can inflate data compressed with any smaller one.
+            decompress: Decompress::new(false),
+            config,
+            reset_compress,
+            reset_decompress,
+        }
+    }
+
+    pub(super) fn config(&self) -> &DeflateConfig {
+        &self.config
+    }
+
+    /// Compresses a message, or returns `None` if it is better sent as is.
+    pub(super) fn compress(&mut self, data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() < self.config.min_size {
+            return None;
+        }
+
+        let start = self.compress.total_in();
+        let mut output = Vec::with_capacity(data.len() / 2 + 64);
+        loop {
+            if output.capacity() - output.len() < 64 {
+                output.reserve(output.capacity());
+            }
+            let consumed = (self.compress.total_in() - start) as usize;
+            self.compress
+                .compress_vec(&data[consumed..], &mut output, FlushCompress::Sync)
+                .ok()?;
+
+            // The flush is complete once all input is consumed without filling the output.
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && output.len() < output.capacity() {
+                break;
+            }
+        }
+        if self.reset_compress {
+            self.compress.reset();
+        }
+
+        // Every message ends with an empty stored block, which is left out on the wire.
+        if output.ends_with(&[0, 0, 0xFF, 0xFF]) {
+            output.truncate(output.len() - 4);
+        }
+        Some(output)
+    }
+
+    /// Inflates a compressed message of at most `max_size` bytes.
+    pub(super) fn decompress(
+        &mut self,
+        data: &[u8],
+        max_size: usize,
+    ) -> Result<Vec<u8>, WebSocketError> {
+        let input = [data, &[0, 0, 0xFF, 0xFF]].concat();
+        let start = self.decompress.total_in();
+        let mut output = Vec::with_capacity(data.len().saturating_mul(2).min(max_size) + 64);
+
+        loop {
+            if output.capacity() - output.len() < 1024 {
+                output.reserve(output.capacity());
+            }
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let produced = output.len();
+            let status = self
+                .decompress
+                .decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|_| WebSocketError::InvalidCompressedData)?;
+
+            if output.len() > max_size {
+                return Err(WebSocketError::MessageTooBig);
+            }
+            let now_consumed = (self.decompress.total_in() - start) as usize;
+            if status == Status::StreamEnd {
+                // A final block ends the context, whatever was negotiated.
+                self.decompress.reset(false);
+                return Ok(output);
+            }
+            if now_consumed == input.len() && output.len() < output.capacity() {
+                break;
+            }
+            if now_consumed == consumed && output.len() == produced {
+                return Err(WebSocketError::InvalidCompressedData);
+            }
+        }
+        if self.reset_decompress {
+            self.decompress.reset(false);
+        }
+        Ok(output)
+    }
+}
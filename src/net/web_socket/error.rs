@@ -0,0 +1,71 @@
+use std::{error::Error, fmt, io};
+
+use super::CloseCode;
+
+/// An error on a WebSocket connection.
+#[derive(Debug)]
+pub enum WebSocketError {
+    Io(io::Error),
+    /// The peer violated RFC 6455.
+    Protocol(&'static str),
+    /// A text message or close reason is not valid UTF-8.
+    InvalidUtf8,
+    /// A compressed message could not be inflated.
+    InvalidCompressedData,
+    /// A frame or message is larger than allowed.
+    MessageTooBig,
+    /// A control frame payload is longer than 125 bytes.
+    ControlFrameTooLarge,
+    /// The connection is closing or closed.
+    Closed,
+    /// The send queue is full. The message was not queued.
+    QueueFull,
+    /// A message was sent as [`MessageDataType::Continue`](super::MessageDataType::Continue),
+    /// which cannot start a message.
+    ContinuationStart,
+}
+
+impl WebSocketError {
+    /// The code to close the connection with.
+    pub fn close_code(&self) -> CloseCode {
+        match self {
+            Self::Protocol(_) | Self::ControlFrameTooLarge => CloseCode::ProtocolError,
+            Self::InvalidUtf8 | Self::InvalidCompressedData => CloseCode::InvalidPayload,
+            Self::MessageTooBig => CloseCode::MessageTooBig,
+            Self::Io(_) | Self::Closed | Self::QueueFull | Self::ContinuationStart => {
+                CloseCode::Abnormal
+            }
+        }
+    }
+}
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Io(e) => return write!(f, "io error: {e}"),
+            Self::Protocol(message) => return write!(f, "protocol error: {message}"),
+            Self::InvalidUtf8 => "invalid UTF-8",
+            Self::InvalidCompressedData => "invalid compressed data",
+            Self::MessageTooBig => "message too big",
+            Self::ControlFrameTooLarge => "control frame too large",
+            Self::Closed => "connection closed",
+            Self::QueueFull => "send queue full",
+            Self::ContinuationStart => "a message cannot start with a continuation frame",
+        })
+    }
+}
+
+impl Error for WebSocketError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WebSocketError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
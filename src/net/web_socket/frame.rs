@@ -0,0 +1,336 @@
+use super::WebSocketError;
+
+/// The largest payload of a control frame.
+pub const MAX_CONTROL_PAYLOAD: usize = 125;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    /// Returns `None` for reserved opcodes.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0x0 => Self::Continuation,
+            0x1 => Self::Text,
+            0x2 => Self::Binary,
+            0x8 => Self::Close,
+            0x9 => Self::Ping,
+            0xA => Self::Pong,
+            _ => return None,
+        })
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+
+    pub fn is_control(self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+/// The status code of a close frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseCode {
+    Normal,
+    GoingAway,
+    ProtocolError,
+    /// The endpoint cannot accept this type of data.
+    Unsupported,
+    /// No code was sent. Never sent on the wire.
+    NoStatus,
+    /// The connection dropped without a close frame. Never sent on the wire.
+    Abnormal,
+    /// A message did not match its type, like invalid UTF-8 in a text message.
+    InvalidPayload,
+    PolicyViolation,
+    MessageTooBig,
+    /// The client expected an extension the server did not negotiate.
+    MandatoryExtension,
+    InternalError,
+    Other(u16),
+}
+
+impl CloseCode {
+    /// Whether the code may appear in a close frame.
+    pub fn is_valid(self) -> bool {
+        matches!(u16::from(self), 1000..=1003 | 1007..=1014 | 3000..=4999)
+    }
+}
+
+impl From<u16> for CloseCode {
+    fn from(code: u16) -> Self {
+        match code {
+            1000 => Self::Normal,
+            1001 => Self::GoingAway,
+            1002 => Self::ProtocolError,
+            1003 => Self::Unsupported,
+            1005 => Self::NoStatus,
+            1006 => Self::Abnormal,
+            1007 => Self::InvalidPayload,
+            1008 => Self::PolicyViolation,
+            1009 => Self::MessageTooBig,
+            1010 => Self::MandatoryExtension,
+            1011 => Self::InternalError,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(code: CloseCode) -> Self {
+        match code {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::Unsupported => 1003,
+            CloseCode::NoStatus => 1005,
+            CloseCode::Abnormal => 1006,
+            CloseCode::InvalidPayload => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::MessageTooBig => 1009,
+            CloseCode::MandatoryExtension => 1010,
+            CloseCode::InternalError => 1011,
+            CloseCode::Other(code) => code,
+        }
+    }
+}
+
+/// A single unmasked frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    /// Marks a compressed message on its first frame.
+    pub rsv1: bool,
+    pub opcode: OpCode,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// A final frame.
+    pub fn new(opcode: OpCode, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            fin: true,
+            rsv1: false,
+            opcode,
+            payload: payload.into(),
+        }
+    }
+
+    /// A close frame, without a payload for `None` or codes that must not be sent. The reason
+    /// is cut to fit into a control frame.
+    pub fn close(code: Option<CloseCode>, reason: &str) -> Self {
+        let mut payload = Vec::new();
+        if let Some(code) = code.filter(|code| code.is_valid()) {
+            let mut len = reason.len().min(MAX_CONTROL_PAYLOAD - 2);
+            while !reason.is_char_boundary(len) {
+                len -= 1;
+            }
+            payload.extend_from_slice(&u16::from(code).to_be_bytes());
+            payload.extend_from_slice(&reason.as_bytes()[..len]);
+        }
+        Self::new(OpCode::Close, payload)
+    }
+
+    /// Parses the payload of a close frame into its code and reason.
+    pub fn close_payload(&self) -> Result<(Option<CloseCode>, &str), WebSocketError> {
+        match self.payload.as_slice() {
+            [] => Ok((None, "")),
+            [_] => Err(WebSocketError::Protocol(
+                "close frame with a one byte payload",
+            )),
+            [high, low, reason @ ..] => {
+                let code = CloseCode::from(u16::from_be_bytes([*high, *low]));
+                if !code.is_valid() {
+                    return Err(WebSocketError::Protocol("invalid close code"));
+                }
+                let reason =
+                    std::str::from_utf8(reason).map_err(|_| WebSocketError::InvalidUtf8)?;
+                Ok((Some(code), reason))
+            }
+        }
+    }
+
+    pub fn encode(&self, masked: bool) -> Vec<u8> {
+        let fin = if self.fin { 0b10000000 } else { 0 };
+        let rsv1 = if self.rsv1 { 0b01000000 } else { 0 };
+        encode_frame(fin | rsv1 | self.opcode.as_u8(), &self.payload, masked)
+    }
+}
+
+/// Encodes a single frame. Masked frames get a random masking key.
+pub(super) fn encode_frame(first_byte: u8, payload: &[u8], masked: bool) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    let mask_bit = if masked { 0b10000000 } else { 0 };
+    frame.push(first_byte);
+
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=65535 => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    if masked {
+        let mask: [u8; 4] = rand::random();
+        frame.extend_from_slice(&mask);
+        frame.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4]),
+        );
+    } else {
+        frame.extend_from_slice(payload);
+    }
+    frame
+}
+
+/// Splits a byte stream into frames.
+///
+/// Checks everything that can be checked on a single frame: reserved bits and opcodes,
+/// control frame rules, masking and the frame size.
+#[derive(Debug)]
+pub struct FrameReader {
+    buffer: Vec<u8>,
+    start: usize,
+    masked: bool,
+    max_frame_size: usize,
+    rsv1: bool,
+}
+
+impl FrameReader {
+    /// A reader for frames sent by a client if `masked`, or by a server otherwise.
+    pub fn new(masked: bool, max_frame_size: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            start: 0,
+            masked,
+            max_frame_size,
+            rsv1: false,
+        }
+    }
+
+    /// Accepts the first frame of a data message with RSV1 set, as used by compression.
+    pub fn allow_rsv1(&mut self, allow: bool) {
+        self.rsv1 = allow;
+    }
+
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        if self.start > 0 && self.start >= self.buffer.len() / 2 {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// The number of bytes not consumed by a frame yet.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Returns the next complete frame, or `None` if more data is needed.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, WebSocketError> {
+        let data = &self.buffer[self.start..];
+        let [first, second, ..] = *data else {
+            return Ok(None);
+        };
+
+        let fin = first & 0b10000000 != 0;
+        let rsv1 = first & 0b01000000 != 0;
+        let opcode = OpCode::from_u8(first & 0b00001111)
+            .ok_or(WebSocketError::Protocol("reserved opcode"))?;
+        let rsv1_allowed = self.rsv1 && matches!(opcode, OpCode::Text | OpCode::Binary);
+        if first & 0b00110000 != 0 || (rsv1 && !rsv1_allowed) {
+            return Err(WebSocketError::Protocol("reserved bits set"));
+        }
+        let masked = second & 0b10000000 != 0;
+
+        let (len, mut offset) = match second & 0b01111111 {
+            126 => match data.get(2..4) {
+                Some(len) => (u16::from_be_bytes([len[0], len[1]]) as u64, 4),
+                None => return Ok(None),
+            },
+            127 => match data.get(2..10) {
+                Some(len) => (u64::from_be_bytes(len.try_into().unwrap()), 10),
+                None => return Ok(None),
+            },
+            len => (len as u64, 2),
+        };
+
+        if opcode.is_control() {
+            if !fin {
+                return Err(WebSocketError::Protocol("fragmented control frame"));
+            }
+            if len > MAX_CONTROL_PAYLOAD as u64 {
+                return Err(WebSocketError::ControlFrameTooLarge);
+            }
+        }
+        if len >> 63 != 0 {
+            return Err(WebSocketError::Protocol("invalid payload length"));
+        }
+        if len > self.max_frame_size as u64 {
+            return Err(WebSocketError::MessageTooBig);
+        }
+        if masked != self.masked {
+            return Err(WebSocketError::Protocol(if self.masked {
+                "unmasked client frame"
+            } else {
+                "masked server frame"
+            }));
+        }
+
+        let mask = if masked {
+            let Some(mask) = data.get(offset..offset + 4) else {
+                return Ok(None);
+            };
+            offset += 4;
+            Some([mask[0], mask[1], mask[2], mask[3]])
+        } else {
+            None
+        };
+
+        let len = len as usize;
+        let Some(payload) = data.get(offset..offset + len) else {
+            return Ok(None);
+        };
+        let mut payload = payload.to_vec();
+        if let Some(mask) = mask {
+            payload
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, byte)| *byte ^= mask[i % 4]);
+        }
+
+        self.start += offset + len;
+        Ok(Some(Frame {
+            fin,
+            rsv1,
+            opcode,
+            payload,
+        }))
+    }
+}
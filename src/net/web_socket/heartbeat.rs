@@ -0,0 +1,155 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How many unanswered pings are remembered for measuring the round-trip time.
+const MAX_PENDING_PINGS: usize = 16;
+
+/// Keep-alive settings of a connection.
+///
+/// With both `ping_interval` and `idle_timeout`, live peers answer the pings in time and only
+/// dead ones are closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketTimeouts {
+    /// Sends a ping at this interval, which must not be zero.
+    pub ping_interval: Option<Duration>,
+    /// Closes the connection with [`CloseCode::GoingAway`](super::CloseCode::GoingAway) if
+    /// nothing was received for this long.
+    pub idle_timeout: Option<Duration>,
+    /// Limits the opening handshake, and drops the connection if the peer does not answer a
+    /// close frame in time.
+    pub handshake_timeout: Option<Duration>,
+}
+
+impl Default for WebSocketTimeouts {
+    fn default() -> Self {
+        Self {
+            ping_interval: None,
+            idle_timeout: None,
+            handshake_timeout: Some(Duration::from_secs(5)),
+        }
+    }
+}
+
+/// Traffic counters of a connection, after the opening handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketStats {
+    pub connected_since: SystemTime,
+    /// Bytes on the wire, including frame headers.
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Complete text and binary messages.
+    pub messages_in: u64,
+    pub messages_out: u64,
+    /// The time between the last answered ping and its pong.
+    pub last_rtt: Option<Duration>,
+}
+
+/// What a connection has to do at some point in time.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Due {
+    /// Nothing until the given time passed.
+    Wait(Option<Duration>),
+    Ping(Vec<u8>),
+    /// Nothing was received for too long.
+    Idle,
+    /// The peer did not answer the close frame.
+    Expired,
+}
+
+/// The timers and incoming counters of a connection.
+#[derive(Debug)]
+pub(super) struct Heartbeat {
+    pub(super) timeouts: WebSocketTimeouts,
+    connected_since: SystemTime,
+    last_received: Instant,
+    last_ping: Instant,
+    closing_since: Option<Instant>,
+    next_ping: u64,
+    /// Payloads of unanswered pings, oldest first.
+    pings: VecDeque<(Vec<u8>, Instant)>,
+    pub(super) bytes_in: u64,
+    pub(super) messages_in: u64,
+    pub(super) last_rtt: Option<Duration>,
+}
+
+impl Heartbeat {
+    pub(super) fn new(timeouts: WebSocketTimeouts, now: Instant) -> Self {
+        Self {
+            timeouts,
+            connected_since: SystemTime::now(),
+            last_received: now,
+            last_ping: now,
+            closing_since: None,
+            next_ping: 0,
+            pings: VecDeque::new(),
+            bytes_in: 0,
+            messages_in: 0,
+            last_rtt: None,
+        }
+    }
+
+    pub(super) fn connected_since(&self) -> SystemTime {
+        self.connected_since
+    }
+
+    pub(super) fn received(&mut self, len: usize, now: Instant) {
+        self.bytes_in += len as u64;
+        self.last_received = now;
+    }
+
+    pub(super) fn ping_sent(&mut self, payload: &[u8], now: Instant) {
+        if self.pings.len() == MAX_PENDING_PINGS {
+            self.pings.pop_front();
+        }
+        self.pings.push_back((payload.to_vec(), now));
+    }
+
+    /// Measures the round-trip time if `payload` belongs to a ping. Older pings are forgotten,
+    /// as their pongs will not come anymore.
+    pub(super) fn pong_received(&mut self, payload: &[u8], now: Instant) {
+        if let Some(index) = self.pings.iter().position(|(ping, _)| ping == payload) {
+            let (_, sent) = self.pings[index];
+            self.last_rtt = Some(now - sent);
+            self.pings.drain(..=index);
+        }
+    }
+
+    pub(super) fn close_sent(&mut self, now: Instant) {
+        self.closing_since.get_or_insert(now);
+    }
+
+    /// Returns what is due at `now`. A ping that is due counts as sent.
+    pub(super) fn due(&mut self, now: Instant) -> Due {
+        let timeouts = &self.timeouts;
+        let mut deadlines = Vec::new();
+
+        if let Some(since) = self.closing_since {
+            // Only the closing handshake is left to wait for.
+            return match timeouts.handshake_timeout {
+                Some(timeout) if now >= since + timeout => Due::Expired,
+                Some(timeout) => Due::Wait(Some(since + timeout - now)),
+                None => Due::Wait(None),
+            };
+        }
+
+        if let Some(timeout) = timeouts.idle_timeout {
+            let deadline = self.last_received + timeout;
+            if now >= deadline {
+                return Due::Idle;
+            }
+            deadlines.push(deadline);
+        }
+        if let Some(interval) = timeouts.ping_interval {
+            let deadline = self.last_ping + interval;
+            if now >= deadline {
+                self.last_ping = now;
+                self.next_ping += 1;
+                return Due::Ping(self.next_ping.to_be_bytes().to_vec());
+            }
+            deadlines.push(deadline);
+        }
+        Due::Wait(deadlines.into_iter().min().map(|deadline| deadline - now))
+    }
+}
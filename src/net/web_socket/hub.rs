@@ -0,0 +1,201 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use super::{Message, WebSocket, WebSocketError, WebSocketHandler};
+
+/// Identifies a connection in a [`WebSocketHub`].
+pub type ConnectionId = u64;
+
+/// Tracks connections by id and groups them into named rooms for broadcasting.
+///
+/// The hub sends through clones of the added sockets, which share the send queue of the
+/// original, so messages are written by the [`WebSocket::run`] of the connection. Cloning the
+/// hub is cheap and shares the connections.
+#[derive(Debug, Clone, Default)]
+pub struct WebSocketHub {
+    state: Arc<Mutex<HubState>>,
+}
+
+#[derive(Debug, Default)]
+struct HubState {
+    next_id: ConnectionId,
+    connections: HashMap<ConnectionId, Connection>,
+    rooms: HashMap<String, BTreeSet<ConnectionId>>,
+}
+
+#[derive(Debug)]
+struct Connection {
+    websocket: WebSocket,
+    rooms: BTreeSet<String>,
+}
+
+impl WebSocketHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a connection and returns its id.
+    pub fn add(&self, websocket: &WebSocket) -> ConnectionId {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        let connection = Connection {
+            websocket: websocket.clone(),
+            rooms: BTreeSet::new(),
+        };
+        state.connections.insert(id, connection);
+        id
+    }
+
+    /// Removes a connection from the hub and all of its rooms. Returns whether it was known.
+    pub fn remove(&self, id: ConnectionId) -> bool {
+        self.state.lock().unwrap().remove(id)
+    }
+
+    /// Runs `handler` with [`WebSocket::run`] and removes the connection once it closed.
+    pub fn run(&self, id: ConnectionId, handler: Arc<RwLock<impl WebSocketHandler>>) {
+        WebSocket::run(handler);
+        self.remove(id);
+    }
+
+    pub fn contains(&self, id: ConnectionId) -> bool {
+        self.state.lock().unwrap().connections.contains_key(&id)
+    }
+
+    /// The number of connections.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds a connection to a room, which is created if needed. Returns false for unknown ids.
+    pub fn join(&self, id: ConnectionId, room: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(connection) = state.connections.get_mut(&id) else {
+            return false;
+        };
+        connection.rooms.insert(room.to_string());
+        state.rooms.entry(room.to_string()).or_default().insert(id);
+        true
+    }
+
+    /// Removes a connection from a room. Empty rooms are dropped. Returns whether the
+    /// connection was in the room.
+    pub fn leave(&self, id: ConnectionId, room: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(connection) = state.connections.get_mut(&id) else {
+            return false;
+        };
+        connection.rooms.remove(room) && state.leave_room(id, room)
+    }
+
+    /// The ids of the connections in a room, in ascending order.
+    pub fn members(&self, room: &str) -> Vec<ConnectionId> {
+        let state = self.state.lock().unwrap();
+        state
+            .rooms
+            .get(room)
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// The rooms a connection joined, in ascending order.
+    pub fn rooms(&self, id: ConnectionId) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .connections
+            .get(&id)
+            .map(|connection| connection.rooms.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Sends a message to one connection.
+    ///
+    /// Closed connections are removed and fail with [`WebSocketError::Closed`].
+    pub fn send(&self, id: ConnectionId, message: &Message) -> Result<(), WebSocketError> {
+        let mut state = self.state.lock().unwrap();
+        let connection = state
+            .connections
+            .get_mut(&id)
+            .ok_or(WebSocketError::Closed)?;
+        let result = connection.websocket.send_message(message);
+        if let Err(WebSocketError::Closed) = result {
+            state.remove(id);
+        }
+        result
+    }
+
+    /// Sends a message to every connection but `except` and returns how many it was queued for.
+    pub fn broadcast(&self, message: &Message, except: Option<ConnectionId>) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let ids: Vec<_> = state.connections.keys().copied().collect();
+        state.send_all(ids, message, except)
+    }
+
+    /// Sends a message to every connection in a room but `except` and returns how many it was
+    /// queued for.
+    pub fn broadcast_room(
+        &self,
+        room: &str,
+        message: &Message,
+        except: Option<ConnectionId>,
+    ) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let ids: Vec<_> = match state.rooms.get(room) {
+            Some(members) => members.iter().copied().collect(),
+            None => return 0,
+        };
+        state.send_all(ids, message, except)
+    }
+}
+
+impl HubState {
+    fn remove(&mut self, id: ConnectionId) -> bool {
+        let Some(connection) = self.connections.remove(&id) else {
+            return false;
+        };
+        for room in &connection.rooms {
+            self.leave_room(id, room);
+        }
+        true
+    }
+
+    fn leave_room(&mut self, id: ConnectionId, room: &str) -> bool {
+        let Some(members) = self.rooms.get_mut(room) else {
+            return false;
+        };
+        let removed = members.remove(&id);
+        if members.is_empty() {
+            self.rooms.remove(room);
+        }
+        removed
+    }
+
+    /// Connections with a full send queue are skipped, closed ones removed.
+    fn send_all(
+        &mut self,
+        ids: Vec<ConnectionId>,
+        message: &Message,
+        except: Option<ConnectionId>,
+    ) -> usize {
+        let mut sent = 0;
+        for id in ids.into_iter().filter(|&id| Some(id) != except) {
+            let Some(connection) = self.connections.get_mut(&id) else {
+                continue;
+            };
+            match connection.websocket.send_message(message) {
+                Ok(()) => sent += 1,
+                Err(WebSocketError::Closed) => {
+                    self.remove(id);
+                }
+                Err(_) => {}
+            }
+        }
+        sent
+    }
+}
@@ -0,0 +1,904 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use sha1_smol::Sha1;
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{HTTPRequest, Headers};
+
+mod deflate;
+mod error;
+mod frame;
+mod heartbeat;
+mod hub;
+mod poller;
+mod queue;
+
+pub use deflate::DeflateConfig;
+pub use error::WebSocketError;
+pub use frame::{CloseCode, Frame, FrameReader, MAX_CONTROL_PAYLOAD, OpCode};
+pub use heartbeat::{WebSocketStats, WebSocketTimeouts};
+pub use hub::{ConnectionId, WebSocketHub};
+pub use poller::WebSocketPoller;
+
+use deflate::Deflate;
+use frame::encode_frame;
+use heartbeat::{Due, Heartbeat};
+use queue::SendQueue;
+
+/// How long [`WebSocket::run`] waits for data before it checks its timers.
+const READ_TIMEOUT: Duration = Duration::from_millis(1000);
+
+#[derive(Debug)]
+pub struct WebSocket {
+    stream: TcpStream,
+    peer: SocketAddr,
+    send_queue: Arc<SendQueue>,
+    /// Clients mask the frames they send and expect unmasked frames from the server.
+    client: bool,
+    reader: FrameReader,
+    limits: WebSocketLimits,
+    message: Option<PartialMessage>,
+    deflate: Option<Deflate>,
+    close_sent: bool,
+    close_received: bool,
+    heartbeat: Heartbeat,
+    /// The peer did not answer the close frame in time and the stream was shut down.
+    timed_out: bool,
+}
+
+#[allow(dead_code)]
+impl WebSocket {
+    /// Answers an opening handshake whose request was read already. Gives up if the answer
+    /// cannot be written within the default
+    /// [`handshake_timeout`](WebSocketTimeouts::handshake_timeout).
+    pub fn try_connect(stream: TcpStream, handshake_key: &str) -> Option<Self> {
+        Self::answer(
+            stream,
+            handshake_key,
+            "",
+            None,
+            &WebSocketTimeouts::default(),
+        )
+        .ok()
+    }
+
+    /// Answers the opening handshake of `request`.
+    ///
+    /// With a [`DeflateConfig`], `permessage-deflate` is used if the client offers it with
+    /// parameters that fit the config. Fails if the answer cannot be written within the default
+    /// [`handshake_timeout`](WebSocketTimeouts::handshake_timeout).
+    pub fn accept(
+        stream: TcpStream,
+        request: &HTTPRequest,
+        deflate: Option<&DeflateConfig>,
+    ) -> io::Result<Self> {
+        Self::accept_with(stream, request, deflate, &WebSocketTimeouts::default())
+    }
+
+    /// Like [`accept`](Self::accept), but with `timeouts` for the handshake and the connection.
+    pub fn accept_with(
+        stream: TcpStream,
+        request: &HTTPRequest,
+        deflate: Option<&DeflateConfig>,
+        timeouts: &WebSocketTimeouts,
+    ) -> io::Result<Self> {
+        let key = request
+            .websocket_key()
+            .ok_or_else(|| handshake_error("not an upgrade request"))?;
+        let offers = request
+            .headers
+            .get_all("sec-websocket-extensions")
+            .collect::<Vec<_>>()
+            .join(",");
+        Self::answer(stream, key, &offers, deflate, timeouts)
+    }
+
+    /// The server side of the opening handshake, shared by [`accept`](Self::accept) and
+    /// [`try_connect`](Self::try_connect). `offers` are the extensions the client offered.
+    fn answer(
+        mut stream: TcpStream,
+        key: &str,
+        offers: &str,
+        deflate: Option<&DeflateConfig>,
+        timeouts: &WebSocketTimeouts,
+    ) -> io::Result<Self> {
+        let agreed = deflate.and_then(|config| config.accept(offers));
+
+        let extension = agreed.as_ref().map(|(_, response)| response.as_str());
+        switch_protocols(&mut stream, key, extension, timeouts.handshake_timeout)?;
+
+        let mut websocket = Self::with_role(stream, false).timeouts(timeouts.clone());
+        if let Some((config, _)) = agreed {
+            websocket.enable_deflate(config);
+        }
+        Ok(websocket)
+    }
+
+    /// Opens a client connection to a `ws://host:port/path` url.
+    ///
+    /// Fails if the server does not switch protocols within the default
+    /// [`handshake_timeout`](WebSocketTimeouts::handshake_timeout) or answers with a wrong
+    /// `Sec-WebSocket-Accept`. `wss://` urls are not supported.
+    pub fn connect(url: &str) -> io::Result<Self> {
+        Self::connect_with(url, None, &WebSocketTimeouts::default())
+    }
+
+    /// Like [`connect`](Self::connect), but offers `permessage-deflate` with the given
+    /// parameters if there are any, and uses `timeouts` for the handshake and the connection.
+    pub fn connect_with(
+        url: &str,
+        deflate: Option<&DeflateConfig>,
+        timeouts: &WebSocketTimeouts,
+    ) -> io::Result<Self> {
+        let (host, port, path) = parse_url(url)?;
+        let mut stream = TcpStream::connect((host.trim_matches(['[', ']']), port))?;
+        let handshake_timeout = timeouts.handshake_timeout;
+        let deadline = handshake_timeout.map(|timeout| Instant::now() + timeout);
+        stream.set_write_timeout(handshake_timeout)?;
+
+        let key = STANDARD.encode(rand::random::<[u8; 16]>());
+        let host_header = if port == 80 {
+            host
+        } else {
+            format!("{host}:{port}")
+        };
+        let extension = deflate
+            .map(|config| format!("Sec-WebSocket-Extensions: {}\r\n", config.offer()))
+            .unwrap_or_default();
+        let request = format!(
+            "GET {path} HTTP/1.1\r\nHost: {host_header}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n{extension}\r\n"
+        );
+        stream.write_all(request.as_bytes())?;
+        stream.flush()?;
+
+        let headers = read_handshake_response(&mut stream, deadline)?;
+        let upgraded =
+            headers.has_token("upgrade", "websocket") && headers.has_token("connection", "upgrade");
+        if !upgraded {
+            return Err(handshake_error("missing upgrade headers"));
+        }
+        if headers.get("sec-websocket-accept") != Some(accept_key(&key).as_str()) {
+            return Err(handshake_error("invalid Sec-WebSocket-Accept"));
+        }
+
+        let extensions = headers
+            .get_all("sec-websocket-extensions")
+            .collect::<Vec<_>>()
+            .join(",");
+        let agreed = match deflate {
+            _ if extensions.is_empty() => None,
+            Some(config) => Some(config.confirm(&extensions).map_err(handshake_error)?),
+            None => return Err(handshake_error("extension that was not offered")),
+        };
+
+        stream.set_write_timeout(None)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut websocket = Self::with_role(stream, true).timeouts(timeouts.clone());
+        if let Some(config) = agreed {
+            websocket.enable_deflate(config);
+        }
+        Ok(websocket)
+    }
+
+    pub fn new(stream: TcpStream) -> Self {
+        Self::with_role(stream, false)
+    }
+
+    fn with_role(stream: TcpStream, client: bool) -> Self {
+        let limits = WebSocketLimits::default();
+        Self {
+            // Kept for after the connection closed, when the stream no longer knows its peer.
+            peer: stream
+                .peer_addr()
+                .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0))),
+            stream,
+            send_queue: Arc::default(),
+            client,
+            reader: FrameReader::new(!client, limits.max_frame_size),
+            limits,
+            message: None,
+            deflate: None,
+            close_sent: false,
+            close_received: false,
+            heartbeat: Heartbeat::new(WebSocketTimeouts::default(), Instant::now()),
+            timed_out: false,
+        }
+    }
+
+    fn enable_deflate(&mut self, config: DeflateConfig) {
+        self.reader.allow_rsv1(true);
+        self.deflate = Some(Deflate::new(config, self.client));
+    }
+
+    /// The negotiated `permessage-deflate` parameters, if the extension is used.
+    pub fn deflate(&self) -> Option<&DeflateConfig> {
+        self.deflate.as_ref().map(Deflate::config)
+    }
+
+    /// Replaces the default [`WebSocketLimits`].
+    ///
+    /// # Panics
+    /// If `max_send_frame_size` is zero.
+    pub fn limits(mut self, limits: WebSocketLimits) -> Self {
+        assert!(
+            limits.max_send_frame_size > 0,
+            "max_send_frame_size is zero"
+        );
+        self.reader.set_max_frame_size(limits.max_frame_size);
+        self.limits = limits;
+        self
+    }
+
+    /// Replaces the default [`WebSocketTimeouts`], which send no pings, keep idle connections
+    /// open and give each handshake 5 seconds. They are enforced by [`run`](Self::run) and
+    /// [`WebSocketPoller`]. The opening handshake is over by now; to give it other timeouts,
+    /// use [`accept_with`](Self::accept_with) or [`connect_with`](Self::connect_with).
+    ///
+    /// # Panics
+    /// If `ping_interval` is zero.
+    pub fn timeouts(mut self, timeouts: WebSocketTimeouts) -> Self {
+        assert!(
+            timeouts.ping_interval != Some(Duration::ZERO),
+            "ping_interval is zero"
+        );
+        self.heartbeat.timeouts = timeouts;
+        self
+    }
+
+    /// The traffic of the connection so far. Outgoing counters include what clones sent.
+    pub fn stats(&self) -> WebSocketStats {
+        let (bytes_out, messages_out) = self.send_queue.sent();
+        WebSocketStats {
+            connected_since: self.heartbeat.connected_since(),
+            bytes_in: self.heartbeat.bytes_in,
+            bytes_out,
+            messages_in: self.heartbeat.messages_in,
+            messages_out,
+            last_rtt: self.heartbeat.last_rtt,
+        }
+    }
+
+    /// Whether this is the client side of the connection.
+    pub fn is_client(&self) -> bool {
+        self.client
+    }
+
+    /// Whether the closing handshake finished or the connection failed.
+    pub fn is_closed(&self) -> bool {
+        self.close_sent && self.close_received
+    }
+
+    /// Starts the closing handshake with [`CloseCode::Normal`].
+    pub fn close(&mut self) {
+        self.close_with(CloseCode::Normal, "");
+    }
+
+    /// Starts the closing handshake. Does nothing if a close frame was sent already.
+    pub fn close_with(&mut self, code: CloseCode, reason: &str) {
+        if !self.close_sent {
+            self.close_sent = true;
+            self.heartbeat.close_sent(Instant::now());
+            // A clone may have closed the connection already.
+            let _ = self.queue(&Frame::close(Some(code), reason));
+        }
+    }
+
+    /// Queues a ping with a payload of at most 125 bytes.
+    pub fn send_ping(&mut self, payload: &[u8]) -> Result<(), WebSocketError> {
+        self.send_control(OpCode::Ping, payload)
+    }
+
+    /// Queues an unsolicited pong with a payload of at most 125 bytes. Pings are answered
+    /// automatically.
+    pub fn send_pong(&mut self, payload: &[u8]) -> Result<(), WebSocketError> {
+        self.send_control(OpCode::Pong, payload)
+    }
+
+    fn send_control(&mut self, opcode: OpCode, payload: &[u8]) -> Result<(), WebSocketError> {
+        if payload.len() > MAX_CONTROL_PAYLOAD {
+            return Err(WebSocketError::ControlFrameTooLarge);
+        }
+        if self.close_sent {
+            return Err(WebSocketError::Closed);
+        }
+        self.queue(&Frame::new(opcode, payload))?;
+        if opcode == OpCode::Ping {
+            self.heartbeat.ping_sent(payload, Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Queues a message, split into frames of at most
+    /// [`max_send_frame_size`](WebSocketLimits::max_send_frame_size) bytes.
+    ///
+    /// Fails with [`WebSocketError::QueueFull`] if more than
+    /// [`max_send_queue`](WebSocketLimits::max_send_queue) bytes would wait to be written, and
+    /// with [`WebSocketError::Closed`] after a close frame. Connections driven by
+    /// [`run`](Self::run) write the message right away; otherwise call [`flush`](Self::flush).
+    ///
+    /// [`MessageDataType::Continue`] fails with [`WebSocketError::ContinuationStart`], as every
+    /// message is sent whole.
+    pub fn send(
+        &mut self,
+        message: &[u8],
+        msg_type: MessageDataType,
+    ) -> Result<(), WebSocketError> {
+        if msg_type == MessageDataType::Continue {
+            return Err(WebSocketError::ContinuationStart);
+        }
+        if self.close_sent {
+            return Err(WebSocketError::Closed);
+        }
+        // Checked before compressing, as a message the peer never gets must not change the
+        // compression context.
+        self.send_queue
+            .check_room(message.len(), self.limits.max_send_queue)?;
+
+        let mut first_byte = msg_type as u8;
+        let compressed = match (&mut self.deflate, msg_type) {
+            (Some(deflate), MessageDataType::Text | MessageDataType::Binary) => {
+                deflate.compress(message)
+            }
+            _ => None,
+        };
+        if compressed.is_some() {
+            first_byte |= 0b01000000;
+        }
+        let payload = compressed.as_deref().unwrap_or(message);
+
+        let size = self.limits.max_send_frame_size;
+        let count = payload.len().div_ceil(size).max(1);
+        let frames = (0..count).map(|index| {
+            let chunk = &payload[(index * size).min(payload.len())..];
+            let chunk = &chunk[..chunk.len().min(size)];
+            // Only the first frame carries the opcode and the compression bit.
+            let mut first_byte = if index == 0 { first_byte } else { 0 };
+            if index == count - 1 {
+                first_byte |= 0b10000000;
+            }
+            encode_frame(first_byte, chunk, self.client)
+        });
+        self.send_queue.push_message(frames)
+    }
+
+    pub fn send_message(&mut self, message: &Message) -> Result<(), WebSocketError> {
+        match message {
+            Message::Text(text) => self.send(text.as_bytes(), MessageDataType::Text),
+            Message::Binary(data) => self.send(data, MessageDataType::Binary),
+        }
+    }
+
+    fn queue(&mut self, frame: &Frame) -> Result<(), WebSocketError> {
+        let close = frame.opcode == OpCode::Close;
+        self.send_queue
+            .push_control(frame.encode(self.client), close)
+    }
+
+    /// The number of bytes waiting to be written, including those of clones.
+    pub fn queued_bytes(&self) -> usize {
+        self.send_queue.bytes()
+    }
+
+    /// Writes the queued frames from the calling thread, for connections that are not driven
+    /// by [`run`](Self::run).
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.send_queue.write_to(&self.stream)
+    }
+
+    /// Sends a close frame and shuts the connection down without waiting for the answer.
+    pub(crate) fn abort(&mut self, code: CloseCode, reason: &str) {
+        self.close_with(code, reason);
+        // A peer that stopped reading must not hold this up.
+        let _ = self.stream.set_write_timeout(Some(READ_TIMEOUT));
+        let _ = self.flush();
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    /// Adds data read from the stream.
+    fn receive(&mut self, data: &[u8]) {
+        self.heartbeat.received(data.len(), Instant::now());
+        self.reader.feed(data);
+    }
+
+    /// Sends due pings and enforces the timeouts. Returns how long until the next check is
+    /// due, if ever.
+    fn check_timeouts(&mut self, now: Instant) -> Option<Duration> {
+        loop {
+            match self.heartbeat.due(now) {
+                Due::Wait(wait) => return wait,
+                Due::Ping(payload) => {
+                    let _ = self.send_ping(&payload);
+                }
+                Due::Idle => self.close_with(CloseCode::GoingAway, "idle timeout"),
+                Due::Expired => {
+                    self.close_received = true;
+                    self.timed_out = true;
+                    // Also ends writes that wait for a peer that stopped reading.
+                    let _ = self.stream.shutdown(Shutdown::Both);
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Returns the next event from the frames read so far.
+    ///
+    /// Pings are answered and close frames echoed on the way.
+    fn next_event(&mut self) -> Result<Option<Event>, WebSocketError> {
+        // Nothing after a close frame is read.
+        while !self.close_received {
+            let Some(frame) = self.reader.next_frame()? else {
+                return Ok(None);
+            };
+
+            match frame.opcode {
+                OpCode::Ping => {
+                    if !self.close_sent {
+                        let _ = self.queue(&Frame::new(OpCode::Pong, frame.payload.as_slice()));
+                    }
+                    return Ok(Some(Event::Ping(frame.payload)));
+                }
+                OpCode::Pong => {
+                    self.heartbeat.pong_received(&frame.payload, Instant::now());
+                    return Ok(Some(Event::Pong(frame.payload)));
+                }
+                OpCode::Close => {
+                    let (code, reason) = frame.close_payload()?;
+                    self.close_received = true;
+                    if !self.close_sent {
+                        self.close_sent = true;
+                        let _ = self.queue(&Frame::close(code, ""));
+                    }
+                    let code = code.unwrap_or(CloseCode::NoStatus);
+                    return Ok(Some(Event::Close(code, reason.to_string())));
+                }
+                _ => {
+                    if let Some(message) = self.assemble(frame)? {
+                        self.heartbeat.messages_in += 1;
+                        return Ok(Some(Event::Message(message)));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Adds a data frame to the current message and returns the message once it is complete.
+    fn assemble(&mut self, frame: Frame) -> Result<Option<Message>, WebSocketError> {
+        let mut message = match (frame.opcode, self.message.take()) {
+            (OpCode::Continuation, Some(message)) => message,
+            (OpCode::Continuation, None) => {
+                return Err(WebSocketError::Protocol("continuation without a message"));
+            }
+            (_, Some(_)) => return Err(WebSocketError::Protocol("expected a continuation")),
+            (opcode, None) => PartialMessage {
+                text: opcode == OpCode::Text,
+                compressed: frame.rsv1,
+                data: Vec::new(),
+                checked: 0,
+            },
+        };
+
+        if message.data.len() + frame.payload.len() > self.limits.max_message_size {
+            return Err(WebSocketError::MessageTooBig);
+        }
+        message.data.extend_from_slice(&frame.payload);
+        // Compressed text can only be checked once it is inflated.
+        if message.text && !message.compressed {
+            message.check_utf8(frame.fin)?;
+        }
+
+        if !frame.fin {
+            self.message = Some(message);
+            return Ok(None);
+        }
+        if message.compressed {
+            let deflate = self
+                .deflate
+                .as_mut()
+                .ok_or(WebSocketError::InvalidCompressedData)?;
+            message.data = deflate.decompress(&message.data, self.limits.max_message_size)?;
+        }
+
+        if message.text {
+            let text = String::from_utf8(message.data).map_err(|_| WebSocketError::InvalidUtf8)?;
+            Ok(Some(Message::Text(text)))
+        } else {
+            Ok(Some(Message::Binary(message.data)))
+        }
+    }
+
+    /// Fails the connection: a close frame with the code for `error` is sent and nothing is read
+    /// anymore.
+    fn fail(&mut self, error: &WebSocketError) {
+        self.close_with(error.close_code(), &error.to_string());
+        self.close_received = true;
+    }
+
+    /// Reads from the connection and calls `handler` until the connection ends.
+    ///
+    /// Queued frames are written by a separate thread as soon as they are sent, also from
+    /// other threads. [`WebSocketHandler::on_close`] is called exactly once, when the peer
+    /// sent a close frame, the connection failed or dropped.
+    pub fn run(handler: Arc<RwLock<impl WebSocketHandler>>) {
+        let (mut stream, send_queue) = {
+            let mut handler = handler.write().unwrap();
+            let ws = handler.websocket_mut();
+            (ws.stream.try_clone().unwrap(), ws.send_queue.clone())
+        };
+        let writer = {
+            let send_queue = send_queue.clone();
+            let stream = stream.try_clone().unwrap();
+            thread::spawn(move || send_queue.write_loop(stream))
+        };
+        let mut buffer = [0; 8192];
+        let mut close_reported = false;
+
+        loop {
+            {
+                let mut handler = handler.write().unwrap();
+                let ws = handler.websocket_mut();
+                let wait = ws.check_timeouts(Instant::now()).unwrap_or(READ_TIMEOUT);
+                if ws.is_closed() {
+                    break;
+                }
+                // Wakes up in time for the next ping or timeout.
+                let timeout = wait.clamp(Duration::from_millis(1), READ_TIMEOUT);
+                let _ = stream.set_read_timeout(Some(timeout));
+            }
+
+            let read = stream.read(&mut buffer);
+            let mut handler = handler.write().unwrap();
+
+            match read {
+                Ok(0) => break,
+                Ok(bytes_read) => {
+                    handler.websocket_mut().receive(&buffer[..bytes_read]);
+                    dispatch(&mut *handler, &mut close_reported);
+                }
+                Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => {
+                    handler.on_error(&WebSocketError::Io(e));
+                    break;
+                }
+            }
+        }
+
+        // The close frame is written before the stream is shut down.
+        send_queue.stop();
+        let _ = writer.join();
+        let _ = stream.shutdown(Shutdown::Write);
+
+        let mut handler = handler.write().unwrap();
+        let timed_out = handler.websocket_mut().timed_out;
+        if let Some(e) = send_queue.take_error().filter(|_| !timed_out) {
+            handler.on_error(&WebSocketError::Io(e));
+        }
+        if !close_reported {
+            handler.on_close(CloseCode::Abnormal, "");
+        }
+    }
+
+    pub fn ip(&self) -> SocketAddr {
+        self.peer
+    }
+}
+
+/// Calls `handler` for the events in the frames read so far. A failed connection is closed
+/// with the code of the error.
+fn dispatch(handler: &mut impl WebSocketHandler, close_reported: &mut bool) {
+    loop {
+        match handler.websocket_mut().next_event() {
+            Ok(Some(Event::Message(message))) => handler.on_message(message),
+            Ok(Some(Event::Ping(payload))) => handler.on_ping(&payload),
+            Ok(Some(Event::Pong(payload))) => handler.on_pong(&payload),
+            Ok(Some(Event::Close(code, reason))) => {
+                *close_reported = true;
+                handler.on_close(code, &reason);
+            }
+            Ok(None) => break,
+            Err(error) => {
+                handler.on_error(&error);
+                handler.websocket_mut().fail(&error);
+                *close_reported = true;
+                handler.on_close(error.close_code(), &error.to_string());
+                break;
+            }
+        }
+    }
+}
+
+/// Cloned sockets share the connection and its send queue, so that messages sent through a
+/// clone are written by [`WebSocket::run`] of the original. They send uncompressed, as the
+/// compression context belongs to the original.
+impl Clone for WebSocket {
+    fn clone(&self) -> Self {
+        let mut websocket = Self::with_role(self.stream.try_clone().unwrap(), self.client)
+            .limits(self.limits.clone())
+            .timeouts(self.heartbeat.timeouts.clone());
+        websocket.send_queue = self.send_queue.clone();
+        websocket
+    }
+}
+
+/// Size limits for frames and messages.
+#[derive(Debug, Clone)]
+pub struct WebSocketLimits {
+    /// The largest incoming frame.
+    pub max_frame_size: usize,
+    /// The largest incoming message after joining its fragments.
+    pub max_message_size: usize,
+    /// Outgoing messages are split into frames of at most this size.
+    pub max_send_frame_size: usize,
+    /// The most bytes waiting to be written before sending fails with
+    /// [`WebSocketError::QueueFull`]. A message is always queued if the queue is empty.
+    pub max_send_queue: usize,
+}
+
+impl Default for WebSocketLimits {
+    fn default() -> Self {
+        Self {
+            max_frame_size: 16 * 1024 * 1024,
+            max_message_size: 64 * 1024 * 1024,
+            max_send_frame_size: 16 * 1024 * 1024,
+            max_send_queue: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// A fragmented message that is not complete yet.
+#[derive(Debug)]
+struct PartialMessage {
+    text: bool,
+    compressed: bool,
+    data: Vec<u8>,
+    /// The length of the data known to be valid UTF-8.
+    checked: usize,
+}
+
+impl PartialMessage {
+    /// Validates the text received so far, so that invalid text fails before the message ends.
+    fn check_utf8(&mut self, fin: bool) -> Result<(), WebSocketError> {
+        match std::str::from_utf8(&self.data[self.checked..]) {
+            Ok(_) => self.checked = self.data.len(),
+            // A character cut at the end of a fragment may be completed by the next one.
+            Err(e) if e.error_len().is_none() && !fin => self.checked += e.valid_up_to(),
+            Err(_) => return Err(WebSocketError::InvalidUtf8),
+        }
+        Ok(())
+    }
+}
+
+/// The `Sec-WebSocket-Accept` value for a `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    let mut sha = Sha1::new();
+    sha.update(key.as_bytes());
+    sha.update(b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11");
+    STANDARD.encode(sha.digest().bytes())
+}
+
+/// Writes the `101 Switching Protocols` answer to a handshake within `timeout`.
+fn switch_protocols(
+    stream: &mut TcpStream,
+    key: &str,
+    extension: Option<&str>,
+    timeout: Option<Duration>,
+) -> io::Result<()> {
+    let extension = extension
+        .map(|extension| format!("Sec-WebSocket-Extensions: {extension}\r\n"))
+        .unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: {}\r\n{extension}\r\n",
+        accept_key(key)
+    );
+    // A peer that does not read cannot hold up the handshake.
+    stream.set_write_timeout(timeout)?;
+    stream.write_all(response.as_bytes())?;
+    stream.flush()?;
+    stream.set_write_timeout(None)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))
+}
+
+/// Splits a `ws://` url into host, port and request target.
+fn parse_url(url: &str) -> io::Result<(String, u16, String)> {
+    let invalid = || io::Error::new(ErrorKind::InvalidInput, format!("invalid url: {url}"));
+
+    let (scheme, rest) = url.split_once("://").ok_or_else(invalid)?;
+    if scheme.eq_ignore_ascii_case("wss") {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "wss:// urls are not supported",
+        ));
+    } else if !scheme.eq_ignore_ascii_case("ws") {
+        return Err(invalid());
+    }
+
+    let (authority, path) = match rest.find(['/', '?']) {
+        Some(index) if rest[index..].starts_with('?') => {
+            (&rest[..index], format!("/{}", &rest[index..]))
+        }
+        Some(index) => (&rest[..index], rest[index..].to_string()),
+        None => (rest, "/".to_string()),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (host, port.parse().map_err(|_| invalid())?),
+        _ => (authority, 80),
+    };
+    if host.is_empty() || path.contains('#') {
+        return Err(invalid());
+    }
+    Ok((host.to_string(), port, path))
+}
+
+/// Reads the response head of the opening handshake byte by byte, so that frames sent right
+/// after it stay in the stream, and returns its headers.
+/// Fails with [`ErrorKind::TimedOut`] if the head is not complete by `deadline`.
+fn read_handshake_response(
+    stream: &mut TcpStream,
+    deadline: Option<Instant>,
+) -> io::Result<Headers> {
+    let mut head = Vec::new();
+    let mut byte = [0];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= 8192 {
+            return Err(handshake_error("response head too large"));
+        }
+        if let Some(deadline) = deadline {
+            let left = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|left| !left.is_zero())
+                .ok_or(ErrorKind::TimedOut)?;
+            stream.set_read_timeout(Some(left))?;
+        }
+        stream.read_exact(&mut byte)?;
+        head.push(byte[0]);
+    }
+
+    let head = String::from_utf8(head).map_err(|_| handshake_error("invalid response"))?;
+    let mut lines = head.split("\r\n");
+    let status = lines.next().unwrap_or_default();
+    let mut parts = status.split(' ');
+    if !parts
+        .next()
+        .is_some_and(|version| version.starts_with("HTTP/1."))
+    {
+        return Err(handshake_error("invalid response"));
+    }
+    if parts.next() != Some("101") {
+        return Err(handshake_error(&format!("unexpected status: {status}")));
+    }
+
+    let mut headers = Headers::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| handshake_error("invalid header"))?;
+        headers.append(name.trim(), value.trim());
+    }
+    Ok(headers)
+}
+
+fn handshake_error(message: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("websocket handshake failed: {message}"),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageDataType {
+    Continue,
+    Text,
+    Binary,
+}
+
+/// A complete text or binary message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Message {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Binary(data) => data,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.into_bytes(),
+            Self::Binary(data) => data,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            Self::Binary(_) => None,
+        }
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Binary(data)
+    }
+}
+
+/// Something that happened on the connection.
+#[derive(Debug, PartialEq, Eq)]
+enum Event {
+    Message(Message),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(CloseCode, String),
+}
+
+/// Handles the events of a connection driven by [`WebSocket::run`].
+///
+/// Pings are answered and close frames echoed before the callbacks run. Everything but
+/// [`on_message`](Self::on_message) is optional.
+pub trait WebSocketHandler {
+    fn websocket_mut(&mut self) -> &mut WebSocket;
+
+    fn on_message(&mut self, message: Message);
+
+    fn on_ping(&mut self, _payload: &[u8]) {}
+
+    fn on_pong(&mut self, _payload: &[u8]) {}
+
+    /// Called once when the connection ends, with [`CloseCode::Abnormal`] if it dropped without
+    /// a close frame and [`CloseCode::NoStatus`] if the close frame had no code.
+    fn on_close(&mut self, _code: CloseCode, _reason: &str) {}
+
+    /// Called for protocol violations and io errors, before the connection closes.
+    fn on_error(&mut self, _error: &WebSocketError) {}
+}
+
+/// The previous handler interface. It still works with [`WebSocket::run`] through
+/// [`WebSocketHandler`], without message types and close codes.
+pub trait WebSocketInterface {
+    fn on_message(&mut self, data: Vec<u8>);
+    fn on_closed(&self, ip: SocketAddr);
+    fn websocket(&self) -> &WebSocket;
+    fn websocket_mut(&mut self) -> &mut WebSocket;
+}
+
+impl<T: WebSocketInterface> WebSocketHandler for T {
+    fn websocket_mut(&mut self) -> &mut WebSocket {
+        WebSocketInterface::websocket_mut(self)
+    }
+
+    fn on_message(&mut self, message: Message) {
+        WebSocketInterface::on_message(self, message.into_bytes());
+    }
+
+    fn on_close(&mut self, _code: CloseCode, _reason: &str) {
+        let ip = self.websocket().ip();
+        self.on_closed(ip);
+    }
+}
+
+#[cfg(test)]
+mod tests;
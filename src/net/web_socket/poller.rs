@@ -0,0 +1,259 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read},
+    net::Shutdown,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use mio::{Events, Interest, Poll, Token, net::TcpStream};
+
+use super::{CloseCode, ConnectionId, SendQueue, WebSocketError, WebSocketHandler, dispatch};
+
+/// How often a connection is read per poll, so that a busy peer cannot starve the others.
+const READS_PER_POLL: usize = 16;
+
+/// Drives many connections on one thread with non-blocking streams.
+///
+/// [`tick`](Self::tick) waits for readiness events from the OS, then reads and writes the
+/// connections that are ready and calls their handlers like
+/// [`WebSocket::run`](super::WebSocket::run) does. Connections are removed once they closed,
+/// after [`WebSocketHandler::on_close`] was called.
+///
+/// Call [`tick`](Self::tick) in the main loop of a server and send through
+/// [`iter_mut`](Self::iter_mut) or a [`WebSocketHub`](super::WebSocketHub) in between. Messages
+/// sent from other threads while `tick` waits are written on the next call.
+pub struct WebSocketPoller<H: WebSocketHandler> {
+    next_id: ConnectionId,
+    connections: HashMap<ConnectionId, Polled<H>>,
+    buffer: Box<[u8]>,
+    poll: Poll,
+    events: Events,
+}
+
+struct Polled<H> {
+    handler: H,
+    stream: TcpStream,
+    send_queue: Arc<SendQueue>,
+    close_reported: bool,
+    ended: bool,
+    /// Readiness is reported by edges, so these stay set until a read or write would block.
+    readable: bool,
+    writable: bool,
+    /// How long until the timers of the connection have to be checked, if ever.
+    wait: Option<Duration>,
+}
+
+impl<H: WebSocketHandler> WebSocketPoller<H> {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            next_id: 0,
+            connections: HashMap::new(),
+            buffer: vec![0; 8192].into_boxed_slice(),
+            poll: Poll::new()?,
+            events: Events::with_capacity(256),
+        })
+    }
+
+    /// Adds the connection of `handler`, whose handshake is complete, and switches it to
+    /// non-blocking mode.
+    pub fn add(&mut self, mut handler: H) -> io::Result<ConnectionId> {
+        let websocket = handler.websocket_mut();
+        websocket.stream.set_nonblocking(true)?;
+        let mut stream = TcpStream::from_std(websocket.stream.try_clone()?);
+        let id = self.next_id + 1;
+        self.poll.registry().register(
+            &mut stream,
+            token(id),
+            Interest::READABLE | Interest::WRITABLE,
+        )?;
+
+        let polled = Polled {
+            stream,
+            send_queue: websocket.send_queue.clone(),
+            handler,
+            close_reported: false,
+            ended: false,
+            // Data may have arrived before the stream was registered.
+            readable: true,
+            writable: true,
+            wait: None,
+        };
+        self.next_id = id;
+        self.connections.insert(id, polled);
+        Ok(id)
+    }
+
+    /// Removes a connection without closing it. Its stream stays non-blocking.
+    pub fn remove(&mut self, id: ConnectionId) -> Option<H> {
+        let mut polled = self.connections.remove(&id)?;
+        let _ = self.poll.registry().deregister(&mut polled.stream);
+        Some(polled.handler)
+    }
+
+    pub fn get_mut(&mut self, id: ConnectionId) -> Option<&mut H> {
+        self.connections
+            .get_mut(&id)
+            .map(|polled| &mut polled.handler)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ConnectionId, &mut H)> {
+        self.connections
+            .iter_mut()
+            .map(|(&id, polled)| (id, &mut polled.handler))
+    }
+
+    /// The number of connections.
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Reads and writes every connection as far as possible without blocking and without
+    /// waiting for readiness events, and returns how many were ready.
+    pub fn poll(&mut self) -> usize {
+        for polled in self.connections.values_mut() {
+            polled.readable = true;
+            polled.writable = true;
+        }
+        self.serve()
+    }
+
+    /// Waits until a connection is ready or `timeout` passed, serves the ready connections and
+    /// returns how many there were.
+    ///
+    /// Also wakes up for the pings and timeouts of the connections.
+    pub fn tick(&mut self, timeout: Duration) -> usize {
+        let start = Instant::now();
+        loop {
+            // Frames queued since the last call are written right away.
+            let ready = self.serve();
+            let elapsed = start.elapsed();
+            if ready > 0 || elapsed >= timeout {
+                return ready;
+            }
+
+            let mut wait = timeout - elapsed;
+            for polled in self.connections.values() {
+                if polled.is_due() {
+                    wait = Duration::ZERO;
+                } else if let Some(timer) = polled.wait {
+                    wait = wait.min(timer);
+                }
+            }
+            match self.poll.poll(&mut self.events, Some(wait)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return 0,
+            }
+            for event in &self.events {
+                let Some(polled) = self.connections.get_mut(&(event.token().0 as ConnectionId))
+                else {
+                    continue;
+                };
+                polled.readable |=
+                    event.is_readable() || event.is_read_closed() || event.is_error();
+                polled.writable |=
+                    event.is_writable() || event.is_write_closed() || event.is_error();
+            }
+        }
+    }
+
+    /// Serves the connections with pending reads, writes or timers and drops the ended ones.
+    fn serve(&mut self) -> usize {
+        let now = Instant::now();
+        let mut ready = 0;
+        for polled in self.connections.values_mut() {
+            if polled.poll(&mut self.buffer, now) {
+                ready += 1;
+            }
+        }
+
+        let registry = self.poll.registry();
+        self.connections.retain(|_, polled| {
+            if polled.ended {
+                let _ = registry.deregister(&mut polled.stream);
+                if !polled.close_reported {
+                    polled.handler.on_close(CloseCode::Abnormal, "");
+                }
+            }
+            !polled.ended
+        });
+        ready
+    }
+}
+
+fn token(id: ConnectionId) -> Token {
+    Token(id as usize)
+}
+
+impl<H: WebSocketHandler> Polled<H> {
+    /// Whether the connection can make progress without waiting for an event.
+    fn is_due(&self) -> bool {
+        self.readable || (self.writable && self.send_queue.bytes() > 0)
+    }
+
+    /// Returns whether anything was read or written, or the connection ended.
+    fn poll(&mut self, buffer: &mut [u8], now: Instant) -> bool {
+        let websocket = self.handler.websocket_mut();
+        self.wait = websocket.check_timeouts(now);
+        if websocket.timed_out {
+            self.ended = true;
+            return true;
+        }
+
+        let mut ready = false;
+        if self.readable {
+            for _ in 0..READS_PER_POLL {
+                if self.handler.websocket_mut().is_closed() {
+                    self.readable = false;
+                    break;
+                }
+                match self.stream.read(buffer) {
+                    Ok(0) => {
+                        self.ended = true;
+                        return true;
+                    }
+                    Ok(len) => {
+                        ready = true;
+                        self.handler.websocket_mut().receive(&buffer[..len]);
+                        dispatch(&mut self.handler, &mut self.close_reported);
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        self.readable = false;
+                        break;
+                    }
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return self.end_with(e),
+                }
+            }
+        }
+
+        if self.writable && self.send_queue.bytes() > 0 {
+            match self.send_queue.write_ready(&self.stream) {
+                Ok((written, blocked)) => {
+                    ready |= written > 0;
+                    self.writable = !blocked;
+                }
+                Err(e) => return self.end_with(e),
+            }
+        }
+
+        // The close frame is written before the stream is shut down.
+        if self.handler.websocket_mut().is_closed() && self.send_queue.bytes() == 0 {
+            let _ = self.stream.shutdown(Shutdown::Write);
+            self.ended = true;
+            ready = true;
+        }
+        ready
+    }
+
+    fn end_with(&mut self, error: io::Error) -> bool {
+        self.handler.on_error(&WebSocketError::Io(error));
+        self.ended = true;
+        true
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::VecDeque,
+    io::{self, ErrorKind, Write},
+    net::{Shutdown, TcpStream},
+    sync::{Condvar, Mutex},
+};
+
+use super::WebSocketError;
+
+/// The encoded frames waiting to be written, shared by a socket, its clones and the thread
+/// writing them.
+#[derive(Debug, Default)]
+pub(super) struct SendQueue {
+    state: Mutex<State>,
+    wakeup: Condvar,
+    /// Held while writing, so that frames taken from the queue are written in order.
+    writing: Mutex<()>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    frames: VecDeque<Vec<u8>>,
+    /// Queued bytes, including those that are being written.
+    bytes: usize,
+    /// How much of the first frame a non-blocking write got out already.
+    offset: usize,
+    /// Bytes written since the connection opened.
+    written: u64,
+    /// Messages queued since the connection opened.
+    messages: u64,
+    /// A close frame was queued, so nothing else may follow.
+    closed: bool,
+    stopped: bool,
+    error: Option<io::Error>,
+}
+
+impl SendQueue {
+    pub(super) fn bytes(&self) -> usize {
+        self.state.lock().unwrap().bytes
+    }
+
+    /// The number of bytes and messages sent so far.
+    pub(super) fn sent(&self) -> (u64, u64) {
+        let state = self.state.lock().unwrap();
+        (state.written, state.messages)
+    }
+
+    /// Fails with [`WebSocketError::QueueFull`] if `len` more bytes do not fit below `limit`.
+    ///
+    /// An empty queue always has room, so that messages larger than the limit can be sent.
+    pub(super) fn check_room(&self, len: usize, limit: usize) -> Result<(), WebSocketError> {
+        let state = self.state.lock().unwrap();
+        if state.closed {
+            Err(WebSocketError::Closed)
+        } else if state.bytes > 0 && state.bytes.saturating_add(len) > limit {
+            Err(WebSocketError::QueueFull)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Queues the frames of one message and wakes up the writer.
+    pub(super) fn push_message(
+        &self,
+        frames: impl IntoIterator<Item = Vec<u8>>,
+    ) -> Result<(), WebSocketError> {
+        self.push(frames, false, 1)
+    }
+
+    /// Queues a control frame and wakes up the writer.
+    pub(super) fn push_control(&self, frame: Vec<u8>, close: bool) -> Result<(), WebSocketError> {
+        self.push([frame], close, 0)
+    }
+
+    fn push(
+        &self,
+        frames: impl IntoIterator<Item = Vec<u8>>,
+        close: bool,
+        messages: u64,
+    ) -> Result<(), WebSocketError> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err(WebSocketError::Closed);
+        }
+        state.messages += messages;
+        for frame in frames {
+            state.bytes += frame.len();
+            state.frames.push_back(frame);
+        }
+        state.closed = close;
+        self.wakeup.notify_all();
+        Ok(())
+    }
+
+    /// Writes everything queued so far.
+    pub(super) fn write_to(&self, mut stream: &TcpStream) -> io::Result<()> {
+        let _writing = self.writing.lock().unwrap();
+        let (frames, offset) = {
+            let mut state = self.state.lock().unwrap();
+            (
+                std::mem::take(&mut state.frames),
+                std::mem::take(&mut state.offset),
+            )
+        };
+
+        let result = frames
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, frame)| {
+                let start = if index == 0 { offset } else { 0 };
+                stream.write_all(&frame[start..])
+            })
+            .and_then(|()| stream.flush());
+        let len: usize = frames.iter().map(Vec::len).sum();
+        let mut state = self.state.lock().unwrap();
+        state.bytes -= len;
+        if result.is_ok() {
+            state.written += (len - offset) as u64;
+        }
+        result
+    }
+
+    /// Writes queued frames to a non-blocking stream until it would block. Returns the number
+    /// of bytes written and whether the stream would block.
+    pub(super) fn write_ready(&self, mut stream: impl Write) -> io::Result<(usize, bool)> {
+        let _writing = self.writing.lock().unwrap();
+        let mut written = 0;
+        loop {
+            let mut state = self.state.lock().unwrap();
+            let offset = state.offset;
+            let Some(frame) = state.frames.front() else {
+                return Ok((written, false));
+            };
+            let len = frame.len();
+            // The stream does not block, so the lock is not held for long.
+            match stream.write(&frame[offset..]) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    written += n;
+                    state.written += n as u64;
+                    state.offset += n;
+                    if state.offset == len {
+                        state.frames.pop_front();
+                        state.bytes -= len;
+                        state.offset = 0;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok((written, true)),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Writes queued frames as soon as they arrive, until [`stop`](Self::stop) is called and
+    /// the queue is empty.
+    pub(super) fn write_loop(&self, stream: TcpStream) {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                while state.frames.is_empty() && !state.stopped {
+                    state = self.wakeup.wait(state).unwrap();
+                }
+                if state.frames.is_empty() {
+                    return;
+                }
+            }
+
+            if let Err(error) = self.write_to(&stream) {
+                // Wakes up the reader, which reports the error.
+                let _ = stream.shutdown(Shutdown::Both);
+                let mut state = self.state.lock().unwrap();
+                state.error = Some(error);
+                state.closed = true;
+                return;
+            }
+        }
+    }
+
+    pub(super) fn stop(&self) {
+        self.state.lock().unwrap().stopped = true;
+        self.wakeup.notify_all();
+    }
+
+    /// The error the writer stopped with.
+    pub(super) fn take_error(&self) -> Option<io::Error> {
+        self.state.lock().unwrap().error.take()
+    }
+}
@@ -0,0 +1,1362 @@
+use std::{
+    io::BufReader,
+    net::{Shutdown, TcpListener},
+    sync::mpsc::{Receiver, Sender, channel},
+    thread,
+    time::SystemTime,
+};
+
+use super::*;
+use crate::net::{HTTPRequest, HttpLimits, HttpServer, Response, Router};
+
+struct Client {
+    websocket: WebSocket,
+    messages: Sender<Vec<u8>>,
+}
+
+impl WebSocketInterface for Client {
+    fn on_message(&mut self, data: Vec<u8>) {
+        let _ = self.messages.send(data);
+    }
+
+    fn on_closed(&self, _: SocketAddr) {}
+
+    fn websocket(&self) -> &WebSocket {
+        &self.websocket
+    }
+
+    fn websocket_mut(&mut self) -> &mut WebSocket {
+        &mut self.websocket
+    }
+}
+
+/// Accepts one connection and answers its handshake, optionally with a wrong key.
+fn server(wrong_key: bool) -> (SocketAddr, thread::JoinHandle<(HTTPRequest, TcpStream)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let request = HTTPRequest::read(&mut reader, &HttpLimits::default()).unwrap();
+        let key = if wrong_key {
+            "AAAA"
+        } else {
+            request.websocket_key().unwrap()
+        };
+        let websocket = WebSocket::try_connect(stream, key).unwrap();
+        (request, websocket.stream)
+    });
+    (addr, handle)
+}
+
+#[test]
+fn parses_urls() {
+    let parse = |url| parse_url(url).unwrap();
+    assert_eq!(
+        parse("ws://localhost"),
+        ("localhost".into(), 80, "/".into())
+    );
+    assert_eq!(
+        parse("ws://a:81/chat?room=1"),
+        ("a".into(), 81, "/chat?room=1".into())
+    );
+    assert_eq!(parse("WS://a?x"), ("a".into(), 80, "/?x".into()));
+    assert_eq!(
+        parse("ws://[::1]:9000/"),
+        ("[::1]".into(), 9000, "/".into())
+    );
+    assert_eq!(parse("ws://[::1]/"), ("[::1]".into(), 80, "/".into()));
+
+    for url in ["http://a/", "ws://", "ws://a:x/", "a/b", "ws://a/#frag"] {
+        assert_eq!(
+            parse_url(url).unwrap_err().kind(),
+            ErrorKind::InvalidInput,
+            "{url}"
+        );
+    }
+    assert_eq!(
+        parse_url("wss://a/").unwrap_err().kind(),
+        ErrorKind::Unsupported
+    );
+}
+
+#[test]
+fn client_masks_and_reads_unmasked_frames() {
+    let (addr, server) = server(false);
+    let mut websocket = WebSocket::connect(&format!("ws://{addr}/chat")).unwrap();
+    let (request, mut stream) = server.join().unwrap();
+    assert!(websocket.is_client());
+    assert_eq!(request.path, "/chat");
+    assert_eq!(request.header("sec-websocket-version"), Some("13"));
+    let key = STANDARD.decode(request.websocket_key().unwrap()).unwrap();
+    assert_eq!(key.len(), 16);
+
+    websocket.send(b"hello", MessageDataType::Text).unwrap();
+    websocket.flush().unwrap();
+    let mut frame = [0; 11];
+    stream.read_exact(&mut frame).unwrap();
+    assert_eq!(frame[..2], [0b10000001, 0b10000000 | 5]);
+    let payload: Vec<u8> = (0..5).map(|i| frame[6 + i] ^ frame[2 + i % 4]).collect();
+    assert_eq!(payload, b"hello");
+
+    let (sender, receiver) = channel();
+    let client = Arc::new(RwLock::new(Client {
+        websocket,
+        messages: sender,
+    }));
+    let runner = thread::spawn(move || WebSocket::run(client));
+    stream
+        .write_all(&encode_frame(0b10000001, b"hi", false))
+        .unwrap();
+    let message = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message, b"hi");
+
+    drop(stream);
+    runner.join().unwrap();
+}
+
+#[test]
+fn rejects_wrong_accept_key() {
+    let (addr, server) = server(true);
+    let error = WebSocket::connect(&format!("ws://{addr}/")).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    server.join().unwrap();
+}
+
+#[test]
+fn limits_the_opening_handshake() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/", listener.local_addr().unwrap());
+    // Every byte arrives quickly, but the response never ends.
+    let trickle = thread::spawn(move || {
+        let (mut server, _) = listener.accept().unwrap();
+        for _ in 0..40 {
+            if server.write_all(b"X").is_err() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    });
+
+    let timeouts = WebSocketTimeouts {
+        handshake_timeout: Some(Duration::from_millis(200)),
+        ..WebSocketTimeouts::default()
+    };
+    let start = Instant::now();
+    let error = WebSocket::connect_with(&url, None, &timeouts).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        ErrorKind::TimedOut | ErrorKind::WouldBlock
+    ));
+    assert!(start.elapsed() < Duration::from_millis(600));
+    trickle.join().unwrap();
+}
+
+const TEXT: u8 = 0x81;
+const BINARY: u8 = 0x82;
+const CLOSE: u8 = 0x88;
+const PING: u8 = 0x89;
+const PONG: u8 = 0x8A;
+
+/// A masked frame as a client sends it.
+fn frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+    encode_frame(first_byte, payload, true)
+}
+
+fn close(code: u16) -> Vec<u8> {
+    frame(CLOSE, &code.to_be_bytes())
+}
+
+/// Echoes every message with its type until the connection closes.
+fn echo(mut websocket: WebSocket) {
+    let mut stream = websocket.stream.try_clone().unwrap();
+    stream.set_read_timeout(None).unwrap();
+    let mut buffer = [0; 4096];
+    while let Ok(len @ 1..) = stream.read(&mut buffer) {
+        websocket.reader.feed(&buffer[..len]);
+        loop {
+            match websocket.next_event() {
+                Ok(Some(Event::Message(message))) => websocket.send_message(&message).unwrap(),
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(error) => {
+                    websocket.fail(&error);
+                    break;
+                }
+            }
+        }
+        if websocket.flush().is_err() || websocket.is_closed() {
+            break;
+        }
+    }
+    // Drains what the client still sends, so the connection is not reset.
+    let _ = stream.shutdown(Shutdown::Write);
+    while let Ok(1..) = stream.read(&mut buffer) {}
+}
+
+/// Starts an echo server on one end of a loopback connection and returns the other end.
+fn echo_server(limits: WebSocketLimits, deflate: Option<DeflateConfig>) -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let mut websocket = WebSocket::new(listener.accept().unwrap().0).limits(limits);
+    if let Some(config) = deflate {
+        websocket.enable_deflate(config);
+    }
+    thread::spawn(move || echo(websocket));
+    stream
+}
+
+/// Sends raw frames to an echo server and returns its answers until it closes the connection.
+fn exchange_raw(
+    limits: WebSocketLimits,
+    deflate: Option<DeflateConfig>,
+    frames: &[Vec<u8>],
+) -> Vec<Frame> {
+    let mut stream = echo_server(limits, deflate);
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    for frame in frames {
+        // The server may have failed the connection already.
+        let _ = stream.write_all(frame);
+    }
+
+    let mut reader = FrameReader::new(false, usize::MAX);
+    reader.allow_rsv1(true);
+    let mut replies = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        while let Some(frame) = reader.next_frame().unwrap() {
+            replies.push(frame);
+        }
+        match stream.read(&mut buffer) {
+            Ok(0) => return replies,
+            Ok(len) => reader.feed(&buffer[..len]),
+            Err(error) => panic!("server did not close the connection: {error}"),
+        }
+    }
+}
+
+fn exchange_with(limits: WebSocketLimits, frames: &[Vec<u8>]) -> Vec<Frame> {
+    exchange_raw(limits, None, frames)
+}
+
+fn exchange(frames: &[Vec<u8>]) -> Vec<Frame> {
+    exchange_with(WebSocketLimits::default(), frames)
+}
+
+/// The code of the close frame the server answered with last.
+fn close_code(replies: &[Frame]) -> Option<u16> {
+    let last = replies.last().expect("no close frame");
+    assert_eq!(last.opcode, OpCode::Close);
+    last.close_payload().unwrap().0.map(u16::from)
+}
+
+#[test]
+fn echoes_text_and_binary() {
+    for len in [0, 1, 125, 126, 127, 65535, 65536] {
+        let text = "*".repeat(len);
+        let binary = vec![0xFE; len];
+        let replies = exchange(&[
+            frame(TEXT, text.as_bytes()),
+            frame(BINARY, &binary),
+            close(1000),
+        ]);
+        assert_eq!(
+            replies,
+            [
+                Frame::new(OpCode::Text, text),
+                Frame::new(OpCode::Binary, binary),
+                Frame::close(Some(CloseCode::Normal), ""),
+            ],
+            "{len}"
+        );
+    }
+}
+
+#[test]
+fn answers_pings() {
+    let replies = exchange(&[
+        frame(PING, b""),
+        frame(PONG, b"unsolicited"),
+        frame(PING, &[0xFF; 125]),
+        close(1000),
+    ]);
+    assert_eq!(replies[0], Frame::new(OpCode::Pong, []));
+    assert_eq!(replies[1], Frame::new(OpCode::Pong, [0xFF; 125]));
+    assert_eq!(replies.len(), 3);
+    assert_eq!(close_code(&replies), Some(1000));
+
+    // Control frames may not be longer than 125 bytes or fragmented.
+    for ping in [frame(PING, &[0; 126]), frame(0x09, b"a")] {
+        let replies = exchange(&[ping, frame(PING, b"b")]);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(close_code(&replies), Some(1002));
+    }
+}
+
+#[test]
+fn rejects_reserved_bits_and_opcodes() {
+    for first_byte in [0xC1, 0xA1, 0x91, 0xF1, 0x83, 0x87, 0x8B, 0x8F] {
+        let replies = exchange(&[
+            frame(TEXT, b"ok"),
+            frame(first_byte, b"x"),
+            frame(PING, b""),
+        ]);
+        assert_eq!(replies[0], Frame::new(OpCode::Text, "ok"), "{first_byte:x}");
+        assert_eq!(replies.len(), 2, "{first_byte:x}");
+        assert_eq!(close_code(&replies), Some(1002), "{first_byte:x}");
+    }
+}
+
+#[test]
+fn joins_fragments() {
+    let replies = exchange(&[
+        frame(0x01, b"Hello, "),
+        frame(PING, b"p"),
+        frame(0x00, b"wor"),
+        frame(0x80, b"ld!"),
+        frame(0x02, b""),
+        frame(0x80, &[1, 2]),
+        close(1000),
+    ]);
+    assert_eq!(
+        replies,
+        [
+            Frame::new(OpCode::Pong, "p"),
+            Frame::new(OpCode::Text, "Hello, world!"),
+            Frame::new(OpCode::Binary, [1, 2]),
+            Frame::close(Some(CloseCode::Normal), ""),
+        ]
+    );
+
+    let invalid = [
+        vec![frame(0x80, b"continuation without a start")],
+        vec![frame(0x00, b"a"), frame(0x80, b"b")],
+        vec![frame(0x01, b"a"), frame(TEXT, b"b")],
+        vec![frame(0x02, b"a"), frame(0x01, b"b")],
+    ];
+    for frames in invalid {
+        let replies = exchange(&frames);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(close_code(&replies), Some(1002));
+    }
+}
+
+#[test]
+fn validates_utf8() {
+    let text = "κόσμε".as_bytes();
+    let replies = exchange(&[
+        frame(0x01, &text[..1]),
+        frame(0x80, &text[1..]),
+        close(1000),
+    ]);
+    assert_eq!(replies[0], Frame::new(OpCode::Text, "κόσμε"));
+
+    // Binary messages are not text.
+    let replies = exchange(&[frame(BINARY, &[0xFF]), close(1000)]);
+    assert_eq!(replies[0], Frame::new(OpCode::Binary, [0xFF]));
+
+    let invalid = [
+        vec![frame(TEXT, b"\xce\xba\xff")],
+        vec![frame(TEXT, b"\xce")],
+        vec![frame(0x01, b"\xed\xa0\x80"), frame(PING, b"")],
+        vec![frame(0x01, b"a\xce"), frame(0x80, b"\xce")],
+        vec![frame(0x01, b"\xf4\x90\x80\x80")],
+    ];
+    for frames in invalid {
+        let replies = exchange(&frames);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(close_code(&replies), Some(1007));
+    }
+}
+
+#[test]
+fn closing_handshake() {
+    assert_eq!(exchange(&[frame(CLOSE, b"")]), [Frame::close(None, "")]);
+
+    let mut reason = 1000u16.to_be_bytes().to_vec();
+    reason.extend_from_slice("bye ✓".as_bytes());
+    let replies = exchange(&[frame(CLOSE, &reason), frame(TEXT, b"too late")]);
+    assert_eq!(replies, [Frame::close(Some(CloseCode::Normal), "")]);
+
+    for code in [1001, 1003, 1007, 1011, 1014, 3000, 4999] {
+        assert_eq!(close_code(&exchange(&[close(code)])), Some(code));
+    }
+    for code in [0, 999, 1004, 1005, 1006, 1015, 2999, 5000, 65535] {
+        assert_eq!(close_code(&exchange(&[close(code)])), Some(1002), "{code}");
+    }
+
+    assert_eq!(close_code(&exchange(&[frame(CLOSE, &[3])])), Some(1002));
+    assert_eq!(
+        close_code(&exchange(&[frame(CLOSE, &[3, 232, 0xFF])])),
+        Some(1007)
+    );
+}
+
+#[test]
+fn limits_message_size() {
+    let limits = WebSocketLimits {
+        max_frame_size: 64,
+        max_message_size: 100,
+        ..WebSocketLimits::default()
+    };
+
+    let replies = exchange_with(limits.clone(), &[frame(TEXT, &[b'a'; 65])]);
+    assert_eq!(close_code(&replies), Some(1009));
+
+    let frames = [frame(0x01, &[b'a'; 60]), frame(0x80, &[b'a'; 41])];
+    assert_eq!(
+        close_code(&exchange_with(limits.clone(), &frames)),
+        Some(1009)
+    );
+
+    let frames = [
+        frame(0x01, &[b'a'; 50]),
+        frame(0x80, &[b'a'; 50]),
+        close(1000),
+    ];
+    let replies = exchange_with(limits, &frames);
+    assert_eq!(replies[0], Frame::new(OpCode::Text, [b'a'; 100]));
+}
+
+#[test]
+fn rejects_unmasked_client_frames() {
+    let replies = exchange(&[encode_frame(TEXT, b"a", false)]);
+    assert_eq!(close_code(&replies), Some(1002));
+}
+
+#[test]
+fn sends_control_frames() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let mut websocket = WebSocket::new(listener.accept().unwrap().0);
+
+    websocket.send_ping(b"heartbeat").unwrap();
+    assert!(matches!(
+        websocket.send_ping(&[0; 126]),
+        Err(WebSocketError::ControlFrameTooLarge)
+    ));
+    websocket.close_with(CloseCode::GoingAway, &"é".repeat(100));
+    assert!(matches!(
+        websocket.send_pong(b""),
+        Err(WebSocketError::Closed)
+    ));
+    assert!(matches!(
+        websocket.send(b"dropped", MessageDataType::Text),
+        Err(WebSocketError::Closed)
+    ));
+    websocket.flush().unwrap();
+    drop(websocket);
+
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    let mut reader = FrameReader::new(false, usize::MAX);
+    reader.feed(&data);
+    assert_eq!(
+        reader.next_frame().unwrap(),
+        Some(Frame::new(OpCode::Ping, "heartbeat"))
+    );
+    let close = reader.next_frame().unwrap().unwrap();
+    assert_eq!(close.payload.len(), 124);
+    let (code, reason) = close.close_payload().unwrap();
+    assert_eq!(code, Some(CloseCode::GoingAway));
+    assert_eq!(reason, "é".repeat(61));
+    assert_eq!(reader.next_frame().unwrap(), None);
+    assert_eq!(reader.buffered(), 0);
+}
+
+/// A server socket with the given limits on a loopback connection, and the client end.
+fn pair(limits: WebSocketLimits) -> (WebSocket, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let websocket = WebSocket::new(listener.accept().unwrap().0).limits(limits);
+    (websocket, stream)
+}
+
+#[test]
+fn fragments_outgoing_messages() {
+    let (mut websocket, mut stream) = pair(WebSocketLimits {
+        max_send_frame_size: 4,
+        ..WebSocketLimits::default()
+    });
+    // Nothing is queued for a message that starts with a continuation frame.
+    assert!(matches!(
+        websocket.send(b"x", MessageDataType::Continue),
+        Err(WebSocketError::ContinuationStart)
+    ));
+    websocket
+        .send(b"hello world", MessageDataType::Text)
+        .unwrap();
+    websocket.send(b"", MessageDataType::Binary).unwrap();
+    websocket.send(b"1234", MessageDataType::Binary).unwrap();
+    websocket.enable_deflate(DeflateConfig {
+        min_size: 0,
+        ..DeflateConfig::default()
+    });
+    websocket
+        .send(&[b'a'; 100], MessageDataType::Binary)
+        .unwrap();
+    websocket.flush().unwrap();
+    drop(websocket);
+
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    let mut reader = FrameReader::new(false, usize::MAX);
+    reader.allow_rsv1(true);
+    reader.feed(&data);
+    let frames: Vec<_> = std::iter::from_fn(|| reader.next_frame().unwrap()).collect();
+    let fragment = |fin, opcode, payload: &str| Frame {
+        fin,
+        ..Frame::new(opcode, payload)
+    };
+    assert_eq!(
+        frames[..5],
+        [
+            fragment(false, OpCode::Text, "hell"),
+            fragment(false, OpCode::Continuation, "o wo"),
+            fragment(true, OpCode::Continuation, "rld"),
+            Frame::new(OpCode::Binary, ""),
+            Frame::new(OpCode::Binary, "1234"),
+        ]
+    );
+
+    // Only the first frame of a compressed message has RSV1 set.
+    let compressed = &frames[5..];
+    assert!(compressed.len() > 1);
+    assert!(compressed[0].rsv1 && compressed[0].opcode == OpCode::Binary);
+    assert!(compressed[1..].iter().all(|frame| !frame.rsv1));
+    assert!(compressed.iter().all(|frame| frame.payload.len() <= 4));
+    assert!(compressed.last().unwrap().fin);
+}
+
+#[test]
+fn reports_backpressure() {
+    let (mut websocket, mut stream) = pair(WebSocketLimits {
+        max_send_queue: 10,
+        ..WebSocketLimits::default()
+    });
+
+    // A message larger than the limit fits into an empty queue.
+    websocket.send(&[1; 20], MessageDataType::Binary).unwrap();
+    assert_eq!(websocket.queued_bytes(), 22);
+    assert!(matches!(
+        websocket.send(b"a", MessageDataType::Text),
+        Err(WebSocketError::QueueFull)
+    ));
+    // Control frames are never held back.
+    websocket.send_ping(b"").unwrap();
+
+    websocket.flush().unwrap();
+    assert_eq!(websocket.queued_bytes(), 0);
+    websocket.send(b"a", MessageDataType::Text).unwrap();
+    websocket.flush().unwrap();
+
+    let mut data = [0; 27];
+    stream.read_exact(&mut data).unwrap();
+    let mut reader = FrameReader::new(false, usize::MAX);
+    reader.feed(&data);
+    let frames: Vec<_> = std::iter::from_fn(|| reader.next_frame().unwrap()).collect();
+    assert_eq!(
+        frames,
+        [
+            Frame::new(OpCode::Binary, [1; 20]),
+            Frame::new(OpCode::Ping, ""),
+            Frame::new(OpCode::Text, "a"),
+        ]
+    );
+}
+
+/// Records every callback as a line of text.
+struct Recorder {
+    websocket: WebSocket,
+    events: Sender<String>,
+}
+
+impl WebSocketHandler for Recorder {
+    fn websocket_mut(&mut self) -> &mut WebSocket {
+        &mut self.websocket
+    }
+
+    fn on_message(&mut self, message: Message) {
+        let _ = self.events.send(format!("{message:?}"));
+        if let Message::Text(text) = message {
+            self.websocket
+                .send_message(&Message::from(text.to_uppercase()))
+                .unwrap();
+        }
+    }
+
+    fn on_ping(&mut self, payload: &[u8]) {
+        let _ = self.events.send(format!("ping {payload:?}"));
+    }
+
+    fn on_pong(&mut self, payload: &[u8]) {
+        let _ = self.events.send(format!("pong {payload:?}"));
+    }
+
+    fn on_close(&mut self, code: CloseCode, reason: &str) {
+        let _ = self.events.send(format!("close {code:?} {reason}"));
+    }
+
+    fn on_error(&mut self, error: &WebSocketError) {
+        let _ = self.events.send(format!("error {error}"));
+    }
+}
+
+/// Runs a [`Recorder`] on a loopback connection and returns the client end.
+fn recorder() -> (TcpStream, Receiver<String>, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (events, receiver) = channel();
+    let recorder = Recorder {
+        websocket: WebSocket::new(listener.accept().unwrap().0),
+        events,
+    };
+    let runner = thread::spawn(move || WebSocket::run(Arc::new(RwLock::new(recorder))));
+    (stream, receiver, runner)
+}
+
+#[test]
+fn calls_handler() {
+    let (mut stream, events, runner) = recorder();
+    let mut close_frame = 1001u16.to_be_bytes().to_vec();
+    close_frame.extend_from_slice(b"bye");
+    for frame in [
+        frame(TEXT, b"hi"),
+        frame(BINARY, &[1, 2]),
+        frame(PING, b"p"),
+        frame(PONG, b"q"),
+        frame(CLOSE, &close_frame),
+    ] {
+        stream.write_all(&frame).unwrap();
+    }
+    runner.join().unwrap();
+
+    assert_eq!(
+        events.try_iter().collect::<Vec<_>>(),
+        [
+            "Text(\"hi\")",
+            "Binary([1, 2])",
+            "ping [112]",
+            "pong [113]",
+            "close GoingAway bye",
+        ]
+    );
+
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    let mut reader = FrameReader::new(false, usize::MAX);
+    reader.feed(&data);
+    let replies: Vec<_> = std::iter::from_fn(|| reader.next_frame().unwrap()).collect();
+    assert_eq!(
+        replies,
+        [
+            Frame::new(OpCode::Text, "HI"),
+            Frame::new(OpCode::Pong, "p"),
+            Frame::close(Some(CloseCode::GoingAway), ""),
+        ]
+    );
+}
+
+#[test]
+fn reports_errors_and_dropped_connections() {
+    let (mut stream, events, runner) = recorder();
+    stream.write_all(&encode_frame(TEXT, b"a", false)).unwrap();
+    runner.join().unwrap();
+    assert_eq!(
+        events.try_iter().collect::<Vec<_>>(),
+        [
+            "error protocol error: unmasked client frame",
+            "close ProtocolError protocol error: unmasked client frame",
+        ]
+    );
+
+    let (stream, events, runner) = recorder();
+    drop(stream);
+    runner.join().unwrap();
+    assert_eq!(events.try_iter().collect::<Vec<_>>(), ["close Abnormal "]);
+}
+
+#[test]
+fn writes_messages_from_other_threads_immediately() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let websocket = WebSocket::new(listener.accept().unwrap().0);
+    let mut sender = websocket.clone();
+    let (events, _) = channel();
+    let recorder = Arc::new(RwLock::new(Recorder { websocket, events }));
+    let runner = thread::spawn(move || WebSocket::run(recorder));
+
+    // Well below the read timeout of the connection.
+    stream
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    let mut data = [0; 7];
+    for _ in 0..3 {
+        sender.send(b"tick", MessageDataType::Text).unwrap();
+        stream.read_exact(&mut data[..6]).unwrap();
+        assert_eq!(data[..6], encode_frame(TEXT, b"tick", false));
+    }
+
+    stream.write_all(&close(1000)).unwrap();
+    runner.join().unwrap();
+    assert!(matches!(
+        sender.send(b"late", MessageDataType::Text),
+        Err(WebSocketError::Closed)
+    ));
+    stream.read_exact(&mut data[..4]).unwrap();
+    assert_eq!(data[..4], [CLOSE, 2, 3, 232]);
+}
+
+#[test]
+fn converts_messages() {
+    let text = Message::from("ü");
+    assert_eq!(text.as_text(), Some("ü"));
+    assert_eq!(text.as_bytes(), "ü".as_bytes());
+    assert_eq!(text.into_bytes(), "ü".as_bytes());
+
+    let binary = Message::from(vec![0xFF]);
+    assert_eq!(binary.as_text(), None);
+    assert_eq!(binary.into_bytes(), [0xFF]);
+}
+
+#[test]
+fn negotiates_deflate() {
+    let local = DeflateConfig::default();
+    let accept = |offers| local.accept(offers).map(|(_, response)| response);
+
+    assert_eq!(local.offer(), "permessage-deflate; client_max_window_bits");
+    assert_eq!(
+        accept("permessage-deflate; client_max_window_bits").as_deref(),
+        Some("permessage-deflate")
+    );
+    assert_eq!(
+        accept("x-webkit-deflate-frame, permessage-deflate; server_max_window_bits=\"10\"; client_no_context_takeover")
+            .as_deref(),
+        Some("permessage-deflate; client_no_context_takeover; server_max_window_bits=10")
+    );
+    // Offers are tried in order and 8 bit compression windows are not supported.
+    assert_eq!(
+        accept("permessage-deflate; server_max_window_bits=8, permessage-deflate").as_deref(),
+        Some("permessage-deflate")
+    );
+    for offer in [
+        "",
+        "x-webkit-deflate-frame",
+        "permessage-deflate; unknown",
+        "permessage-deflate; server_max_window_bits",
+        "permessage-deflate; server_max_window_bits=16",
+        "permessage-deflate; server_max_window_bits=+9",
+        "permessage-deflate; client_no_context_takeover; client_no_context_takeover",
+        "permessage-deflate; server_no_context_takeover=1",
+    ] {
+        assert_eq!(accept(offer), None, "{offer}");
+    }
+
+    let limited = DeflateConfig {
+        client_max_window_bits: 12,
+        server_no_context_takeover: true,
+        ..DeflateConfig::default()
+    };
+    assert_eq!(
+        limited.offer(),
+        "permessage-deflate; client_max_window_bits=12; server_no_context_takeover"
+    );
+    let (agreed, response) = limited
+        .accept("permessage-deflate; client_max_window_bits")
+        .unwrap();
+    assert_eq!(
+        response,
+        "permessage-deflate; server_no_context_takeover; client_max_window_bits=12"
+    );
+    assert_eq!(agreed.client_max_window_bits, 12);
+    // The client window cannot be limited if the client did not offer it.
+    let (agreed, _) = limited.accept("permessage-deflate").unwrap();
+    assert_eq!(agreed.client_max_window_bits, 15);
+
+    let agreed = local
+        .confirm("permessage-deflate; server_no_context_takeover; client_max_window_bits=10")
+        .unwrap();
+    assert!(agreed.server_no_context_takeover);
+    assert_eq!(agreed.client_max_window_bits, 10);
+    assert_eq!(agreed.server_max_window_bits, 15);
+    for response in [
+        "x-webkit-deflate-frame",
+        "permessage-deflate, permessage-deflate",
+        "permessage-deflate; client_max_window_bits",
+        "permessage-deflate; client_max_window_bits=8",
+        "permessage-deflate; mystery",
+    ] {
+        assert!(local.confirm(response).is_err(), "{response}");
+    }
+    let small = DeflateConfig {
+        server_max_window_bits: 10,
+        ..DeflateConfig::default()
+    };
+    assert!(
+        small
+            .confirm("permessage-deflate; server_max_window_bits=11")
+            .is_err()
+    );
+}
+
+#[test]
+fn deflate_codec() {
+    let config = DeflateConfig {
+        min_size: 0,
+        ..DeflateConfig::default()
+    };
+    let mut client = Deflate::new(config.clone(), true);
+
+    // The examples of RFC 7692, section 7.2.3.
+    let hello = [0xF2, 0x48, 0xCD, 0xC9, 0xC9, 0x07, 0x00];
+    assert_eq!(client.decompress(&hello, 100).unwrap(), b"Hello");
+    assert_eq!(
+        client
+            .decompress(&[0xF2, 0x00, 0x11, 0x00, 0x00], 100)
+            .unwrap(),
+        b"Hello"
+    );
+
+    let message = "the same state, again and again ".repeat(20);
+    for (config, shared) in [
+        (config.clone(), true),
+        (
+            DeflateConfig {
+                server_no_context_takeover: true,
+                server_max_window_bits: 9,
+                ..config.clone()
+            },
+            false,
+        ),
+    ] {
+        let mut server = Deflate::new(config.clone(), false);
+        let mut client = Deflate::new(config, true);
+        let first = server.compress(message.as_bytes()).unwrap();
+        let second = server.compress(message.as_bytes()).unwrap();
+        assert!(first.len() < message.len() / 4);
+        assert_eq!(second.len() < first.len(), shared);
+        assert_eq!(client.decompress(&first, 1000).unwrap(), message.as_bytes());
+        assert_eq!(
+            client.decompress(&second, 1000).unwrap(),
+            message.as_bytes()
+        );
+    }
+
+    let mut server = Deflate::new(DeflateConfig::default(), false);
+    assert_eq!(server.compress(b"short"), None);
+    let bomb = server.compress(&[0; 100_000]).unwrap();
+    assert!(matches!(
+        client.decompress(&bomb, 1000),
+        Err(WebSocketError::MessageTooBig)
+    ));
+    assert!(matches!(
+        Deflate::new(config, true).decompress(&[0xFF; 8], 1000),
+        Err(WebSocketError::InvalidCompressedData)
+    ));
+}
+
+#[test]
+fn inflates_compressed_frames() {
+    let config = DeflateConfig {
+        min_size: 0,
+        ..DeflateConfig::default()
+    };
+    let exchange =
+        |frames: &[Vec<u8>]| exchange_raw(WebSocketLimits::default(), Some(config.clone()), frames);
+    let mut client = Deflate::new(config.clone(), true);
+    let compressed = client.compress("κόσμε κόσμε κόσμε".as_bytes()).unwrap();
+    let (start, end) = compressed.split_at(compressed.len() / 2);
+
+    let replies = exchange(&[frame(0x41, start), frame(0x80, end), close(1000)]);
+    assert!(replies[0].rsv1);
+    assert_eq!(replies[0].opcode, OpCode::Text);
+    let mut client = Deflate::new(config.clone(), true);
+    let echo = client.decompress(&replies[0].payload, 100).unwrap();
+    assert_eq!(echo, "κόσμε κόσμε κόσμε".as_bytes());
+
+    let invalid_text = Deflate::new(config.clone(), true)
+        .compress(&[0xFF])
+        .unwrap();
+    let cases = [
+        (vec![frame(0x41, start), frame(0xC0, end)], 1002),
+        (vec![frame(0xC9, b"")], 1002),
+        (vec![frame(0xC1, &[0xFF; 4])], 1007),
+        (vec![frame(0xC1, &invalid_text)], 1007),
+    ];
+    for (frames, code) in cases {
+        assert_eq!(close_code(&exchange(&frames)), Some(code));
+    }
+}
+
+#[test]
+fn compresses_over_loopback() {
+    let server_config = DeflateConfig {
+        server_no_context_takeover: true,
+        ..DeflateConfig::default()
+    };
+    let server = HttpServer::new(Router::new())
+        .websocket_deflate(server_config.clone())
+        .on_websocket(|websocket, _| echo(websocket))
+        .bind("127.0.0.1:0")
+        .unwrap();
+
+    let url = format!("ws://{}/", server.local_addr());
+    assert_eq!(WebSocket::connect(&url).unwrap().deflate(), None);
+
+    let mut client = WebSocket::connect_with(
+        &url,
+        Some(&DeflateConfig::default()),
+        &WebSocketTimeouts::default(),
+    )
+    .unwrap();
+    assert_eq!(client.deflate(), Some(&server_config));
+
+    let text = "position 1.0 2.0 3.0; ".repeat(500);
+    client.send_message(&Message::from(text.as_str())).unwrap();
+    client.send_message(&Message::from(text.as_str())).unwrap();
+    client.send(b"tiny", MessageDataType::Binary).unwrap();
+    client.close();
+    client.flush().unwrap();
+
+    let mut data = Vec::new();
+    client.stream.set_read_timeout(None).unwrap();
+    client
+        .stream
+        .try_clone()
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+
+    let mut raw = FrameReader::new(false, usize::MAX);
+    raw.allow_rsv1(true);
+    raw.feed(&data);
+    let frames: Vec<_> = std::iter::from_fn(|| raw.next_frame().unwrap()).collect();
+    assert_eq!(frames.len(), 4);
+    assert!(frames[0].rsv1 && frames[0].payload.len() < 200);
+    // The server starts every message with an empty window.
+    assert_eq!(frames[0].payload, frames[1].payload);
+    assert!(!frames[2].rsv1);
+
+    client.reader.feed(&data);
+    let events: Vec<_> = std::iter::from_fn(|| client.next_event().unwrap()).collect();
+    assert_eq!(
+        events,
+        [
+            Event::Message(Message::Text(text.clone())),
+            Event::Message(Message::Text(text)),
+            Event::Message(Message::Binary(b"tiny".to_vec())),
+            Event::Close(CloseCode::Normal, String::new()),
+        ]
+    );
+
+    drop(client);
+    server.shutdown();
+}
+
+#[test]
+fn upgrades_do_not_hold_up_workers() {
+    let server = HttpServer::new(Router::new().get("/", |_, _| Response::ok().text("ok")))
+        .workers(1)
+        .on_websocket(|websocket, _| echo(websocket))
+        .bind("127.0.0.1:0")
+        .unwrap();
+
+    let url = format!("ws://{}/", server.local_addr());
+    let mut clients: Vec<_> = (0..3).map(|_| WebSocket::connect(&url).unwrap()).collect();
+    clients[2]
+        .send(b"still there", MessageDataType::Text)
+        .unwrap();
+    clients[2].flush().unwrap();
+    assert_eq!(receive(&mut clients[2]), Message::from("still there"));
+
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    // Shutting down closes the open connections instead of waiting for them.
+    server.shutdown();
+    for client in &mut clients {
+        let mut data = Vec::new();
+        let _ = client.stream.read_to_end(&mut data);
+        client.reader.feed(&data);
+        assert_eq!(
+            client.next_event().unwrap(),
+            Some(Event::Close(CloseCode::GoingAway, String::new()))
+        );
+    }
+}
+
+/// Reads the next message from a client socket.
+fn receive(websocket: &mut WebSocket) -> Message {
+    let mut stream = websocket.stream.try_clone().unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut buffer = [0; 4096];
+    loop {
+        if let Some(Event::Message(message)) = websocket.next_event().unwrap() {
+            return message;
+        }
+        let len = stream.read(&mut buffer).unwrap();
+        assert!(len > 0, "connection closed");
+        websocket.reader.feed(&buffer[..len]);
+    }
+}
+
+fn wait_until(condition: impl Fn() -> bool) {
+    for _ in 0..500 {
+        if condition() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("condition not met in time");
+}
+
+#[test]
+fn hub_tracks_rooms() {
+    let hub = WebSocketHub::new();
+    let (first, _first_client) = pair(WebSocketLimits::default());
+    let (second, _second_client) = pair(WebSocketLimits::default());
+    let a = hub.add(&first);
+    let b = hub.add(&second);
+    assert_ne!(a, b);
+    assert_eq!(hub.len(), 2);
+
+    assert!(hub.join(a, "lobby"));
+    assert!(hub.join(b, "lobby"));
+    assert!(hub.join(b, "game"));
+    assert!(!hub.join(99, "lobby"));
+    assert_eq!(hub.members("lobby"), [a, b]);
+    assert_eq!(hub.rooms(b), ["game", "lobby"]);
+
+    assert!(hub.leave(b, "game"));
+    assert!(!hub.leave(b, "game"));
+    assert!(hub.members("game").is_empty());
+    assert_eq!(hub.broadcast_room("game", &Message::from("x"), None), 0);
+
+    // Sending to a closed connection removes it.
+    let mut closing = first.clone();
+    closing.close();
+    assert!(matches!(
+        hub.send(a, &Message::from("x")),
+        Err(WebSocketError::Closed)
+    ));
+    assert!(!hub.contains(a));
+    assert_eq!(hub.members("lobby"), [b]);
+
+    assert_eq!(hub.broadcast(&Message::from("x"), Some(b)), 0);
+    assert!(hub.remove(b));
+    assert!(!hub.remove(b));
+    assert!(hub.is_empty());
+    assert!(hub.members("lobby").is_empty());
+}
+
+/// Forwards every message to the other members of the room named by the request path.
+struct Chat {
+    id: ConnectionId,
+    room: String,
+    hub: WebSocketHub,
+    websocket: WebSocket,
+}
+
+impl WebSocketHandler for Chat {
+    fn websocket_mut(&mut self) -> &mut WebSocket {
+        &mut self.websocket
+    }
+
+    fn on_message(&mut self, message: Message) {
+        self.hub.broadcast_room(&self.room, &message, Some(self.id));
+    }
+}
+
+#[test]
+fn hub_broadcasts_over_loopback() {
+    let hub = WebSocketHub::new();
+    let server_hub = hub.clone();
+    let server = HttpServer::new(Router::new())
+        .workers(4)
+        .on_websocket(move |websocket, request| {
+            let hub = server_hub.clone();
+            let id = hub.add(&websocket);
+            let room = request.path.trim_start_matches('/').to_string();
+            hub.join(id, &room);
+            let chat = Chat {
+                id,
+                room,
+                hub: hub.clone(),
+                websocket,
+            };
+            hub.run(id, Arc::new(RwLock::new(chat)));
+        })
+        .bind("127.0.0.1:0")
+        .unwrap();
+
+    let connect =
+        |room| WebSocket::connect(&format!("ws://{}/{room}", server.local_addr())).unwrap();
+    let mut red = connect("red");
+    let mut red2 = connect("red");
+    let mut blue = connect("blue");
+    wait_until(|| hub.len() == 3);
+    assert_eq!(hub.members("red").len(), 2);
+
+    red.send(b"hi", MessageDataType::Text).unwrap();
+    red.flush().unwrap();
+    assert_eq!(receive(&mut red2), Message::from("hi"));
+
+    assert_eq!(hub.broadcast(&Message::from("all"), None), 3);
+    // The sender and the other room did not get the first message.
+    assert_eq!(receive(&mut red), Message::from("all"));
+    assert_eq!(receive(&mut red2), Message::from("all"));
+    assert_eq!(receive(&mut blue), Message::from("all"));
+
+    red2.close();
+    red2.flush().unwrap();
+    drop(blue);
+    wait_until(|| hub.len() == 1);
+    assert!(hub.members("blue").is_empty());
+    let message = Message::from("again");
+    assert_eq!(hub.broadcast_room("red", &message, None), 1);
+    assert_eq!(receive(&mut red), message);
+
+    drop(red);
+    wait_until(|| hub.is_empty());
+    server.shutdown();
+}
+
+#[test]
+fn poller_drives_many_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let (events, receiver) = channel();
+    let mut poller = WebSocketPoller::new().unwrap();
+    let mut clients = Vec::new();
+    for _ in 0..20 {
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let recorder = Recorder {
+            websocket: WebSocket::new(listener.accept().unwrap().0),
+            events: events.clone(),
+        };
+        poller.add(recorder).unwrap();
+        clients.push(client);
+    }
+    assert_eq!(poller.len(), 20);
+    assert_eq!(poller.poll(), 0);
+    // Idle connections let the poller wait for the whole timeout.
+    let start = Instant::now();
+    assert_eq!(poller.tick(Duration::from_millis(50)), 0);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    for (index, client) in clients.iter_mut().enumerate() {
+        client
+            .write_all(&frame(TEXT, format!("hi {index}").as_bytes()))
+            .unwrap();
+    }
+    let mut received = 0;
+    while received < 20 {
+        poller.tick(Duration::from_millis(100));
+        received += receiver.try_iter().count();
+    }
+    for (index, client) in clients.iter_mut().enumerate() {
+        let reply = encode_frame(TEXT, format!("HI {index}").as_bytes(), false);
+        let mut data = vec![0; reply.len()];
+        client.read_exact(&mut data).unwrap();
+        assert_eq!(data, reply);
+    }
+
+    for client in &mut clients {
+        client.write_all(&close(1000)).unwrap();
+    }
+    while !poller.is_empty() {
+        poller.tick(Duration::from_millis(100));
+    }
+    assert_eq!(
+        receiver.try_iter().collect::<Vec<_>>(),
+        ["close Normal "; 20]
+    );
+    for client in &mut clients {
+        let mut data = Vec::new();
+        client.read_to_end(&mut data).unwrap();
+        assert_eq!(data, [CLOSE, 2, 3, 232]);
+    }
+}
+
+#[test]
+fn poller_writes_large_messages_and_reports_drops() {
+    let (websocket, mut client) = pair(WebSocketLimits::default());
+    let (events, receiver) = channel();
+    let mut poller = WebSocketPoller::new().unwrap();
+    let id = poller.add(Recorder { websocket, events }).unwrap();
+
+    // Far more than the socket buffers take at once.
+    let message: Vec<u8> = (0..4 * 1024 * 1024).map(|i| i as u8).collect();
+    let websocket = &mut poller.get_mut(id).unwrap().websocket;
+    websocket.send(&message, MessageDataType::Binary).unwrap();
+    let expected = encode_frame(BINARY, &message, false);
+    let len = expected.len();
+    let reader = thread::spawn(move || {
+        let mut data = vec![0; len];
+        client.read_exact(&mut data).unwrap();
+        (client, data)
+    });
+    while poller.get_mut(id).unwrap().websocket.queued_bytes() > 0 {
+        poller.tick(Duration::from_millis(100));
+    }
+    let (client, data) = reader.join().unwrap();
+    assert!(data == expected);
+
+    drop(client);
+    while !poller.is_empty() {
+        poller.tick(Duration::from_millis(100));
+    }
+    assert_eq!(receiver.try_iter().collect::<Vec<_>>(), ["close Abnormal "]);
+}
+
+#[test]
+fn heartbeat_schedules_pings_and_timeouts() {
+    let start = Instant::now();
+    let second = Duration::from_secs(1);
+    let mut heartbeat = Heartbeat::new(
+        WebSocketTimeouts {
+            ping_interval: Some(10 * second),
+            idle_timeout: Some(25 * second),
+            handshake_timeout: Some(5 * second),
+        },
+        start,
+    );
+    assert_eq!(heartbeat.due(start), Due::Wait(Some(10 * second)));
+
+    let Due::Ping(first) = heartbeat.due(start + 10 * second) else {
+        panic!("no ping");
+    };
+    assert_eq!(
+        heartbeat.due(start + 10 * second),
+        Due::Wait(Some(10 * second))
+    );
+    heartbeat.ping_sent(&first, start + 10 * second);
+    let Due::Ping(second_ping) = heartbeat.due(start + 20 * second) else {
+        panic!("no ping");
+    };
+    assert_ne!(first, second_ping);
+    heartbeat.ping_sent(&second_ping, start + 20 * second);
+
+    // An unknown pong is ignored, a late one for the first ping still counts.
+    heartbeat.pong_received(b"other", start + 21 * second);
+    assert_eq!(heartbeat.last_rtt, None);
+    heartbeat.pong_received(&first, start + 21 * second);
+    assert_eq!(heartbeat.last_rtt, Some(11 * second));
+    heartbeat.pong_received(&second_ping, start + 22 * second);
+    assert_eq!(heartbeat.last_rtt, Some(2 * second));
+
+    heartbeat.received(10, start + 22 * second);
+    assert_eq!(heartbeat.bytes_in, 10);
+    assert_eq!(heartbeat.due(start + 47 * second), Due::Idle);
+
+    heartbeat.close_sent(start + 47 * second);
+    assert_eq!(heartbeat.due(start + 51 * second), Due::Wait(Some(second)));
+    assert_eq!(heartbeat.due(start + 52 * second), Due::Expired);
+}
+
+#[test]
+fn rejects_a_zero_ping_interval() {
+    let timeouts = WebSocketTimeouts {
+        ping_interval: Some(Duration::ZERO),
+        ..WebSocketTimeouts::default()
+    };
+    let result = std::panic::catch_unwind(|| pair(WebSocketLimits::default()).0.timeouts(timeouts));
+    assert!(result.is_err());
+}
+
+#[test]
+fn pings_and_closes_idle_connections() {
+    let (websocket, mut client) = pair(WebSocketLimits::default());
+    let websocket = websocket.timeouts(WebSocketTimeouts {
+        ping_interval: Some(Duration::from_millis(50)),
+        idle_timeout: Some(Duration::from_millis(300)),
+        handshake_timeout: Some(Duration::from_millis(200)),
+    });
+    let (events, receiver) = channel();
+    let recorder = Arc::new(RwLock::new(Recorder { websocket, events }));
+    let runner = {
+        let recorder = recorder.clone();
+        thread::spawn(move || WebSocket::run(recorder))
+    };
+
+    let start = Instant::now();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut reader = FrameReader::new(false, usize::MAX);
+    let mut frames = Vec::new();
+    let (mut bytes_in, mut bytes_out) = (0, 0);
+    let mut buffer = [0; 1024];
+    while let Ok(len @ 1..) = client.read(&mut buffer) {
+        bytes_in += len;
+        reader.feed(&buffer[..len]);
+        while let Some(received) = reader.next_frame().unwrap() {
+            if frames.is_empty() {
+                // Well before the read timeout of the connection.
+                assert!(start.elapsed() < Duration::from_millis(900));
+                assert_eq!(received, Frame::new(OpCode::Ping, 1u64.to_be_bytes()));
+                for data in [frame(PONG, &received.payload), frame(TEXT, b"hi")] {
+                    client.write_all(&data).unwrap();
+                    bytes_out += data.len();
+                }
+            }
+            frames.push(received);
+        }
+    }
+    runner.join().unwrap();
+
+    assert!(frames.contains(&Frame::new(OpCode::Text, "HI")));
+    assert_eq!(
+        frames.last(),
+        Some(&Frame::close(Some(CloseCode::GoingAway), "idle timeout"))
+    );
+    let events: Vec<_> = receiver.try_iter().collect();
+    assert_eq!(events[0], "pong [0, 0, 0, 0, 0, 0, 0, 1]");
+    assert_eq!(events[1], "Text(\"hi\")");
+    // The peer never answered the close frame.
+    assert_eq!(events.last().unwrap(), "close Abnormal ");
+
+    let stats = recorder.write().unwrap().websocket.stats();
+    assert_eq!(stats.bytes_in, bytes_out as u64);
+    assert_eq!(stats.bytes_out, bytes_in as u64);
+    assert_eq!((stats.messages_in, stats.messages_out), (1, 1));
+    assert!(stats.last_rtt.is_some());
+    assert!(stats.connected_since <= SystemTime::now());
+}
+
+#[test]
+fn poller_closes_idle_connections() {
+    let (websocket, mut client) = pair(WebSocketLimits::default());
+    let websocket = websocket.timeouts(WebSocketTimeouts {
+        ping_interval: None,
+        idle_timeout: Some(Duration::from_millis(100)),
+        handshake_timeout: Some(Duration::from_millis(100)),
+    });
+    let (events, receiver) = channel();
+    let mut poller = WebSocketPoller::new().unwrap();
+    poller.add(Recorder { websocket, events }).unwrap();
+    while !poller.is_empty() {
+        poller.tick(Duration::from_millis(50));
+    }
+    assert_eq!(receiver.try_iter().collect::<Vec<_>>(), ["close Abnormal "]);
+
+    let mut data = Vec::new();
+    client.read_to_end(&mut data).unwrap();
+    let mut reader = FrameReader::new(false, usize::MAX);
+    reader.feed(&data);
+    assert_eq!(
+        reader.next_frame().unwrap(),
+        Some(Frame::close(Some(CloseCode::GoingAway), "idle timeout"))
+    );
+}
@@ -1,6 +1,8 @@
-use std::fmt;
+use std::{fmt, io};
 
-#[derive(Debug)]
+use crate::serial::{ReadFrom, SerialError, SerialErrorKind, WriteTo};
+
+#[derive(Debug, WriteTo)]
 pub struct Date {
     pub year: u16,
     pub month: u8,
@@ -71,8 +73,101 @@ impl Date {
             sec,
         }
     }
+
+    pub fn to_unix_secs(&self) -> u64 {
+        let mut days = (1970..self.year)
+            .map(|year| if Self::is_leap(year) { 366 } else { 365 })
+            .sum::<u64>();
+        days += (1..self.month as u32)
+            .map(|month| Self::days_in_month(self.year, month) as u64)
+            .sum::<u64>();
+        days += self.day as u64 - 1;
+
+        ((days * 24 + self.hour as u64) * 60 + self.min as u64) * 60 + self.sec as u64
+    }
+
+    /// Formats the date as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+    pub fn to_http_date(&self) -> String {
+        const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+        let weekday = WEEKDAYS[(self.to_unix_secs() / 86400 % 7) as usize];
+
+        format!(
+            "{weekday}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            self.day,
+            MONTHS[self.month as usize - 1],
+            self.year,
+            self.hour,
+            self.min,
+            self.sec
+        )
+    }
+
+    /// Parses an HTTP date in the preferred format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+    pub fn parse_http_date(text: &str) -> Option<Date> {
+        let (_, rest) = text.split_once(", ")?;
+        let mut parts = rest.split(' ');
+        let (Some(day), Some(month), Some(year), Some(time), Some("GMT"), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return None;
+        };
+
+        let mut time = time.split(':').map(|part| part.parse::<u8>().ok());
+        let (Some(Some(hour)), Some(Some(min)), Some(Some(sec)), None) =
+            (time.next(), time.next(), time.next(), time.next())
+        else {
+            return None;
+        };
+
+        let date = Date {
+            year: year.parse().ok()?,
+            month: MONTHS.iter().position(|name| *name == month)? as u8 + 1,
+            day: day.parse().ok()?,
+            hour,
+            min,
+            sec,
+        };
+        (date.year >= 1970 && date.is_valid()).then_some(date)
+    }
+
+    /// Whether all fields are in range, which the conversions rely on.
+    fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=Self::days_in_month(self.year, self.month as u32)).contains(&(self.day as u32))
+            && self.hour < 24
+            && self.min < 60
+            && self.sec < 60
+    }
+}
+
+impl ReadFrom for Date {
+    fn read(data: &mut impl io::Read) -> io::Result<Self> {
+        let date = Date {
+            year: u16::read(data)?,
+            month: u8::read(data)?,
+            day: u8::read(data)?,
+            hour: u8::read(data)?,
+            min: u8::read(data)?,
+            sec: u8::read(data)?,
+        };
+        if !date.is_valid() {
+            return Err(SerialError::new(SerialErrorKind::OutOfRange)
+                .with_source(format!("invalid date {date}"))
+                .into());
+        }
+        Ok(date)
+    }
 }
 
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
 impl fmt::Display for Date {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -1,7 +1,9 @@
 use std::ops::{Index, IndexMut};
 
 use super::Vec4;
+use crate::serial::{ReadFrom, WriteTo};
 
+#[derive(ReadFrom, WriteTo)]
 pub struct Matrix4 {
     pub x: Vec4,
     pub y: Vec4,
@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+use crate::serial::{ReadFrom, WriteTo};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, ReadFrom, WriteTo)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -4,7 +4,9 @@ use std::{
     ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
-#[derive(Debug, Clone, Default)]
+use crate::serial::{ReadFrom, WriteTo};
+
+#[derive(Debug, Clone, Default, ReadFrom, WriteTo)]
 pub struct Vec2<T> {
     pub x: T,
     pub y: T,
@@ -3,7 +3,9 @@ use std::{
     ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
-#[derive(Debug, Clone, Copy, Default)]
+use crate::serial::{ReadFrom, WriteTo};
+
+#[derive(Debug, Clone, Copy, Default, ReadFrom, WriteTo)]
 pub struct Vec4 {
     pub x: f32,
     pub y: f32,
@@ -0,0 +1,456 @@
+use std::io::{Read, Result, Write};
+
+use crate::serial::{ReadFrom, SerialError, SerialErrorKind, WriteTo};
+
+/// Packs values into a bitstream, least significant bit first.
+///
+/// Byte-oriented values can be mixed in with [`write_aligned`](Self::write_aligned), which
+/// pads the current byte with zeros first.
+pub struct BitWriter<W: Write> {
+    writer: W,
+    byte: u8,
+    len: u32,
+}
+
+impl<W: Write> BitWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            byte: 0,
+            len: 0,
+        }
+    }
+
+    /// Writes the lowest `bits` bits of `value`.
+    pub fn write_bits(&mut self, value: u64, bits: u32) -> Result<()> {
+        assert!(bits <= 64, "at most 64 bits can be written at once");
+        let mut value = value;
+        let mut bits = bits;
+
+        while bits > 0 {
+            let take = bits.min(8 - self.len);
+            self.byte |= ((value & ((1 << take) - 1)) as u8) << self.len;
+            self.len += take;
+            value >>= take;
+            bits -= take;
+
+            if self.len == 8 {
+                self.writer.write_all(&[self.byte])?;
+                self.byte = 0;
+                self.len = 0;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_bool(&mut self, value: bool) -> Result<()> {
+        self.write_bits(value as u64, 1)
+    }
+
+    /// Quantizes `value` and writes it with the bits of the quantizer.
+    pub fn write_quantized(&mut self, value: f32, quantizer: &Quantizer) -> Result<()> {
+        self.write_bits(quantizer.quantize(value), quantizer.bits())
+    }
+
+    pub fn write_packed<T: WriteBits + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.write_bits(self)
+    }
+
+    /// Pads the current byte with zero bits.
+    pub fn align(&mut self) -> Result<()> {
+        if self.len != 0 {
+            self.writer.write_all(&[self.byte])?;
+            self.byte = 0;
+            self.len = 0;
+        }
+        Ok(())
+    }
+
+    /// Aligns to the next byte and writes a byte-oriented value.
+    pub fn write_aligned<T: WriteTo + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.align()?;
+        value.write(&mut self.writer)
+    }
+
+    /// Pads the last byte and returns the writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.align()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads values from a bitstream written by [`BitWriter`].
+pub struct BitReader<R: Read> {
+    reader: R,
+    byte: u8,
+    len: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            byte: 0,
+            len: 0,
+        }
+    }
+
+    pub fn read_bits(&mut self, bits: u32) -> Result<u64> {
+        assert!(bits <= 64, "at most 64 bits can be read at once");
+        let mut value = 0;
+        let mut read = 0;
+
+        while read < bits {
+            if self.len == 0 {
+                self.byte = u8::read(&mut self.reader)?;
+                self.len = 8;
+            }
+
+            let take = (bits - read).min(self.len);
+            value |= (self.byte as u64 & ((1 << take) - 1)) << read;
+            self.byte = self.byte.checked_shr(take).unwrap_or(0);
+            self.len -= take;
+            read += take;
+        }
+        Ok(value)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    pub fn read_quantized(&mut self, quantizer: &Quantizer) -> Result<f32> {
+        quantizer.dequantize(self.read_bits(quantizer.bits())?)
+    }
+
+    pub fn read_packed<T: ReadBits>(&mut self) -> Result<T> {
+        T::read_bits(self)
+    }
+
+    /// Skips the padding bits of the current byte.
+    pub fn align(&mut self) {
+        self.byte = 0;
+        self.len = 0;
+    }
+
+    /// Aligns to the next byte and reads a byte-oriented value.
+    pub fn read_aligned<T: ReadFrom>(&mut self) -> Result<T> {
+        self.align();
+        T::read(&mut self.reader)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Maps floats in `min..=max` onto evenly spaced integer steps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantizer {
+    min: f32,
+    max: f32,
+    steps: u64,
+    bits: u32,
+}
+
+impl Quantizer {
+    /// Uses as many bits as needed so values are off by at most half of `precision`.
+    pub fn new(min: f32, max: f32, precision: f32) -> Self {
+        assert!(min < max && precision > 0.0);
+        let steps = ((max as f64 - min as f64) / precision as f64).ceil() as u64;
+        Self {
+            min,
+            max,
+            steps,
+            bits: u64::BITS - steps.leading_zeros(),
+        }
+    }
+
+    /// Uses exactly `bits` bits for the range.
+    pub fn with_bits(min: f32, max: f32, bits: u32) -> Self {
+        assert!(min < max && (1..=32).contains(&bits));
+        Self {
+            min,
+            max,
+            steps: (1 << bits) - 1,
+            bits,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Converts a value to its step, clamping it into the range first.
+    pub fn quantize(&self, value: f32) -> u64 {
+        let value = value.clamp(self.min, self.max) as f64;
+        let normalized = (value - self.min as f64) / (self.max as f64 - self.min as f64);
+        (normalized * self.steps as f64).round() as u64
+    }
+
+    pub fn dequantize(&self, step: u64) -> Result<f32> {
+        if step > self.steps {
+            return Err(SerialError::new(SerialErrorKind::OutOfRange)
+                .with_source(format!("step {step} is above {}", self.steps))
+                .into());
+        }
+        let normalized = step as f64 / self.steps as f64;
+        Ok((self.min as f64 + normalized * (self.max as f64 - self.min as f64)) as f32)
+    }
+}
+
+/// An unsigned integer packed into `BITS` bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UBits<const BITS: u32>(pub u64);
+
+/// A signed integer packed into `BITS` bits as two's complement.
+///
+/// `BITS` has to be between 1 and 64, which is checked at compile time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SBits<const BITS: u32>(pub i64);
+
+impl<const BITS: u32> SBits<BITS> {
+    /// Fails to compile for widths the shifts below cannot handle.
+    const VALID_WIDTH: () = assert!(BITS > 0 && BITS <= 64, "SBits needs 1 to 64 bits");
+}
+
+/// A trait for writing data into a bitstream.
+pub trait WriteBits {
+    fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()>;
+}
+
+/// A trait for reading data from a bitstream.
+pub trait ReadBits: Sized {
+    fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self>;
+}
+
+impl WriteBits for bool {
+    fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()> {
+        writer.write_bool(*self)
+    }
+}
+
+impl ReadBits for bool {
+    fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        reader.read_bool()
+    }
+}
+
+macro_rules! impl_bits {
+    ($($unsigned:ty, $signed:ty),*) => {$(
+        impl WriteBits for $unsigned {
+            fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()> {
+                writer.write_bits(*self as u64, <$unsigned>::BITS)
+            }
+        }
+
+        impl ReadBits for $unsigned {
+            fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self> {
+                Ok(reader.read_bits(<$unsigned>::BITS)? as $unsigned)
+            }
+        }
+
+        impl WriteBits for $signed {
+            fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()> {
+                writer.write_bits(*self as $unsigned as u64, <$signed>::BITS)
+            }
+        }
+
+        impl ReadBits for $signed {
+            fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self> {
+                Ok(reader.read_bits(<$signed>::BITS)? as $unsigned as $signed)
+            }
+        }
+    )*};
+}
+
+impl_bits!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl<const BITS: u32> WriteBits for UBits<BITS> {
+    fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()> {
+        if BITS < 64 && self.0 >> BITS != 0 {
+            Err(SerialError::new(SerialErrorKind::OutOfRange)
+                .with_source(format!("{} does not fit into {BITS} bits", self.0)))?;
+        }
+        writer.write_bits(self.0, BITS)
+    }
+}
+
+impl<const BITS: u32> ReadBits for UBits<BITS> {
+    fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        reader.read_bits(BITS).map(Self)
+    }
+}
+
+impl<const BITS: u32> WriteBits for SBits<BITS> {
+    fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()> {
+        let () = Self::VALID_WIDTH;
+        let shift = 64 - BITS;
+        if (self.0 << shift) >> shift != self.0 {
+            Err(SerialError::new(SerialErrorKind::OutOfRange)
+                .with_source(format!("{} does not fit into {BITS} bits", self.0)))?;
+        }
+        writer.write_bits(self.0 as u64, BITS)
+    }
+}
+
+impl<const BITS: u32> ReadBits for SBits<BITS> {
+    fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        let () = Self::VALID_WIDTH;
+        let shift = 64 - BITS;
+        let value = reader.read_bits(BITS)?;
+        Ok(Self(((value << shift) as i64) >> shift))
+    }
+}
+
+impl<T: WriteBits> WriteBits for Option<T> {
+    fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()> {
+        writer.write_bool(self.is_some())?;
+        if let Some(value) = self {
+            value.write_bits(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ReadBits> ReadBits for Option<T> {
+    fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        if reader.read_bool()? {
+            Ok(Some(T::read_bits(reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: WriteBits, const N: usize> WriteBits for [T; N] {
+    fn write_bits(&self, writer: &mut BitWriter<impl Write>) -> Result<()> {
+        for item in self {
+            item.write_bits(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ReadBits + Default + Copy, const N: usize> ReadBits for [T; N] {
+    fn read_bits(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        let mut items = [T::default(); N];
+        for item in &mut items {
+            *item = T::read_bits(reader)?;
+        }
+        Ok(items)
+    }
+}
+
+/// Bit-packs a value into whole bytes, so it can be used wherever `ReadFrom`/`WriteTo` is
+/// expected, e.g. as a field of a derived struct.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Packed<T>(pub T);
+
+impl<T: WriteBits> WriteTo for Packed<T> {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        let mut bits = BitWriter::new(writer);
+        self.0.write_bits(&mut bits)?;
+        bits.finish()?;
+        Ok(())
+    }
+}
+
+impl<T: ReadBits> ReadFrom for Packed<T> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        T::read_bits(&mut BitReader::new(data)).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_bits() {
+        let mut writer = BitWriter::new(Vec::new());
+        writer.write_bool(true).unwrap();
+        writer.write_bits(0b101, 3).unwrap();
+        writer.write_bits(0xabc, 12).unwrap();
+        writer.write_bits(u64::MAX, 64).unwrap();
+        writer.write_bool(false).unwrap();
+        let buf = writer.finish().unwrap();
+        assert_eq!(buf.len(), 11);
+        assert_eq!(buf[0], 0b1100_1011);
+
+        let mut reader = BitReader::new(buf.as_slice());
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert_eq!(reader.read_bits(12).unwrap(), 0xabc);
+        assert_eq!(reader.read_bits(64).unwrap(), u64::MAX);
+        assert!(!reader.read_bool().unwrap());
+        assert!(reader.read_bits(8).is_err());
+    }
+
+    #[test]
+    fn mixes_with_bytes() {
+        let mut writer = BitWriter::new(Vec::new());
+        writer.write_packed(&UBits::<3>(5)).unwrap();
+        writer.write_aligned("aligned").unwrap();
+        writer.write_packed(&SBits::<5>(-7)).unwrap();
+        writer.write_packed(&Some(true)).unwrap();
+        writer.write_aligned(&0x1234u16).unwrap();
+        let buf = writer.finish().unwrap();
+        assert_eq!(buf.len(), 1 + 4 + 7 + 1 + 2);
+
+        let mut reader = BitReader::new(buf.as_slice());
+        assert_eq!(reader.read_packed::<UBits<3>>().unwrap().0, 5);
+        assert_eq!(reader.read_aligned::<String>().unwrap(), "aligned");
+        assert_eq!(reader.read_packed::<SBits<5>>().unwrap().0, -7);
+        assert_eq!(reader.read_packed::<Option<bool>>().unwrap(), Some(true));
+        assert_eq!(reader.read_aligned::<u16>().unwrap(), 0x1234);
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        let mut writer = BitWriter::new(Vec::new());
+        assert!(writer.write_packed(&UBits::<3>(8)).is_err());
+        assert!(writer.write_packed(&SBits::<4>(8)).is_err());
+        assert!(writer.write_packed(&SBits::<4>(-9)).is_err());
+        assert!(writer.write_packed(&SBits::<4>(-8)).is_ok());
+        assert!(writer.write_packed(&UBits::<64>(u64::MAX)).is_ok());
+    }
+
+    #[test]
+    fn quantized_floats() {
+        let quantizer = Quantizer::new(-100.0, 100.0, 0.01);
+        assert_eq!(quantizer.bits(), 15);
+
+        let mut writer = BitWriter::new(Vec::new());
+        let values = [-100.0, -3.1, 0.0, 42.424, 100.0, 250.0];
+        for value in values {
+            writer.write_quantized(value, &quantizer).unwrap();
+        }
+        let buf = writer.finish().unwrap();
+        assert_eq!(buf.len(), (15 * values.len()).div_ceil(8));
+
+        let mut reader = BitReader::new(buf.as_slice());
+        for value in values {
+            let read = reader.read_quantized(&quantizer).unwrap();
+            assert!((read - value.clamp(-100.0, 100.0)).abs() <= 0.005 + f32::EPSILON * 100.0);
+        }
+
+        let angle = Quantizer::with_bits(0.0, 1.0, 8);
+        assert_eq!(angle.quantize(1.0), 255);
+        assert_eq!(angle.dequantize(0).unwrap(), 0.0);
+        assert!(quantizer.dequantize((1 << 15) - 1).is_err());
+    }
+
+    #[test]
+    fn packed_in_byte_stream() {
+        let flags = Packed([true, false, true, true, false, false, false, true, true]);
+        let mut buf = Vec::new();
+        flags.write(&mut buf).unwrap();
+        7u8.write(&mut buf).unwrap();
+        assert_eq!(buf, [0b1000_1101, 0b1, 7]);
+
+        let mut data = buf.as_slice();
+        assert_eq!(Packed::<[bool; 9]>::read(&mut data).unwrap(), flags);
+        assert_eq!(u8::read(&mut data).unwrap(), 7);
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Result, Write},
+};
+
+use crate::serial::{
+    self, Crc32, ReadFrom, SerialError, SerialErrorKind, WriteTo, decode_bytes, encode,
+};
+
+/// The layout version of the envelope itself, independent of the payload schema.
+const FORMAT: u8 = 1;
+const FLAG_DEFLATE: u8 = 1;
+const DEFAULT_MAX_LEN: usize = 64 << 20;
+
+type Migration = Box<dyn Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync>;
+
+#[derive(serial::ReadFrom, serial::WriteTo)]
+struct Header {
+    magic: [u8; 4],
+    format: u8,
+    flags: u8,
+    version: u16,
+    len: u32,
+    crc: u32,
+}
+
+/// A versioned and checksummed envelope for save games and cached data.
+///
+/// The header holds the magic bytes, the schema version of the payload, the payload length
+/// and a CRC-32 of the stored payload. Payloads from older schema versions are upgraded with
+/// the registered migrations before they are decoded.
+pub struct Container {
+    magic: [u8; 4],
+    version: u16,
+    compress: bool,
+    max_len: usize,
+    migrations: BTreeMap<u16, Migration>,
+}
+
+impl Container {
+    /// Creates a container for payloads with the current schema `version`.
+    pub fn new(magic: [u8; 4], version: u16) -> Self {
+        Self {
+            magic,
+            version,
+            compress: false,
+            max_len: DEFAULT_MAX_LEN,
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Deflates the payload when saving.
+    #[cfg(feature = "deflate")]
+    pub fn compressed(mut self) -> Self {
+        self.compress = true;
+        self
+    }
+
+    /// Sets the largest payload accepted on load, compressed and uncompressed.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Registers a function that upgrades an encoded payload from schema version `from` to
+    /// `from + 1`.
+    pub fn migration(
+        mut self,
+        from: u16,
+        migrate: impl Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.migrations.insert(from, Box::new(migrate));
+        self
+    }
+
+    /// Registers a migration from version `from` to `from + 1` by decoding the old type and
+    /// converting it into the new one.
+    pub fn migrate<Old: ReadFrom, New: WriteTo>(
+        self,
+        from: u16,
+        migrate: impl Fn(Old) -> New + Send + Sync + 'static,
+    ) -> Self {
+        self.migration(from, move |payload| {
+            let old = decode_bytes::<Old>(payload)?;
+            Ok(encode(&migrate(old))?)
+        })
+    }
+
+    pub fn save<T: WriteTo + ?Sized>(&self, value: &T, writer: &mut impl Write) -> Result<()> {
+        let payload = encode(value)?;
+        let (flags, payload) = if self.compress {
+            (FLAG_DEFLATE, deflate(&payload)?)
+        } else {
+            (0, payload)
+        };
+
+        let header = Header {
+            magic: self.magic,
+            format: FORMAT,
+            flags,
+            version: self.version,
+            len: payload
+                .len()
+                .try_into()
+                .map_err(|_| SerialErrorKind::BoundExceeded)?,
+            crc: Crc32::checksum(&payload),
+        };
+        header.write(writer)?;
+        writer.write_all(&payload)
+    }
+
+    pub fn load<T: ReadFrom>(&self, reader: &mut impl Read) -> Result<T> {
+        let header = Header::read(reader)?;
+
+        if header.magic != self.magic {
+            Err(SerialErrorKind::InvalidMagic)?;
+        }
+        if header.format != FORMAT || header.flags & !FLAG_DEFLATE != 0 {
+            Err(SerialError::new(SerialErrorKind::Unsupported)
+                .with_source(format!("container format {}", header.format)))?;
+        }
+        if header.version > self.version {
+            Err(
+                SerialError::new(SerialErrorKind::Unsupported).with_source(format!(
+                    "schema version {} is newer than {}",
+                    header.version, self.version
+                )),
+            )?;
+        }
+
+        let len = header.len as usize;
+        if len > self.max_len {
+            Err(SerialErrorKind::BoundExceeded)?;
+        }
+        let mut payload = vec![0; len];
+        reader.read_exact(&mut payload)?;
+
+        if Crc32::checksum(&payload) != header.crc {
+            Err(SerialErrorKind::Checksum)?;
+        }
+
+        if header.flags & FLAG_DEFLATE != 0 {
+            payload = self.inflate(&payload)?;
+        }
+
+        for version in header.version..self.version {
+            let Some(migrate) = self.migrations.get(&version) else {
+                return Err(SerialError::new(SerialErrorKind::Unsupported)
+                    .with_source(format!("no migration from schema version {version}"))
+                    .into());
+            };
+            payload = migrate(&payload)?;
+        }
+
+        Ok(decode_bytes(&payload)?)
+    }
+
+    #[cfg(feature = "deflate")]
+    fn inflate(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut inflated = Vec::new();
+        flate2::read::DeflateDecoder::new(payload)
+            .take(self.max_len as u64 + 1)
+            .read_to_end(&mut inflated)?;
+
+        if inflated.len() > self.max_len {
+            Err(SerialErrorKind::BoundExceeded)?;
+        }
+        Ok(inflated)
+    }
+
+    #[cfg(not(feature = "deflate"))]
+    fn inflate(&self, _: &[u8]) -> Result<Vec<u8>> {
+        Err(SerialError::new(SerialErrorKind::Unsupported)
+            .with_source("compressed containers need the `deflate` feature")
+            .into())
+    }
+}
+
+#[cfg(feature = "deflate")]
+fn deflate(payload: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(payload)?;
+    encoder.finish()
+}
+
+#[cfg(not(feature = "deflate"))]
+fn deflate(_: &[u8]) -> Result<Vec<u8>> {
+    unreachable!("compression can only be enabled with the `deflate` feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::{ReadFrom, WriteTo};
+
+    #[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+    struct SaveV1 {
+        gold: u16,
+    }
+
+    #[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+    struct SaveV2 {
+        gold: u32,
+        level: u8,
+    }
+
+    #[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+    struct Save {
+        gold: u64,
+        level: u8,
+        #[serial(prefix = u8)]
+        name: String,
+    }
+
+    fn saves() -> Container {
+        Container::new(*b"SAVE", 3)
+            .migrate(1, |old: SaveV1| SaveV2 {
+                gold: old.gold as u32,
+                level: 1,
+            })
+            .migrate(2, |old: SaveV2| Save {
+                gold: old.gold as u64,
+                level: old.level,
+                name: "unnamed".to_string(),
+            })
+    }
+
+    fn error_kind(error: std::io::Error) -> SerialErrorKind {
+        SerialError::from_io(error).kind()
+    }
+
+    #[test]
+    fn round_trip() {
+        let save = Save {
+            gold: 100,
+            level: 4,
+            name: "hero".to_string(),
+        };
+        let mut buf = Vec::new();
+        saves().save(&save, &mut buf).unwrap();
+        assert_eq!(&buf[..4], b"SAVE");
+        assert_eq!(saves().load::<Save>(&mut buf.as_slice()).unwrap(), save);
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn compressed_round_trip() {
+        let save = Save {
+            gold: 1,
+            level: 2,
+            name: "a".repeat(200),
+        };
+        let mut buf = Vec::new();
+        saves().compressed().save(&save, &mut buf).unwrap();
+        assert!(buf.len() < 100);
+        assert_eq!(saves().load::<Save>(&mut buf.as_slice()).unwrap(), save);
+
+        assert_eq!(
+            error_kind(
+                saves()
+                    .max_len(150)
+                    .load::<Save>(&mut buf.as_slice())
+                    .unwrap_err()
+            ),
+            SerialErrorKind::BoundExceeded
+        );
+    }
+
+    #[test]
+    fn migrates_old_versions() {
+        let mut buf = Vec::new();
+        Container::new(*b"SAVE", 1)
+            .save(&SaveV1 { gold: 7 }, &mut buf)
+            .unwrap();
+
+        let save = saves().load::<Save>(&mut buf.as_slice()).unwrap();
+        assert_eq!(
+            save,
+            Save {
+                gold: 7,
+                level: 1,
+                name: "unnamed".to_string()
+            }
+        );
+
+        let without_migration = Container::new(*b"SAVE", 2);
+        let error = without_migration
+            .load::<SaveV2>(&mut buf.as_slice())
+            .unwrap_err();
+        assert_eq!(error_kind(error), SerialErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        let mut buf = Vec::new();
+        saves().save(&SaveV1 { gold: 1 }, &mut buf).unwrap();
+
+        let error = Container::new(*b"SAVX", 3)
+            .load::<Save>(&mut buf.as_slice())
+            .unwrap_err();
+        assert_eq!(error_kind(error), SerialErrorKind::InvalidMagic);
+
+        let error = Container::new(*b"SAVE", 2)
+            .load::<Save>(&mut buf.as_slice())
+            .unwrap_err();
+        assert_eq!(error_kind(error), SerialErrorKind::Unsupported);
+
+        let mut corrupted = buf.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        let error = saves().load::<Save>(&mut corrupted.as_slice()).unwrap_err();
+        assert_eq!(error_kind(error), SerialErrorKind::Checksum);
+
+        let error = saves()
+            .load::<Save>(&mut &buf[..buf.len() - 1])
+            .unwrap_err();
+        assert_eq!(error_kind(error), SerialErrorKind::Eof);
+
+        // The payload is a `SaveV1`, which is too short for a `Save`.
+        let error = saves().load::<Save>(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(error_kind(error), SerialErrorKind::Eof);
+    }
+}
@@ -0,0 +1,190 @@
+use std::marker::PhantomData;
+
+use crate::serial::{
+    CountingReader, DEFAULT_BOUND, ReadFrom, SerialError, SerialErrorKind, prefixed_read,
+};
+
+/// The result of [`FrameDecoder::decode`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded<T> {
+    /// At least this many more bytes have to be fed before the next frame is complete.
+    NeedMore(usize),
+    /// A complete frame.
+    Frame(T),
+}
+
+/// Decodes frames written by [`write_framed`](super::write_framed) from data that arrives in
+/// chunks of any size.
+///
+/// A frame whose value fails to decode is skipped, so decoding can continue after the error.
+/// An invalid or too large length prefix cannot be skipped and is reported again until
+/// [`clear`](Self::clear) is called.
+pub struct FrameDecoder<P, T> {
+    buf: Vec<u8>,
+    start: usize,
+    offset: u64,
+    bound: usize,
+    _marker: PhantomData<fn() -> (P, T)>,
+}
+
+impl<P: TryInto<usize> + ReadFrom, T: ReadFrom> FrameDecoder<P, T> {
+    pub fn new() -> Self {
+        Self::with_bound(DEFAULT_BOUND)
+    }
+
+    /// Accepts frames of at most `bound` bytes.
+    pub fn with_bound(bound: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            start: 0,
+            offset: 0,
+            bound,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Appends received data.
+    pub fn feed(&mut self, data: &[u8]) {
+        if self.start > 0 && self.start >= self.buf.len() / 2 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Decodes the next frame if it is complete.
+    ///
+    /// Errors carry the offset in the whole stream that was fed.
+    pub fn decode(&mut self) -> Result<Decoded<T>, SerialError> {
+        let mut data = &self.buf[self.start..];
+        let available = data.len();
+
+        let len = match prefixed_read::read_len::<P>(&mut data, self.bound) {
+            Ok(len) => len,
+            Err(e) => {
+                let error = SerialError::from_io(e);
+                if error.kind() == SerialErrorKind::Eof {
+                    return Ok(Decoded::NeedMore(1));
+                }
+                return Err(error.at(self.offset));
+            }
+        };
+
+        let header = available - data.len();
+        if data.len() < len {
+            return Ok(Decoded::NeedMore(len - data.len()));
+        }
+
+        let frame_offset = self.offset + header as u64;
+        self.start += header + len;
+        self.offset += (header + len) as u64;
+
+        let mut reader = CountingReader::new(&data[..len]);
+        let value = T::read(&mut reader)
+            .map_err(|e| SerialError::from_io(e).at(frame_offset + reader.count()))?;
+
+        if reader.count() != len as u64 {
+            return Err(
+                SerialError::new(SerialErrorKind::TrailingBytes).at(frame_offset + reader.count())
+            );
+        }
+        Ok(Decoded::Frame(value))
+    }
+
+    /// The number of bytes fed but not yet decoded.
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// Drops all buffered data.
+    pub fn clear(&mut self) {
+        self.offset += self.buffered() as u64;
+        self.buf.clear();
+        self.start = 0;
+    }
+}
+
+impl<P: TryInto<usize> + ReadFrom, T: ReadFrom> Default for FrameDecoder<P, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::{VarU32, write_framed};
+
+    fn frames() -> (Vec<u8>, Vec<(String, u32)>) {
+        let values = vec![
+            ("".to_string(), 0),
+            ("hello".to_string(), 7),
+            ("x".repeat(300), u32::MAX),
+        ];
+        let mut buf = Vec::new();
+        for value in &values {
+            write_framed::<VarU32, _>(value, &mut buf, DEFAULT_BOUND).unwrap();
+        }
+        (buf, values)
+    }
+
+    fn drain(decoder: &mut FrameDecoder<VarU32, (String, u32)>, out: &mut Vec<(String, u32)>) {
+        while let Decoded::Frame(value) = decoder.decode().unwrap() {
+            out.push(value);
+        }
+    }
+
+    #[test]
+    fn split_at_every_position() {
+        let (buf, values) = frames();
+
+        for split in 0..=buf.len() {
+            let mut decoder = FrameDecoder::new();
+            let mut out = Vec::new();
+            decoder.feed(&buf[..split]);
+            drain(&mut decoder, &mut out);
+            decoder.feed(&buf[split..]);
+            drain(&mut decoder, &mut out);
+            assert_eq!(out, values, "split at {split}");
+            assert_eq!(decoder.buffered(), 0);
+        }
+
+        let mut decoder = FrameDecoder::new();
+        let mut out = Vec::new();
+        for byte in &buf {
+            decoder.feed(std::slice::from_ref(byte));
+            drain(&mut decoder, &mut out);
+        }
+        assert_eq!(out, values);
+    }
+
+    #[test]
+    fn reports_missing_bytes() {
+        let mut decoder = FrameDecoder::<u16, u32>::new();
+        assert_eq!(decoder.decode().unwrap(), Decoded::NeedMore(1));
+        decoder.feed(&[4]);
+        assert_eq!(decoder.decode().unwrap(), Decoded::NeedMore(1));
+        decoder.feed(&[0, 1]);
+        assert_eq!(decoder.decode().unwrap(), Decoded::NeedMore(3));
+        decoder.feed(&[0, 0, 0]);
+        assert_eq!(decoder.decode().unwrap(), Decoded::Frame(1));
+    }
+
+    #[test]
+    fn errors_and_recovery() {
+        let mut decoder = FrameDecoder::<u8, u8>::with_bound(4);
+        decoder.feed(&[2, 1, 2, 1, 9, 5]);
+
+        let error = decoder.decode().unwrap_err();
+        assert_eq!(error.kind(), SerialErrorKind::TrailingBytes);
+        assert_eq!(error.offset(), Some(2));
+        assert_eq!(decoder.decode().unwrap(), Decoded::Frame(9));
+
+        let error = decoder.decode().unwrap_err();
+        assert_eq!(error.kind(), SerialErrorKind::BoundExceeded);
+        assert_eq!(error.offset(), Some(5));
+        decoder.clear();
+        decoder.feed(&[1, 3]);
+        assert_eq!(decoder.decode().unwrap(), Decoded::Frame(3));
+    }
+}
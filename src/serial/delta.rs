@@ -0,0 +1,278 @@
+use std::collections::VecDeque;
+use std::io::{Read, Result, Write};
+
+use crate::serial::{ReadFrom, SerialError, SerialErrorKind, VarU32, WriteTo};
+
+/// A trait for encoding a value as the changes from a baseline value.
+///
+/// The derive writes a bitmask of the fields that differ, followed by those fields.
+pub trait Delta: ReadFrom + WriteTo {
+    fn write_delta(&self, baseline: &Self, writer: &mut impl Write) -> Result<()>;
+
+    fn read_delta(baseline: &Self, data: &mut impl Read) -> Result<Self>;
+}
+
+const DEFAULT_HISTORY: usize = 32;
+
+/// Returns whether `a` is a later sequence number than `b`, allowing for wrap-around.
+fn is_newer(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+/// Writes snapshots as deltas against the latest one the receiver acknowledged.
+///
+/// Every snapshot starts with its sequence number and the sequence number of its
+/// baseline, where 0 means it is written in full.
+pub struct DeltaSender<T> {
+    sequence: u32,
+    acked: Option<(u32, T)>,
+    sent: VecDeque<(u32, T)>,
+    capacity: usize,
+}
+
+impl<T: Delta + Clone> DeltaSender<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_HISTORY)
+    }
+
+    /// Keeps up to `capacity` unacknowledged snapshots. Acks for older ones are ignored.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            sequence: 0,
+            acked: None,
+            sent: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Writes `value` and returns its sequence number.
+    pub fn write(&mut self, value: &T, writer: &mut impl Write) -> Result<u32> {
+        self.sequence = self.sequence.wrapping_add(1).max(1);
+        VarU32(self.sequence).write(writer)?;
+
+        match &self.acked {
+            Some((sequence, baseline)) => {
+                VarU32(*sequence).write(writer)?;
+                value.write_delta(baseline, writer)?;
+            }
+            None => {
+                VarU32(0).write(writer)?;
+                value.write(writer)?;
+            }
+        }
+
+        if self.sent.len() == self.capacity {
+            self.sent.pop_front();
+        }
+        self.sent.push_back((self.sequence, value.clone()));
+        Ok(self.sequence)
+    }
+
+    /// Marks the snapshot `sequence` as received, so later snapshots use it as baseline.
+    ///
+    /// Returns false if the snapshot is no longer known or older than the current baseline.
+    pub fn ack(&mut self, sequence: u32) -> bool {
+        if self
+            .acked
+            .as_ref()
+            .is_some_and(|(acked, _)| !is_newer(sequence, *acked))
+        {
+            return false;
+        }
+        let Some(index) = self.sent.iter().position(|(sent, _)| *sent == sequence) else {
+            return false;
+        };
+
+        self.acked = self.sent.drain(..=index).last();
+        true
+    }
+
+    /// The sequence number of the current baseline.
+    pub fn baseline(&self) -> Option<u32> {
+        self.acked.as_ref().map(|(sequence, _)| *sequence)
+    }
+
+    /// Forgets the baseline, so the next snapshot is written in full.
+    pub fn reset(&mut self) {
+        self.acked = None;
+        self.sent.clear();
+    }
+}
+
+impl<T: Delta + Clone> Default for DeltaSender<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads snapshots written by a [`DeltaSender`].
+///
+/// The sequence number of each snapshot should be sent back to the sender as an ack.
+pub struct DeltaReceiver<T> {
+    received: VecDeque<(u32, T)>,
+    capacity: usize,
+}
+
+impl<T: Delta + Clone> DeltaReceiver<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_HISTORY)
+    }
+
+    /// Keeps the last `capacity` snapshots as possible baselines.
+    ///
+    /// This should be at least the capacity of the sender.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            received: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Reads a snapshot and returns its sequence number along with the value.
+    pub fn read(&mut self, data: &mut impl Read) -> Result<(u32, T)> {
+        let sequence = VarU32::read(data)?.0;
+        let baseline = VarU32::read(data)?.0;
+
+        let value = if baseline == 0 {
+            T::read(data)?
+        } else {
+            let Some((_, baseline)) = self.received.iter().find(|(seq, _)| *seq == baseline) else {
+                return Err(SerialError::new(SerialErrorKind::MissingBaseline)
+                    .with_source(format!("snapshot {baseline} is not known"))
+                    .into());
+            };
+            T::read_delta(baseline, data)?
+        };
+
+        if self.received.len() == self.capacity {
+            self.received.pop_front();
+        }
+        self.received.push_back((sequence, value.clone()));
+        Ok((sequence, value))
+    }
+
+    /// The most recently received snapshot.
+    pub fn latest(&self) -> Option<&T> {
+        self.received.back().map(|(_, value)| value)
+    }
+}
+
+impl<T: Delta + Clone> Default for DeltaReceiver<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::{Delta, ReadFrom, WriteTo, encode};
+
+    #[derive(Debug, Clone, PartialEq, ReadFrom, WriteTo, Delta)]
+    struct Player {
+        id: u32,
+        name: String,
+        position: (f32, f32),
+        #[serial(big_endian)]
+        health: u16,
+        #[serial(prefix = u8)]
+        items: Vec<u16>,
+        #[serial(skip)]
+        cached: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, ReadFrom, WriteTo, Delta)]
+    struct Pair<T>(T, T);
+
+    fn player() -> Player {
+        Player {
+            id: 7,
+            name: "ferris".into(),
+            position: (1.0, 2.0),
+            health: 100,
+            items: vec![1, 2, 3],
+            cached: 0,
+        }
+    }
+
+    fn delta<T: Delta>(value: &T, baseline: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        value.write_delta(baseline, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn writes_changed_fields() {
+        let baseline = player();
+        assert_eq!(delta(&baseline, &baseline), [0]);
+
+        let mut moved = baseline.clone();
+        moved.position.0 = 3.5;
+        moved.health = 90;
+        moved.cached = 1;
+        let buf = delta(&moved, &baseline);
+        assert_eq!(buf.len(), 1 + 8 + 2);
+        assert_eq!(buf[0], 0b1100);
+        assert!(buf.len() < encode(&moved).unwrap().len());
+
+        let applied = Player::read_delta(&baseline, &mut buf.as_slice()).unwrap();
+        assert_eq!(applied, Player { cached: 0, ..moved });
+
+        let pair = Pair(1u8, 2u8);
+        let buf = delta(&Pair(1, 5), &pair);
+        assert_eq!(buf, [0b10, 5]);
+        assert_eq!(
+            Pair::read_delta(&pair, &mut buf.as_slice()).unwrap(),
+            Pair(1, 5)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let error = Pair::read_delta(&Pair(0u8, 0u8), &mut [0b100u8].as_slice()).unwrap_err();
+        assert_eq!(
+            SerialError::from_io(error).kind(),
+            SerialErrorKind::InvalidTag
+        );
+    }
+
+    #[test]
+    fn acked_baselines() {
+        let mut sender = DeltaSender::new();
+        let mut receiver = DeltaReceiver::new();
+        let mut state = player();
+
+        let mut full = Vec::new();
+        let first = sender.write(&state, &mut full).unwrap();
+        let (sequence, value) = receiver.read(&mut full.as_slice()).unwrap();
+        assert_eq!((sequence, &value), (first, &state));
+        assert!(sender.ack(sequence));
+
+        state.name.push('!');
+        let mut lost = Vec::new();
+        sender.write(&state, &mut lost).unwrap();
+
+        state.health -= 1;
+        let mut buf = Vec::new();
+        let third = sender.write(&state, &mut buf).unwrap();
+        assert!(buf.len() < full.len());
+        let (sequence, value) = receiver.read(&mut buf.as_slice()).unwrap();
+        assert_eq!((sequence, &value), (third, &state));
+        assert!(sender.ack(third));
+        assert!(!sender.ack(first));
+        assert_eq!(sender.baseline(), Some(third));
+
+        let mut other = DeltaReceiver::<Player>::new();
+        let error = other.read(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(
+            SerialError::from_io(error).kind(),
+            SerialErrorKind::MissingBaseline
+        );
+
+        sender.reset();
+        let mut buf = Vec::new();
+        sender.write(&state, &mut buf).unwrap();
+        assert_eq!(other.read(&mut buf.as_slice()).unwrap().1, state);
+        assert_eq!(other.latest(), Some(&state));
+    }
+}
@@ -0,0 +1,108 @@
+/// A number that is read and written in big-endian (network) byte order.
+///
+/// The plain number types use little-endian, which is the same as wrapping them in [`Le`].
+/// `Be` also works as the prefix type of [`PrefixedRead`](super::PrefixedRead) and
+/// [`PrefixedWrite`](super::PrefixedWrite), e.g. `read_prefixed::<Be<u16>>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Be<T>(pub T);
+
+/// A number that is read and written in little-endian byte order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Le<T>(pub T);
+
+impl<T> From<T> for Be<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> From<T> for Le<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+macro_rules! impl_prefix {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<usize> for Be<$ty> {
+            type Error = <$ty as TryFrom<usize>>::Error;
+
+            fn try_from(value: usize) -> Result<Self, Self::Error> {
+                <$ty>::try_from(value).map(Self)
+            }
+        }
+
+        #[allow(clippy::infallible_try_from)]
+        impl TryFrom<Be<$ty>> for usize {
+            type Error = <usize as TryFrom<$ty>>::Error;
+
+            fn try_from(value: Be<$ty>) -> Result<Self, Self::Error> {
+                usize::try_from(value.0)
+            }
+        }
+
+        impl TryFrom<usize> for Le<$ty> {
+            type Error = <$ty as TryFrom<usize>>::Error;
+
+            fn try_from(value: usize) -> Result<Self, Self::Error> {
+                <$ty>::try_from(value).map(Self)
+            }
+        }
+
+        #[allow(clippy::infallible_try_from)]
+        impl TryFrom<Le<$ty>> for usize {
+            type Error = <usize as TryFrom<$ty>>::Error;
+
+            fn try_from(value: Le<$ty>) -> Result<Self, Self::Error> {
+                usize::try_from(value.0)
+            }
+        }
+    )*};
+}
+
+impl_prefix!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::{PrefixedRead, PrefixedWrite, ReadFrom, WriteTo};
+
+    #[test]
+    fn byte_order() {
+        let mut buf = Vec::new();
+        Be(0x0102_0304u32).write(&mut buf).unwrap();
+        Le(0x0102_0304u32).write(&mut buf).unwrap();
+        0x0102_0304u32.write(&mut buf).unwrap();
+        Be(-2i16).write(&mut buf).unwrap();
+        Be(1.0f32).write(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            [
+                1, 2, 3, 4, 4, 3, 2, 1, 4, 3, 2, 1, 0xff, 0xfe, 0x3f, 0x80, 0, 0
+            ]
+        );
+
+        let mut data = buf.as_slice();
+        assert_eq!(Be::<u32>::read(&mut data).unwrap().0, 0x0102_0304);
+        assert_eq!(Le::<u32>::read(&mut data).unwrap().0, 0x0102_0304);
+        assert_eq!(u32::read(&mut data).unwrap(), 0x0102_0304);
+        assert_eq!(Be::<i16>::read(&mut data).unwrap().0, -2);
+        assert_eq!(Be::<f32>::read(&mut data).unwrap().0, 1.0);
+    }
+
+    #[test]
+    fn big_endian_prefix() {
+        let mut buf = Vec::new();
+        "net".write_prefixed::<Be<u16>>(&mut buf).unwrap();
+        assert_eq!(buf, [0, 3, b'n', b'e', b't']);
+        assert_eq!(
+            String::read_prefixed::<Be<u16>>(&mut buf.as_slice()).unwrap(),
+            "net"
+        );
+        assert!(
+            "a".repeat(300)
+                .write_prefixed::<Be<u8>>(&mut Vec::new())
+                .is_err()
+        );
+    }
+}
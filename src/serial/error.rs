@@ -0,0 +1,226 @@
+use std::{borrow::Cow, error::Error, fmt, io};
+
+/// What went wrong while reading or writing serial data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SerialErrorKind {
+    /// The data ended before the value was complete.
+    Eof,
+    /// A length is larger than the bound allowed for it.
+    BoundExceeded,
+    /// A string is not valid UTF-8.
+    InvalidUtf8,
+    /// An enum, `Option` or `Result` tag does not name a variant.
+    InvalidTag,
+    /// A bool is neither 0 nor 1.
+    InvalidBool,
+    /// A length prefix does not fit into the prefix type or into `usize`.
+    InvalidPrefix,
+    /// A variable-length integer is overlong or overflows its type.
+    InvalidVarInt,
+    /// A `char` is not a unicode scalar value.
+    InvalidChar,
+    /// A length does not match the fixed length of the value.
+    InvalidLength,
+    /// A number does not fit into the type it is read as.
+    OutOfRange,
+    /// A map or set contains the same key twice.
+    DuplicateKey,
+    /// A frame contains bytes after its value.
+    TrailingBytes,
+    /// The data does not start with the expected magic bytes.
+    InvalidMagic,
+    /// The checksum of the data does not match.
+    Checksum,
+    /// The data uses a format or version that cannot be read.
+    Unsupported,
+    /// A delta refers to a baseline that is not known.
+    MissingBaseline,
+    /// The underlying reader or writer failed.
+    Io(io::ErrorKind),
+}
+
+impl SerialErrorKind {
+    fn description(self) -> &'static str {
+        match self {
+            Self::Eof => "unexpected end of data",
+            Self::BoundExceeded => "length exceeds bound",
+            Self::InvalidUtf8 => "invalid UTF-8",
+            Self::InvalidTag => "invalid tag",
+            Self::InvalidBool => "invalid bool",
+            Self::InvalidPrefix => "invalid length prefix",
+            Self::InvalidVarInt => "invalid variable-length integer",
+            Self::InvalidChar => "invalid char",
+            Self::InvalidLength => "invalid length",
+            Self::OutOfRange => "number out of range",
+            Self::DuplicateKey => "duplicate key",
+            Self::TrailingBytes => "trailing bytes",
+            Self::InvalidMagic => "invalid magic bytes",
+            Self::Checksum => "checksum mismatch",
+            Self::Unsupported => "unsupported format",
+            Self::MissingBaseline => "missing baseline",
+            Self::Io(_) => "io error",
+        }
+    }
+
+    fn io_kind(self) -> io::ErrorKind {
+        match self {
+            Self::Eof => io::ErrorKind::UnexpectedEof,
+            Self::Io(kind) => kind,
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// An error from the serial module with the position it occurred at.
+///
+/// The traits return `io::Error`, which carries a `SerialError` inside. Use
+/// [`SerialError::from_io`] to get it back out, or the [`decode`](super::decode) functions which
+/// do that and also record the byte offset.
+#[derive(Debug)]
+pub struct SerialError {
+    kind: SerialErrorKind,
+    offset: Option<u64>,
+    path: Vec<Cow<'static, str>>,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl SerialError {
+    pub fn new(kind: SerialErrorKind) -> Self {
+        Self {
+            kind,
+            offset: None,
+            path: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Attaches the underlying error or a message with more detail.
+    pub fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Sets the byte offset in the stream, unless one is already known.
+    pub fn at(mut self, offset: u64) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Recovers the `SerialError` from an `io::Error` returned by the serial traits.
+    ///
+    /// Errors from the underlying reader become [`SerialErrorKind::Io`], or
+    /// [`SerialErrorKind::Eof`] for `UnexpectedEof`.
+    pub fn from_io(error: io::Error) -> Self {
+        if error.get_ref().is_some_and(|inner| inner.is::<Self>()) {
+            let inner = error.into_inner().unwrap();
+            return *inner.downcast::<Self>().unwrap();
+        }
+
+        let kind = match error.kind() {
+            io::ErrorKind::UnexpectedEof => SerialErrorKind::Eof,
+            kind => SerialErrorKind::Io(kind),
+        };
+        Self::new(kind).with_source(error)
+    }
+
+    /// Adds the field an error occurred in to its path.
+    ///
+    /// The derives call this for every field, so the path names each nested type and field
+    /// from the outermost one inwards.
+    pub fn in_field(error: io::Error, field: impl Into<Cow<'static, str>>) -> io::Error {
+        let mut error = Self::from_io(error);
+        error.path.push(field.into());
+        error.into()
+    }
+
+    pub fn kind(&self) -> SerialErrorKind {
+        self.kind
+    }
+
+    /// The number of bytes that were consumed when the error occurred.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// The nested fields the error occurred in, outermost first.
+    pub fn path(&self) -> impl Iterator<Item = &str> {
+        self.path.iter().rev().map(|field| field.as_ref())
+    }
+}
+
+impl fmt::Display for SerialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind.description())?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {offset}")?;
+        }
+        if !self.path.is_empty() {
+            f.write_str(" in ")?;
+            for (i, field) in self.path().enumerate() {
+                if i != 0 {
+                    f.write_str(" > ")?;
+                }
+                f.write_str(field)?;
+            }
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {source}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SerialError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+impl From<SerialErrorKind> for SerialError {
+    fn from(kind: SerialErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl From<SerialError> for io::Error {
+    fn from(error: SerialError) -> Self {
+        io::Error::new(error.kind.io_kind(), error)
+    }
+}
+
+impl From<SerialErrorKind> for io::Error {
+    fn from(kind: SerialErrorKind) -> Self {
+        SerialError::new(kind).into()
+    }
+}
+
+/// A reader that counts the bytes read through it, used to report error offsets.
+pub struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// The number of bytes read so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
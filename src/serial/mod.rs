@@ -1,20 +1,60 @@
-use std::io::{Cursor, Result, Write};
+use std::io::{Read, Result, Write};
 
+pub use bits::{BitReader, BitWriter, Packed, Quantizer, ReadBits, SBits, UBits, WriteBits};
+pub use container::Container;
+pub use crc32::Crc32;
+pub use decoder::{Decoded, FrameDecoder};
+pub use delta::{Delta, DeltaReceiver, DeltaSender};
+pub use endian::{Be, Le};
+pub use error::{CountingReader, SerialError, SerialErrorKind};
+pub use iron_oxide_derive::{Delta, PrefixedRead, PrefixedWrite, ReadFrom, WriteTo};
+pub use value::{Value, annotate};
+pub use varint::{VarI32, VarI64, VarU32, VarU64};
+
+/// A module for bit-level packing.
+mod bits;
+/// A module for the versioned container format.
+mod container;
+/// A module for the CRC-32 checksum.
+mod crc32;
+/// A module for decoding frames from partial input.
+mod decoder;
+/// A module for delta encoding against a baseline.
+mod delta;
+/// A module for byte order wrappers.
+mod endian;
+/// A module for the serial error type.
+mod error;
 /// A module for reading prefixed data.
 mod prefixed_read;
+/// A module for reading prefixed data borrowed from a slice.
+mod prefixed_read_borrowed;
 /// A module for writing prefixed data.
 mod prefixed_write;
 /// A module for reading data.
 mod read;
+/// A module for the self-describing encoding.
+mod value;
+/// A module for variable-length integers.
+mod varint;
 /// A module for writing data.
 mod write;
 
+#[cfg(test)]
+mod tests;
+
+/// The largest length prefixed reads and writes accept unless a bound is given, so that a
+/// corrupt prefix cannot make reads allocate huge buffers. Writes use the same bound, so that
+/// everything written can be read back.
 const DEFAULT_BOUND: usize = i16::MAX as _;
 
-/// A trait for reading data from a cursor.
+/// A trait for reading data from a reader.
+///
+/// Values are read with many small reads, so unbuffered sources like a `File` or
+/// `TcpStream` should be wrapped in a `BufReader`.
 pub trait ReadFrom: Sized {
-    /// Reads data from a cursor.
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self>;
+    /// Reads data from a reader.
+    fn read(data: &mut impl Read) -> Result<Self>;
 }
 
 /// A trait for writing data to a writer.
@@ -23,20 +63,36 @@ pub trait WriteTo {
     fn write(&self, writer: &mut impl Write) -> Result<()>;
 }
 
-/// A trait for reading prefixed data from a cursor.
+/// A trait for reading prefixed data from a reader.
 pub trait PrefixedRead: Sized {
-    /// Reads prefixed data from a cursor with a bound.
+    /// Reads prefixed data from a reader with a bound.
     fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
-        data: &mut Cursor<&[u8]>,
+        data: &mut impl Read,
         bound: usize,
     ) -> Result<Self>;
 
-    /// Reads prefixed data from a cursor.
-    fn read_prefixed<P: TryInto<usize> + ReadFrom>(data: &mut Cursor<&[u8]>) -> Result<Self> {
+    /// Reads prefixed data from a reader.
+    fn read_prefixed<P: TryInto<usize> + ReadFrom>(data: &mut impl Read) -> Result<Self> {
         Self::read_prefixed_bound::<P>(data, DEFAULT_BOUND)
     }
 }
 
+/// A trait for reading prefixed data that borrows from a slice instead of allocating.
+///
+/// The slice is advanced past the data that was read, the same way `Read for &[u8]` does.
+pub trait PrefixedReadBorrowed<'a>: Sized {
+    /// Reads prefixed data from a slice with a bound.
+    fn read_prefixed_bound_borrowed<P: TryInto<usize> + ReadFrom>(
+        data: &mut &'a [u8],
+        bound: usize,
+    ) -> Result<Self>;
+
+    /// Reads prefixed data from a slice.
+    fn read_prefixed_borrowed<P: TryInto<usize> + ReadFrom>(data: &mut &'a [u8]) -> Result<Self> {
+        Self::read_prefixed_bound_borrowed::<P>(data, DEFAULT_BOUND)
+    }
+}
+
 /// A trait for writing prefixed data to a writer.
 pub trait PrefixedWrite {
     /// Writes prefixed data to a writer with a bound.
@@ -51,3 +107,94 @@ pub trait PrefixedWrite {
         self.write_prefixed_bound::<P>(writer, DEFAULT_BOUND)
     }
 }
+
+/// Reads a value written by [`write_framed`].
+///
+/// The frame may be at most `bound` bytes long and must be consumed completely by the value.
+pub fn read_framed<P: TryInto<usize> + ReadFrom, T: ReadFrom>(
+    data: &mut impl Read,
+    bound: usize,
+) -> Result<T> {
+    let len = prefixed_read::read_len::<P>(data, bound)?;
+
+    let mut buf = vec![0; len];
+    data.read_exact(&mut buf)?;
+
+    let mut frame = buf.as_slice();
+    let value = T::read(&mut frame)?;
+    if !frame.is_empty() {
+        Err(SerialErrorKind::TrailingBytes)?;
+    }
+    Ok(value)
+}
+
+/// Writes a value prefixed with the length of its encoding in bytes.
+pub fn write_framed<P: TryFrom<usize> + WriteTo, T: WriteTo + ?Sized>(
+    value: &T,
+    writer: &mut impl Write,
+    bound: usize,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    value.write(&mut buf)?;
+
+    prefixed_write::write_len::<P>(buf.len(), writer, bound)?;
+    writer.write_all(&buf)
+}
+
+/// Reads `len` little-endian `f32`s with bulk reads.
+///
+/// The buffer grows with the data that arrives, so a corrupted length fails at the end of the
+/// data instead of allocating all of it up front.
+pub fn read_f32s(data: &mut impl Read, len: usize) -> Result<Vec<f32>> {
+    const CHUNK: usize = 1024;
+
+    let mut values = Vec::with_capacity(len.min(CHUNK));
+    let mut buf = [0; CHUNK * 4];
+    while values.len() < len {
+        let count = (len - values.len()).min(CHUNK);
+        let bytes = &mut buf[..count * 4];
+        data.read_exact(bytes)?;
+        values.extend(
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap())),
+        );
+    }
+    Ok(values)
+}
+
+/// Writes `f32`s in little-endian byte order with bulk writes.
+pub fn write_f32s(values: &[f32], writer: &mut impl Write) -> Result<()> {
+    let mut buf = [0; 4096];
+    for chunk in values.chunks(buf.len() / 4) {
+        for (bytes, value) in buf.chunks_exact_mut(4).zip(chunk) {
+            bytes.copy_from_slice(&value.to_le_bytes());
+        }
+        writer.write_all(&buf[..chunk.len() * 4])?;
+    }
+    Ok(())
+}
+
+/// Writes a value into a new buffer.
+pub fn encode<T: WriteTo + ?Sized>(value: &T) -> std::result::Result<Vec<u8>, SerialError> {
+    let mut buf = Vec::new();
+    value.write(&mut buf).map_err(SerialError::from_io)?;
+    Ok(buf)
+}
+
+/// Reads a value from a reader, reporting errors with the byte offset they occurred at.
+pub fn decode<T: ReadFrom>(reader: impl Read) -> std::result::Result<T, SerialError> {
+    let mut reader = CountingReader::new(reader);
+    T::read(&mut reader).map_err(|e| SerialError::from_io(e).at(reader.count()))
+}
+
+/// Reads a value that has to span all of `data`.
+pub fn decode_bytes<T: ReadFrom>(data: &[u8]) -> std::result::Result<T, SerialError> {
+    let mut reader = CountingReader::new(data);
+    let value = T::read(&mut reader).map_err(|e| SerialError::from_io(e).at(reader.count()))?;
+
+    if reader.count() != data.len() as u64 {
+        return Err(SerialError::new(SerialErrorKind::TrailingBytes).at(reader.count()));
+    }
+    Ok(value)
+}
@@ -1,39 +1,56 @@
-#![allow(missing_docs)]
-use std::io::{Cursor, Error, Read, Result};
+#![allow(missing_docs, clippy::disallowed_types)]
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+    io::{Error, Read, Result},
+};
 
-use crate::serial::{PrefixedRead, ReadFrom};
+use crate::serial::{PrefixedRead, ReadFrom, SerialError, SerialErrorKind};
+
+/// Reads a length prefix and checks it against the bound.
+pub(crate) fn read_len<P: TryInto<usize> + ReadFrom>(
+    data: &mut impl Read,
+    bound: usize,
+) -> Result<usize> {
+    let len: usize = P::read(data)?
+        .try_into()
+        .map_err(|_| SerialErrorKind::InvalidPrefix)?;
+
+    if len > bound {
+        Err(SerialError::new(SerialErrorKind::BoundExceeded)
+            .with_source(format!("length {len} is larger than {bound}")))?;
+    }
+    Ok(len)
+}
+
+fn duplicate_key() -> Error {
+    SerialErrorKind::DuplicateKey.into()
+}
 
 impl PrefixedRead for String {
     fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
-        data: &mut Cursor<&[u8]>,
+        data: &mut impl Read,
         bound: usize,
     ) -> Result<Self> {
-        let len: usize = P::read(data)?
-            .try_into()
-            .map_err(|_| Error::other("Invalid Prefix"))?;
-
-        if len > bound {
-            Err(Error::other("To long"))?;
-        }
+        let len = read_len::<P>(data, bound)?;
 
         let mut buf = vec![0; len];
         data.read_exact(&mut buf)?;
-        Ok(unsafe { String::from_utf8_unchecked(buf) })
+        String::from_utf8(buf).map_err(|e| {
+            SerialError::new(SerialErrorKind::InvalidUtf8)
+                .with_source(e)
+                .into()
+        })
     }
 }
 
 impl<T: ReadFrom> PrefixedRead for Vec<T> {
     fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
-        data: &mut Cursor<&[u8]>,
+        data: &mut impl Read,
         bound: usize,
     ) -> Result<Self> {
-        let len: usize = P::read(data)?
-            .try_into()
-            .map_err(|_| Error::other("Invalid Prefix"))?;
+        let len = read_len::<P>(data, bound)?;
 
-        if len > bound {
-            Err(Error::other("To long"))?;
-        }
         let mut items = Vec::with_capacity(len);
         for _ in 0..len {
             items.push(T::read(data)?);
@@ -42,9 +59,102 @@ impl<T: ReadFrom> PrefixedRead for Vec<T> {
     }
 }
 
+impl<T: ReadFrom, const N: usize> PrefixedRead for [T; N] {
+    fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
+        data: &mut impl Read,
+        bound: usize,
+    ) -> Result<Self> {
+        if read_len::<P>(data, bound)? != N {
+            Err(SerialErrorKind::InvalidLength)?;
+        }
+        Self::read(data)
+    }
+}
+
+impl<T: ReadFrom> PrefixedRead for VecDeque<T> {
+    fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
+        data: &mut impl Read,
+        bound: usize,
+    ) -> Result<Self> {
+        Vec::read_prefixed_bound::<P>(data, bound).map(Self::from)
+    }
+}
+
+impl<T: ReadFrom + Eq + Hash, S: BuildHasher + Default> PrefixedRead for HashSet<T, S> {
+    fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
+        data: &mut impl Read,
+        bound: usize,
+    ) -> Result<Self> {
+        let len = read_len::<P>(data, bound)?;
+
+        let mut set = HashSet::with_capacity_and_hasher(len, S::default());
+        for _ in 0..len {
+            if !set.insert(T::read(data)?) {
+                return Err(duplicate_key());
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl<T: ReadFrom + Ord> PrefixedRead for BTreeSet<T> {
+    fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
+        data: &mut impl Read,
+        bound: usize,
+    ) -> Result<Self> {
+        let len = read_len::<P>(data, bound)?;
+
+        let mut set = BTreeSet::new();
+        for _ in 0..len {
+            if !set.insert(T::read(data)?) {
+                return Err(duplicate_key());
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl<K: ReadFrom + Eq + Hash, V: ReadFrom, S: BuildHasher + Default> PrefixedRead
+    for HashMap<K, V, S>
+{
+    fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
+        data: &mut impl Read,
+        bound: usize,
+    ) -> Result<Self> {
+        let len = read_len::<P>(data, bound)?;
+
+        let mut map = HashMap::with_capacity_and_hasher(len, S::default());
+        for _ in 0..len {
+            let key = K::read(data)?;
+            if map.insert(key, V::read(data)?).is_some() {
+                return Err(duplicate_key());
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<K: ReadFrom + Ord, V: ReadFrom> PrefixedRead for BTreeMap<K, V> {
+    fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
+        data: &mut impl Read,
+        bound: usize,
+    ) -> Result<Self> {
+        let len = read_len::<P>(data, bound)?;
+
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let key = K::read(data)?;
+            if map.insert(key, V::read(data)?).is_some() {
+                return Err(duplicate_key());
+            }
+        }
+        Ok(map)
+    }
+}
+
 impl<T: PrefixedRead> PrefixedRead for Option<T> {
     fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
-        data: &mut Cursor<&[u8]>,
+        data: &mut impl Read,
         bound: usize,
     ) -> Result<Self> {
         if bool::read(data)? {
@@ -54,3 +164,12 @@ impl<T: PrefixedRead> PrefixedRead for Option<T> {
         }
     }
 }
+
+impl<T: PrefixedRead> PrefixedRead for Box<T> {
+    fn read_prefixed_bound<P: TryInto<usize> + ReadFrom>(
+        data: &mut impl Read,
+        bound: usize,
+    ) -> Result<Self> {
+        T::read_prefixed_bound::<P>(data, bound).map(Box::new)
+    }
+}
@@ -0,0 +1,52 @@
+#![allow(missing_docs)]
+use std::io::Result;
+
+use crate::serial::{
+    PrefixedReadBorrowed, ReadFrom, SerialError, SerialErrorKind, prefixed_read::read_len,
+};
+
+fn take<'a, P: TryInto<usize> + ReadFrom>(data: &mut &'a [u8], bound: usize) -> Result<&'a [u8]> {
+    let len = read_len::<P>(data, bound)?;
+
+    let Some((bytes, rest)) = data.split_at_checked(len) else {
+        return Err(SerialErrorKind::Eof.into());
+    };
+    *data = rest;
+    Ok(bytes)
+}
+
+impl<'a> PrefixedReadBorrowed<'a> for &'a [u8] {
+    fn read_prefixed_bound_borrowed<P: TryInto<usize> + ReadFrom>(
+        data: &mut &'a [u8],
+        bound: usize,
+    ) -> Result<Self> {
+        take::<P>(data, bound)
+    }
+}
+
+impl<'a> PrefixedReadBorrowed<'a> for &'a str {
+    fn read_prefixed_bound_borrowed<P: TryInto<usize> + ReadFrom>(
+        data: &mut &'a [u8],
+        bound: usize,
+    ) -> Result<Self> {
+        let bytes = take::<P>(data, bound)?;
+        str::from_utf8(bytes).map_err(|e| {
+            SerialError::new(SerialErrorKind::InvalidUtf8)
+                .with_source(e)
+                .into()
+        })
+    }
+}
+
+impl<'a, T: PrefixedReadBorrowed<'a>> PrefixedReadBorrowed<'a> for Option<T> {
+    fn read_prefixed_bound_borrowed<P: TryInto<usize> + ReadFrom>(
+        data: &mut &'a [u8],
+        bound: usize,
+    ) -> Result<Self> {
+        if bool::read(data)? {
+            Ok(Some(T::read_prefixed_bound_borrowed::<P>(data, bound)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -1,7 +1,26 @@
-#![allow(missing_docs)]
-use std::io::{Error, Result, Write};
+#![allow(missing_docs, clippy::disallowed_types)]
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::BuildHasher,
+    io::{Result, Write},
+};
+
+use crate::serial::{PrefixedWrite, SerialError, SerialErrorKind, WriteTo};
+
+/// Checks the length against the bound and writes it as a prefix.
+pub(crate) fn write_len<P: TryFrom<usize> + WriteTo>(
+    len: usize,
+    writer: &mut impl Write,
+    bound: usize,
+) -> Result<()> {
+    if len > bound {
+        Err(SerialError::new(SerialErrorKind::BoundExceeded)
+            .with_source(format!("length {len} is larger than {bound}")))?;
+    }
 
-use crate::serial::{PrefixedWrite, WriteTo};
+    let len: P = len.try_into().map_err(|_| SerialErrorKind::InvalidPrefix)?;
+    len.write(writer)
+}
 
 impl PrefixedWrite for String {
     fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
@@ -9,17 +28,7 @@ impl PrefixedWrite for String {
         writer: &mut impl Write,
         bound: usize,
     ) -> Result<()> {
-        if self.len() > bound {
-            Err(Error::other("Too long"))?;
-        }
-
-        let len: P = self
-            .len()
-            .try_into()
-            .map_err(|_| Error::other("This cant happen"))?;
-        len.write(writer)?;
-
-        writer.write_all(self.as_bytes())
+        self.as_str().write_prefixed_bound::<P>(writer, bound)
     }
 }
 
@@ -29,16 +38,7 @@ impl PrefixedWrite for str {
         writer: &mut impl Write,
         bound: usize,
     ) -> Result<()> {
-        if self.len() > bound {
-            Err(Error::other("Too long"))?;
-        }
-
-        let len: P = self
-            .len()
-            .try_into()
-            .map_err(|_| Error::other("This cant happen"))?;
-        len.write(writer)?;
-
+        write_len::<P>(self.len(), writer, bound)?;
         writer.write_all(self.as_bytes())
     }
 }
@@ -49,16 +49,17 @@ impl<T: WriteTo> PrefixedWrite for Vec<T> {
         writer: &mut impl Write,
         bound: usize,
     ) -> Result<()> {
-        if self.len() > bound {
-            Err(Error::other("Too long"))?;
-        }
-
-        let len: P = self
-            .len()
-            .try_into()
-            .map_err(|_| Error::other("This cant happen"))?;
+        self.as_slice().write_prefixed_bound::<P>(writer, bound)
+    }
+}
 
-        len.write(writer)?;
+impl<T: WriteTo> PrefixedWrite for [T] {
+    fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
+        &self,
+        writer: &mut impl Write,
+        bound: usize,
+    ) -> Result<()> {
+        write_len::<P>(self.len(), writer, bound)?;
 
         for property in self {
             property.write(writer)?;
@@ -68,47 +69,88 @@ impl<T: WriteTo> PrefixedWrite for Vec<T> {
     }
 }
 
-impl<T: WriteTo> PrefixedWrite for [T] {
+impl<T: WriteTo, const N: usize> PrefixedWrite for [T; N] {
     fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
         &self,
         writer: &mut impl Write,
         bound: usize,
     ) -> Result<()> {
-        if self.len() > bound {
-            Err(Error::other("Too long"))?;
-        }
+        self.as_slice().write_prefixed_bound::<P>(writer, bound)
+    }
+}
 
-        let len: P = self
-            .len()
-            .try_into()
-            .map_err(|_| Error::other("This cant happen"))?;
+impl<T: WriteTo> PrefixedWrite for VecDeque<T> {
+    fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
+        &self,
+        writer: &mut impl Write,
+        bound: usize,
+    ) -> Result<()> {
+        write_len::<P>(self.len(), writer, bound)?;
 
-        len.write(writer)?;
+        for item in self {
+            item.write(writer)?;
+        }
+        Ok(())
+    }
+}
 
-        for property in self {
-            property.write(writer)?;
+impl<T: WriteTo, S: BuildHasher> PrefixedWrite for HashSet<T, S> {
+    fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
+        &self,
+        writer: &mut impl Write,
+        bound: usize,
+    ) -> Result<()> {
+        write_len::<P>(self.len(), writer, bound)?;
+
+        for item in self {
+            item.write(writer)?;
         }
+        Ok(())
+    }
+}
 
+impl<T: WriteTo> PrefixedWrite for BTreeSet<T> {
+    fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
+        &self,
+        writer: &mut impl Write,
+        bound: usize,
+    ) -> Result<()> {
+        write_len::<P>(self.len(), writer, bound)?;
+
+        for item in self {
+            item.write(writer)?;
+        }
         Ok(())
     }
 }
 
-impl<T: WriteTo, const N: usize> PrefixedWrite for [T; N] {
+impl<K: WriteTo, V: WriteTo, S: BuildHasher> PrefixedWrite for HashMap<K, V, S> {
     fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
         &self,
         writer: &mut impl Write,
         bound: usize,
     ) -> Result<()> {
-        if N > bound {
-            Err(Error::other("Too long"))?;
+        write_len::<P>(self.len(), writer, bound)?;
+
+        for (key, value) in self {
+            key.write(writer)?;
+            value.write(writer)?;
         }
+        Ok(())
+    }
+}
 
-        P::try_from(N)
-            .map_err(|_| Error::other("This cant happen"))?
-            .write(writer)?;
+impl<K: WriteTo, V: WriteTo> PrefixedWrite for BTreeMap<K, V> {
+    fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
+        &self,
+        writer: &mut impl Write,
+        bound: usize,
+    ) -> Result<()> {
+        write_len::<P>(self.len(), writer, bound)?;
 
-        for i in self {
-            i.write(writer)?;
+        for (key, value) in self {
+            key.write(writer)?;
+            value.write(writer)?;
         }
         Ok(())
     }
@@ -128,3 +170,23 @@ impl<T: PrefixedWrite> PrefixedWrite for Option<T> {
         }
     }
 }
+
+impl<T: PrefixedWrite + ?Sized> PrefixedWrite for Box<T> {
+    fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
+        &self,
+        writer: &mut impl Write,
+        bound: usize,
+    ) -> Result<()> {
+        (**self).write_prefixed_bound::<P>(writer, bound)
+    }
+}
+
+impl<T: PrefixedWrite + ?Sized> PrefixedWrite for &T {
+    fn write_prefixed_bound<P: TryFrom<usize> + WriteTo>(
+        &self,
+        writer: &mut impl Write,
+        bound: usize,
+    ) -> Result<()> {
+        (**self).write_prefixed_bound::<P>(writer, bound)
+    }
+}
@@ -1,116 +1,208 @@
+#![allow(clippy::disallowed_types)]
 use std::{
-    io::{Cursor, Read, Result},
-    mem::{self, MaybeUninit},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+    io::{Read, Result},
+    mem::MaybeUninit,
+    ptr,
 };
 
-use crate::serial::ReadFrom;
+use crate::serial::{Be, Le, PrefixedRead, ReadFrom, SerialError, SerialErrorKind};
 
 impl ReadFrom for bool {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let byte = u8::read(data)?;
-        Ok(byte == 1)
+    fn read(data: &mut impl Read) -> Result<Self> {
+        match u8::read(data)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SerialErrorKind::InvalidBool.into()),
+        }
     }
 }
 
-impl ReadFrom for u8 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
-    }
+macro_rules! impl_read {
+    ($($ty:ty),*) => {$(
+        impl ReadFrom for $ty {
+            fn read(data: &mut impl Read) -> Result<Self> {
+                Le::<$ty>::read(data).map(|value| value.0)
+            }
+        }
+
+        impl ReadFrom for Le<$ty> {
+            fn read(data: &mut impl Read) -> Result<Self> {
+                let mut buf = [0; size_of::<$ty>()];
+                data.read_exact(&mut buf)?;
+                Ok(Self(<$ty>::from_le_bytes(buf)))
+            }
+        }
+
+        impl ReadFrom for Be<$ty> {
+            fn read(data: &mut impl Read) -> Result<Self> {
+                let mut buf = [0; size_of::<$ty>()];
+                data.read_exact(&mut buf)?;
+                Ok(Self(<$ty>::from_be_bytes(buf)))
+            }
+        }
+    )*};
 }
 
-impl ReadFrom for u16 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+impl_read!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// `usize` is always encoded as a `u64`, so the encoding does not depend on the platform.
+impl ReadFrom for usize {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        u64::read(data)?.try_into().map_err(|e| {
+            SerialError::new(SerialErrorKind::OutOfRange)
+                .with_source(e)
+                .into()
+        })
     }
 }
 
-impl ReadFrom for u32 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+/// `isize` is always encoded as an `i64`, so the encoding does not depend on the platform.
+impl ReadFrom for isize {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        i64::read(data)?.try_into().map_err(|e| {
+            SerialError::new(SerialErrorKind::OutOfRange)
+                .with_source(e)
+                .into()
+        })
     }
 }
 
-impl ReadFrom for u64 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+impl ReadFrom for char {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        char::from_u32(u32::read(data)?).ok_or_else(|| SerialErrorKind::InvalidChar.into())
     }
 }
 
-impl ReadFrom for i8 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+impl ReadFrom for () {
+    fn read(_: &mut impl Read) -> Result<Self> {
+        Ok(())
     }
 }
 
-impl ReadFrom for i16 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+impl<T: ReadFrom> ReadFrom for Option<T> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        if bool::read(data)? {
+            Ok(Some(T::read(data)?))
+        } else {
+            Ok(None)
+        }
     }
 }
 
-impl ReadFrom for i32 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+impl<T: ReadFrom, E: ReadFrom> ReadFrom for std::result::Result<T, E> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        match u8::read(data)? {
+            0 => Ok(Ok(T::read(data)?)),
+            1 => Ok(Err(E::read(data)?)),
+            _ => Err(SerialErrorKind::InvalidTag.into()),
+        }
     }
 }
 
-impl ReadFrom for i64 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+impl<T: ReadFrom> ReadFrom for Box<T> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        T::read(data).map(Box::new)
     }
 }
 
-impl ReadFrom for f32 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+/// The elements of an array that is being read.
+///
+/// Only the first `len` elements are initialized, and only those are dropped when reading
+/// fails halfway through.
+struct PartialArray<T, const N: usize> {
+    items: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> Drop for PartialArray<T, N> {
+    fn drop(&mut self) {
+        for item in &mut self.items[..self.len] {
+            unsafe { item.assume_init_drop() };
+        }
     }
 }
 
-impl ReadFrom for f64 {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        let mut buf = [0; size_of::<Self>()];
-        data.read_exact(&mut buf)?;
-        Ok(Self::from_le_bytes(buf))
+impl<T: ReadFrom, const N: usize> ReadFrom for [T; N] {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        let mut array = PartialArray {
+            items: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        };
+
+        while array.len < N {
+            array.items[array.len].write(T::read(data)?);
+            array.len += 1;
+        }
+
+        // All elements are initialized, ownership moves out of the guard.
+        array.len = 0;
+        Ok(unsafe { ptr::read(array.items.as_ptr().cast::<[T; N]>()) })
     }
 }
 
-impl<T: ReadFrom> ReadFrom for Option<T> {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        if bool::read(data)? {
-            Ok(Some(T::read(data)?))
-        } else {
-            Ok(None)
+macro_rules! impl_read_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: ReadFrom),+> ReadFrom for ($($name,)+) {
+            fn read(data: &mut impl Read) -> Result<Self> {
+                Ok(($($name::read(data)?,)+))
+            }
         }
+    };
+}
+
+impl_read_tuple!(A);
+impl_read_tuple!(A B);
+impl_read_tuple!(A B C);
+impl_read_tuple!(A B C D);
+impl_read_tuple!(A B C D E);
+impl_read_tuple!(A B C D E F);
+impl_read_tuple!(A B C D E F G);
+impl_read_tuple!(A B C D E F G H);
+impl_read_tuple!(A B C D E F G H I);
+impl_read_tuple!(A B C D E F G H I J);
+impl_read_tuple!(A B C D E F G H I J K);
+impl_read_tuple!(A B C D E F G H I J K L);
+
+impl ReadFrom for String {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_prefixed::<u32>(data)
     }
 }
 
-impl<T: ReadFrom, const N: usize> ReadFrom for [T; N] {
-    fn read(data: &mut Cursor<&[u8]>) -> Result<Self> {
-        #[allow(clippy::uninit_assumed_init)]
-        let mut buf: [T; N] = unsafe { MaybeUninit::uninit().assume_init() };
+impl<T: ReadFrom> ReadFrom for Vec<T> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_prefixed::<u32>(data)
+    }
+}
 
-        for i in &mut buf {
-            mem::forget(mem::replace(i, T::read(data)?));
-        }
+impl<T: ReadFrom> ReadFrom for VecDeque<T> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_prefixed::<u32>(data)
+    }
+}
+
+impl<T: ReadFrom + Eq + Hash, S: BuildHasher + Default> ReadFrom for HashSet<T, S> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_prefixed::<u32>(data)
+    }
+}
+
+impl<T: ReadFrom + Ord> ReadFrom for BTreeSet<T> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_prefixed::<u32>(data)
+    }
+}
+
+impl<K: ReadFrom + Eq + Hash, V: ReadFrom, S: BuildHasher + Default> ReadFrom for HashMap<K, V, S> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_prefixed::<u32>(data)
+    }
+}
 
-        Ok(buf)
+impl<K: ReadFrom + Ord, V: ReadFrom> ReadFrom for BTreeMap<K, V> {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_prefixed::<u32>(data)
     }
 }
@@ -0,0 +1,724 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    io::{BufReader, Cursor, Read},
+};
+
+use crate::serial::{
+    PrefixedRead, PrefixedReadBorrowed, PrefixedWrite, ReadFrom, SerialError, SerialErrorKind,
+    WriteTo,
+};
+
+fn round_trip<T: ReadFrom + WriteTo + PartialEq + Debug>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.write(&mut buf).unwrap();
+
+    let mut cursor = Cursor::new(buf.as_slice());
+    assert_eq!(&T::read(&mut cursor).unwrap(), value);
+    assert_eq!(cursor.position() as usize, buf.len());
+    buf
+}
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct Login {
+    id: u32,
+    #[serial(prefix = u8, bound = 16)]
+    name: String,
+    #[serial(prefix = u16)]
+    scores: Vec<i16>,
+    #[serial(skip)]
+    cached: Option<u64>,
+    admin: bool,
+}
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct Position(f32, f32);
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct Wrapper<T> {
+    inner: T,
+    tail: [u8; 2],
+}
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct Marker;
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+#[repr(u8)]
+enum Packet {
+    Ping,
+    Move(Position),
+    Chat {
+        #[serial(prefix = u16)]
+        text: String,
+        to: Option<u32>,
+    },
+    Kick = 10,
+    Ban(#[serial(skip)] u8, u64),
+}
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+#[serial(tag = u16)]
+enum Wide {
+    A = 300,
+    B,
+}
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo, PrefixedRead, PrefixedWrite)]
+struct Framed {
+    a: u8,
+    b: u32,
+}
+
+/// The header of a PNG chunk, which stores its numbers in network byte order.
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct ChunkHeader {
+    #[serial(big_endian)]
+    length: u32,
+    kind: [u8; 4],
+}
+
+#[test]
+fn derive_struct_round_trip() {
+    let login = Login {
+        id: 7,
+        name: "ferris".to_string(),
+        scores: vec![-1, 2, 300],
+        cached: None,
+        admin: true,
+    };
+    let buf = round_trip(&login);
+    assert_eq!(buf.len(), 4 + 1 + 6 + 2 + 6 + 1);
+
+    round_trip(&Position(1.5, -2.0));
+    round_trip(&Wrapper {
+        inner: Position(0.0, 3.0),
+        tail: [1, 2],
+    });
+    assert!(round_trip(&Marker).is_empty());
+}
+
+#[test]
+fn derive_skip_fills_default() {
+    let login = Login {
+        id: 1,
+        name: String::new(),
+        scores: Vec::new(),
+        cached: Some(5),
+        admin: false,
+    };
+    let mut buf = Vec::new();
+    login.write(&mut buf).unwrap();
+
+    let read = Login::read(&mut Cursor::new(buf.as_slice())).unwrap();
+    assert_eq!(read.cached, None);
+}
+
+#[test]
+fn derive_prefix_bound() {
+    let login = Login {
+        id: 1,
+        name: "a name that is far too long".to_string(),
+        scores: Vec::new(),
+        cached: None,
+        admin: false,
+    };
+    assert!(login.write(&mut Vec::new()).is_err());
+
+    let mut buf = Vec::new();
+    1u32.write(&mut buf).unwrap();
+    "a name that is far too long"
+        .write_prefixed::<u8>(&mut buf)
+        .unwrap();
+    assert!(Login::read(&mut Cursor::new(buf.as_slice())).is_err());
+}
+
+/// Has inherent methods named like the trait methods, which derived code must not call.
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct Shadowing(u8);
+
+impl Shadowing {
+    #[allow(dead_code)]
+    fn write(&self, _: &mut Vec<u8>) -> std::io::Result<()> {
+        panic!("inherent write called")
+    }
+}
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct HoldsShadowing {
+    plain: Shadowing,
+    #[serial(prefix = u8)]
+    names: Vec<String>,
+}
+
+#[test]
+fn derive_calls_trait_methods() {
+    let value = HoldsShadowing {
+        plain: Shadowing(7),
+        names: vec!["a".into()],
+    };
+    assert_eq!(round_trip(&value), [7, 1, 1, 0, 0, 0, b'a']);
+}
+
+#[test]
+fn derive_big_endian() {
+    let header = ChunkHeader {
+        length: 13,
+        kind: *b"IHDR",
+    };
+    assert_eq!(round_trip(&header), [0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+}
+
+#[test]
+fn derive_enum_round_trip() {
+    for packet in [
+        Packet::Ping,
+        Packet::Move(Position(4.0, 5.0)),
+        Packet::Chat {
+            text: "hello".to_string(),
+            to: Some(3),
+        },
+        Packet::Chat {
+            text: String::new(),
+            to: None,
+        },
+        Packet::Kick,
+        Packet::Ban(0, u64::MAX),
+    ] {
+        round_trip(&packet);
+    }
+
+    assert_eq!(round_trip(&Packet::Kick), [10]);
+    assert_eq!(round_trip(&Packet::Ban(0, 0))[0], 11);
+    assert_eq!(round_trip(&Wide::B), 301u16.to_le_bytes());
+}
+
+#[test]
+fn derive_enum_invalid_tag() {
+    assert!(Packet::read(&mut Cursor::new([5u8].as_slice())).is_err());
+    assert!(Wide::read(&mut Cursor::new([0u8, 0].as_slice())).is_err());
+}
+
+#[test]
+fn derive_framed_round_trip() {
+    let value = Framed { a: 1, b: 2 };
+    let mut buf = Vec::new();
+    value.write_prefixed::<u16>(&mut buf).unwrap();
+    assert_eq!(buf, [5, 0, 1, 2, 0, 0, 0]);
+
+    let mut cursor = Cursor::new(buf.as_slice());
+    assert_eq!(Framed::read_prefixed::<u16>(&mut cursor).unwrap(), value);
+
+    assert!(
+        value
+            .write_prefixed_bound::<u16>(&mut Vec::new(), 4)
+            .is_err()
+    );
+    buf[0] = 6;
+    buf.push(0);
+    assert!(Framed::read_prefixed::<u16>(&mut Cursor::new(buf.as_slice())).is_err());
+}
+
+/// Hands out one byte per `read` call, like a slow socket.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(1);
+        self.0.read(&mut buf[..len])
+    }
+}
+
+#[test]
+fn read_from_any_reader() {
+    let packet = Packet::Chat {
+        text: "streamed".to_string(),
+        to: Some(9),
+    };
+    let mut buf = Vec::new();
+    packet.write(&mut buf).unwrap();
+
+    let mut reader = BufReader::new(Trickle(&buf));
+    assert_eq!(Packet::read(&mut reader).unwrap(), packet);
+
+    let mut slice = buf.as_slice();
+    assert_eq!(Packet::read(&mut slice).unwrap(), packet);
+    assert!(slice.is_empty());
+
+    assert!(Packet::read(&mut Trickle(&buf[..buf.len() - 1])).is_err());
+}
+
+#[test]
+fn read_borrowed() {
+    let mut buf = Vec::new();
+    "zero copy".write_prefixed::<u8>(&mut buf).unwrap();
+    [1u8, 2, 3].write_prefixed::<u16>(&mut buf).unwrap();
+    Some("x").write_prefixed::<u8>(&mut buf).unwrap();
+    7u32.write(&mut buf).unwrap();
+
+    let mut data = buf.as_slice();
+    let text = <&str>::read_prefixed_borrowed::<u8>(&mut data).unwrap();
+    let bytes = <&[u8]>::read_prefixed_borrowed::<u16>(&mut data).unwrap();
+    let option = Option::<&str>::read_prefixed_borrowed::<u8>(&mut data).unwrap();
+    let tail = u32::read(&mut data).unwrap();
+
+    assert_eq!(text, "zero copy");
+    assert_eq!(text.as_ptr(), buf[1..].as_ptr());
+    assert_eq!(bytes, [1, 2, 3]);
+    assert_eq!(option, Some("x"));
+    assert_eq!(tail, 7);
+    assert!(data.is_empty());
+
+    let mut invalid: &[u8] = &[2, 0xff, 0xfe];
+    assert!(<&str>::read_prefixed_borrowed::<u8>(&mut invalid).is_err());
+    let mut short: &[u8] = &[4, b'a'];
+    assert!(<&[u8]>::read_prefixed_borrowed::<u8>(&mut short).is_err());
+    assert!(<&[u8]>::read_prefixed_bound_borrowed::<u8>(&mut &buf[..], 4).is_err());
+}
+
+/// Produces random values for the round-trip properties below.
+trait Arbitrary {
+    fn arbitrary() -> Self;
+}
+
+macro_rules! arbitrary_number {
+    ($($ty:ty),*) => {$(
+        impl Arbitrary for $ty {
+            fn arbitrary() -> Self {
+                rand::random()
+            }
+        }
+    )*};
+}
+
+arbitrary_number!(bool, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Arbitrary for usize {
+    fn arbitrary() -> Self {
+        rand::random::<u64>() as usize
+    }
+}
+
+impl Arbitrary for isize {
+    fn arbitrary() -> Self {
+        rand::random::<i64>() as isize
+    }
+}
+
+impl Arbitrary for f32 {
+    fn arbitrary() -> Self {
+        let value = f32::from_bits(rand::random());
+        if value.is_nan() { 0.0 } else { value }
+    }
+}
+
+impl Arbitrary for f64 {
+    fn arbitrary() -> Self {
+        let value = f64::from_bits(rand::random());
+        if value.is_nan() { 0.0 } else { value }
+    }
+}
+
+impl Arbitrary for char {
+    fn arbitrary() -> Self {
+        char::from_u32(rand::random_range(0..0x11_0000)).unwrap_or('\u{fffd}')
+    }
+}
+
+impl Arbitrary for () {
+    fn arbitrary() -> Self {}
+}
+
+fn arbitrary_len() -> usize {
+    rand::random_range(0..12)
+}
+
+impl Arbitrary for String {
+    fn arbitrary() -> Self {
+        (0..arbitrary_len()).map(|_| char::arbitrary()).collect()
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    fn arbitrary() -> Self {
+        rand::random::<bool>().then(T::arbitrary)
+    }
+}
+
+impl<T: Arbitrary, E: Arbitrary> Arbitrary for Result<T, E> {
+    fn arbitrary() -> Self {
+        if rand::random() {
+            Ok(T::arbitrary())
+        } else {
+            Err(E::arbitrary())
+        }
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Box<T> {
+    fn arbitrary() -> Self {
+        Box::new(T::arbitrary())
+    }
+}
+
+impl<T: Arbitrary, const N: usize> Arbitrary for [T; N] {
+    fn arbitrary() -> Self {
+        std::array::from_fn(|_| T::arbitrary())
+    }
+}
+
+macro_rules! arbitrary_collection {
+    ($($collection:ident<$($param:ident),+>),*) => {$(
+        impl<$($param: Arbitrary + Ord + std::hash::Hash),+> Arbitrary for $collection<$($param),+> {
+            fn arbitrary() -> Self {
+                (0..arbitrary_len()).map(|_| Arbitrary::arbitrary()).collect()
+            }
+        }
+    )*};
+}
+
+arbitrary_collection!(
+    Vec<T>,
+    VecDeque<T>,
+    HashSet<T>,
+    BTreeSet<T>,
+    HashMap<K, V>,
+    BTreeMap<K, V>
+);
+
+macro_rules! arbitrary_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: Arbitrary),+> Arbitrary for ($($name,)+) {
+            fn arbitrary() -> Self {
+                ($($name::arbitrary(),)+)
+            }
+        }
+    };
+}
+
+arbitrary_tuple!(A);
+arbitrary_tuple!(A B);
+arbitrary_tuple!(A B C);
+arbitrary_tuple!(A B C D E F G H I J K L);
+
+/// Round-trips random values of `T` and checks that every truncated encoding is rejected.
+fn check<T: Arbitrary + ReadFrom + WriteTo + PartialEq + Debug>() {
+    for _ in 0..64 {
+        let value = T::arbitrary();
+        let buf = round_trip(&value);
+
+        for len in 0..buf.len() {
+            assert!(T::read(&mut &buf[..len]).is_err(), "{value:?} cut at {len}");
+        }
+    }
+}
+
+#[test]
+fn round_trip_numbers() {
+    check::<bool>();
+    check::<u8>();
+    check::<u16>();
+    check::<u32>();
+    check::<u64>();
+    check::<u128>();
+    check::<i8>();
+    check::<i16>();
+    check::<i32>();
+    check::<i64>();
+    check::<i128>();
+    check::<usize>();
+    check::<isize>();
+    check::<f32>();
+    check::<f64>();
+    check::<char>();
+    check::<()>();
+}
+
+#[test]
+fn round_trip_std() {
+    check::<String>();
+    check::<Option<u16>>();
+    check::<Result<u8, String>>();
+    check::<Box<i64>>();
+    check::<[char; 3]>();
+    check::<[String; 2]>();
+    check::<Vec<u32>>();
+    check::<Vec<Vec<u8>>>();
+    check::<VecDeque<i16>>();
+    check::<HashSet<String>>();
+    check::<BTreeSet<u8>>();
+    check::<HashMap<u32, String>>();
+    check::<BTreeMap<String, Vec<bool>>>();
+    check::<(u8,)>();
+    check::<(u8, String)>();
+    check::<(i32, Option<char>, Vec<u8>)>();
+    check::<(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, bool, char)>();
+}
+
+#[test]
+fn round_trip_prefixed_collections() {
+    let map: BTreeMap<u8, String> = Arbitrary::arbitrary();
+    let mut buf = Vec::new();
+    map.write_prefixed::<u8>(&mut buf).unwrap();
+    assert_eq!(
+        BTreeMap::<u8, String>::read_prefixed::<u8>(&mut buf.as_slice()).unwrap(),
+        map
+    );
+
+    let array: [u16; 4] = Arbitrary::arbitrary();
+    let mut buf = Vec::new();
+    array.write_prefixed::<u8>(&mut buf).unwrap();
+    assert_eq!(
+        <[u16; 4]>::read_prefixed::<u8>(&mut buf.as_slice()).unwrap(),
+        array
+    );
+    assert!(<[u16; 3]>::read_prefixed::<u8>(&mut buf.as_slice()).is_err());
+}
+
+#[test]
+fn invalid_std_encodings() {
+    assert!(char::read(&mut 0xd800u32.to_le_bytes().as_slice()).is_err());
+    assert!(Result::<u8, u8>::read(&mut [2, 0].as_slice()).is_err());
+    assert!(String::read(&mut [2, 0, 0, 0, 0xc3, 0x28].as_slice()).is_err());
+    // The same key twice.
+    assert!(HashMap::<u8, u8>::read(&mut [2, 0, 0, 0, 1, 1, 1, 2].as_slice()).is_err());
+    assert!(BTreeSet::<u8>::read(&mut [2, 0, 0, 0, 1, 1].as_slice()).is_err());
+}
+
+#[test]
+fn array_read_drops_only_initialized() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Tracked;
+
+    impl ReadFrom for Tracked {
+        fn read(data: &mut impl Read) -> std::io::Result<Self> {
+            u8::read(data).map(|_| Tracked)
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    assert!(<[Tracked; 4]>::read(&mut [0, 0].as_slice()).is_err());
+    assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+    let array = <[Tracked; 4]>::read(&mut [0; 4].as_slice()).unwrap();
+    assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+    drop(array);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 6);
+}
+
+#[derive(Debug, PartialEq, ReadFrom, WriteTo)]
+struct Save {
+    version: u16,
+    players: Vec<Login>,
+}
+
+#[test]
+fn error_offset_and_path() {
+    let save = Save {
+        version: 1,
+        players: vec![
+            Login {
+                id: 1,
+                name: "ok".to_string(),
+                scores: Vec::new(),
+                cached: None,
+                admin: false,
+            },
+            Login {
+                id: 2,
+                name: "bad".to_string(),
+                scores: Vec::new(),
+                cached: None,
+                admin: true,
+            },
+        ],
+    };
+    let mut buf = crate::serial::encode(&save).unwrap();
+
+    // Corrupt the UTF-8 of the second name.
+    let name = buf.len() - 1 - 2 - 3;
+    buf[name] = 0xff;
+    let error = crate::serial::decode_bytes::<Save>(&buf).unwrap_err();
+    assert_eq!(error.kind(), SerialErrorKind::InvalidUtf8);
+    assert_eq!(error.offset(), Some(name as u64 + 3));
+    assert_eq!(
+        error.path().collect::<Vec<_>>(),
+        ["Save.players", "Login.name"]
+    );
+    assert!(error.to_string().starts_with(&format!(
+        "invalid UTF-8 at byte {} in Save.players > Login.name",
+        name + 3
+    )));
+
+    // Turn the last bool into a 2.
+    buf[name] = b'b';
+    *buf.last_mut().unwrap() = 2;
+    let error = crate::serial::decode_bytes::<Save>(&buf).unwrap_err();
+    assert_eq!(error.kind(), SerialErrorKind::InvalidBool);
+    assert_eq!(error.offset(), Some(buf.len() as u64));
+
+    let error = crate::serial::decode_bytes::<Save>(&buf[..buf.len() - 1]).unwrap_err();
+    assert_eq!(error.kind(), SerialErrorKind::Eof);
+    assert_eq!(error.path().last(), Some("Login.admin"));
+
+    buf.push(0);
+    let error = crate::serial::decode_bytes::<u16>(&buf).unwrap_err();
+    assert_eq!(error.kind(), SerialErrorKind::TrailingBytes);
+    assert_eq!(error.offset(), Some(2));
+}
+
+#[test]
+fn error_converts_to_io() {
+    let error = Packet::read(&mut [42u8].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let error = SerialError::from_io(error);
+    assert_eq!(error.kind(), SerialErrorKind::InvalidTag);
+    assert_eq!(
+        error.to_string(),
+        "invalid tag: 42 is not a variant of Packet"
+    );
+
+    let error = u32::read(&mut [1u8].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(SerialError::from_io(error).kind(), SerialErrorKind::Eof);
+
+    let error = "too long"
+        .write_prefixed_bound::<u8>(&mut Vec::new(), 4)
+        .unwrap_err();
+    assert_eq!(
+        SerialError::from_io(error).kind(),
+        SerialErrorKind::BoundExceeded
+    );
+}
+
+#[test]
+fn reads_and_writes_share_the_default_bound() {
+    let limit = i16::MAX as usize;
+
+    let text = "a".repeat(limit);
+    assert_eq!(round_trip(&text)[..4], (limit as u32).to_le_bytes());
+    let list = vec![7u8; limit];
+    round_trip(&list);
+    let map: HashMap<u16, u8> = (0..limit as u16).map(|i| (i, 0)).collect();
+    round_trip(&map);
+
+    // Just above the bound, neither side accepts the value.
+    let text = "a".repeat(limit + 1);
+    let error = crate::serial::encode(&text).unwrap_err();
+    assert_eq!(error.kind(), SerialErrorKind::BoundExceeded);
+    let error = crate::serial::encode(&vec![7u8; limit + 1]).unwrap_err();
+    assert_eq!(error.kind(), SerialErrorKind::BoundExceeded);
+
+    let mut buf = Vec::new();
+    text.write_prefixed_bound::<u32>(&mut buf, limit + 1)
+        .unwrap();
+    let error = String::read(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(
+        SerialError::from_io(error).kind(),
+        SerialErrorKind::BoundExceeded
+    );
+    let read = String::read_prefixed_bound::<u32>(&mut buf.as_slice(), limit + 1).unwrap();
+    assert_eq!(read, text);
+}
+
+#[test]
+fn round_trip_primitives() {
+    use crate::primitives::{Date, Matrix4, Point, Vec2, Vec3, Vec4};
+
+    assert_eq!(round_trip(&Vec2::new(-3i16, 4)).len(), 4);
+    round_trip(&Vec3::new(1.0, 2.5, -0.5));
+    round_trip(&Point::new(0.25, 8.0));
+
+    let matrix = Matrix4::ortho(0.0, 800.0, 600.0, 0.0, -1.0, 1.0);
+    let buf = crate::serial::encode(&matrix).unwrap();
+    assert_eq!(buf.len(), 64);
+    let read = Matrix4::read(&mut buf.as_slice()).unwrap();
+    for i in 0..4 {
+        assert_eq!(read[i], matrix[i]);
+    }
+    assert_eq!(read.w, Vec4::new(-1.0, 1.0, 0.0, 1.0));
+
+    let date = Date::from_unix_secs(1_700_000_000);
+    let buf = crate::serial::encode(&date).unwrap();
+    assert_eq!(buf.len(), 7);
+    assert_eq!(
+        Date::read(&mut buf.as_slice()).unwrap().to_string(),
+        date.to_string()
+    );
+
+    // Month, day, hour, minute and second.
+    for (index, value) in [(2, 0), (2, 13), (3, 0), (3, 31), (4, 24), (5, 60), (6, 60)] {
+        let mut invalid = buf.clone();
+        invalid[index] = value;
+        let error = Date::read(&mut invalid.as_slice()).unwrap_err();
+        assert_eq!(
+            SerialError::from_io(error).kind(),
+            SerialErrorKind::OutOfRange,
+            "byte {index} = {value}"
+        );
+    }
+}
+
+#[test]
+fn refuses_empty_matrices() {
+    use crate::collections::Matrix;
+
+    let empty = Matrix::from_vec(Vec::new(), 0, 3);
+    let error = crate::serial::encode(&empty).unwrap_err();
+    // Dropping would free the dangling pointer of the empty Vec.
+    std::mem::forget(empty);
+    assert_eq!(error.kind(), SerialErrorKind::InvalidLength);
+
+    let mut buf = Vec::new();
+    0u32.write(&mut buf).unwrap();
+    3u32.write(&mut buf).unwrap();
+    let error = Matrix::read(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(
+        SerialError::from_io(error).kind(),
+        SerialErrorKind::InvalidLength
+    );
+}
+
+#[test]
+fn round_trip_collections() {
+    use crate::collections::{Matrix, Vec3D};
+
+    let matrix = Matrix::random(3, 700, 1.0);
+    let buf = crate::serial::encode(&matrix).unwrap();
+    assert_eq!(buf.len(), 8 + 3 * 700 * 4);
+    let read = Matrix::read(&mut buf.as_slice()).unwrap();
+    assert_eq!((read.rows(), read.cols()), (3, 700));
+    assert_eq!(read.as_slice(), matrix.as_slice());
+    assert!(Matrix::read(&mut &buf[..buf.len() - 1]).is_err());
+
+    let mut volume = Vec3D::new(2, 3, 4);
+    for d in 0..2 {
+        for r in 0..3 {
+            for c in 0..4 {
+                volume[(d, r, c)] = (d * 100 + r * 10 + c) as f32;
+            }
+        }
+    }
+    let buf = crate::serial::encode(&volume).unwrap();
+    let read = Vec3D::read(&mut buf.as_slice()).unwrap();
+    assert_eq!((read.depth(), read.rows(), read.cols()), (2, 3, 4));
+    assert_eq!(read[(1, 2, 3)], 123.0);
+    assert_eq!(read.get(0, 1, 2), 12.0);
+
+    let empty = [0u8, 0, 0, 0, 5, 0, 0, 0];
+    let error = Matrix::read(&mut empty.as_slice()).unwrap_err();
+    assert_eq!(
+        SerialError::from_io(error).kind(),
+        SerialErrorKind::InvalidLength
+    );
+    let huge = [0xffu8; 12];
+    assert!(Vec3D::read(&mut huge.as_slice()).is_err());
+}
@@ -0,0 +1,431 @@
+use std::{
+    fmt::{self, Write as _},
+    io::{Read, Result, Write},
+};
+
+use crate::serial::{
+    PrefixedRead, PrefixedWrite, ReadFrom, SerialError, SerialErrorKind, VarI64, VarU32, VarU64,
+    WriteTo,
+};
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INT: u8 = 3;
+const UINT: u8 = 4;
+const FLOAT: u8 = 5;
+const BYTES: u8 = 6;
+const STRING: u8 = 7;
+const LIST: u8 = 8;
+const MAP: u8 = 9;
+
+/// The largest string or byte string that is read.
+const MAX_LEN: usize = 16 << 20;
+/// How deep lists and maps can be nested, so reading cannot overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// A value of the self-describing encoding, where every value starts with a tag byte.
+///
+/// Integers are encoded as LEB128, floats as little-endian `f64` and strings, byte strings,
+/// lists and maps start with their length as a [`VarU32`]. Maps keep their entries in order and
+/// allow any value as key.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bytes(Vec<u8>),
+    String(String),
+    List(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+    /// The value of the entry in a map with a string key of `key`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, Self::String(k) if k == key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn read_nested(data: &mut impl Read, depth: usize) -> Result<Self> {
+        let tag = u8::read(data)?;
+        Ok(match tag {
+            NULL => Self::Null,
+            FALSE => Self::Bool(false),
+            TRUE => Self::Bool(true),
+            INT => Self::Int(VarI64::read(data)?.0),
+            UINT => Self::UInt(VarU64::read(data)?.0),
+            FLOAT => Self::Float(f64::read(data)?),
+            BYTES => Self::Bytes(Vec::read_prefixed_bound::<VarU32>(data, MAX_LEN)?),
+            STRING => Self::String(String::read_prefixed_bound::<VarU32>(data, MAX_LEN)?),
+            LIST | MAP => {
+                let len = read_count(data, depth)?;
+                let mut items = Vec::with_capacity(len.min(1024));
+                if tag == LIST {
+                    for _ in 0..len {
+                        items.push(Self::read_nested(data, depth + 1)?);
+                    }
+                    Self::List(items)
+                } else {
+                    let mut entries = Vec::with_capacity(len.min(1024));
+                    for _ in 0..len {
+                        let key = Self::read_nested(data, depth + 1)?;
+                        entries.push((key, Self::read_nested(data, depth + 1)?));
+                    }
+                    Self::Map(entries)
+                }
+            }
+            _ => Err(invalid_tag(tag))?,
+        })
+    }
+}
+
+fn invalid_tag(tag: u8) -> SerialError {
+    SerialError::new(SerialErrorKind::InvalidTag).with_source(format!("{tag} is not a value tag"))
+}
+
+fn read_count(data: &mut impl Read, depth: usize) -> Result<usize> {
+    if depth == MAX_DEPTH {
+        Err(SerialError::new(SerialErrorKind::BoundExceeded)
+            .with_source(format!("values are nested deeper than {MAX_DEPTH}")))?;
+    }
+    Ok(VarU32::read(data)?.0 as usize)
+}
+
+fn write_count(len: usize, writer: &mut impl Write) -> Result<()> {
+    let len = VarU32::try_from(len)
+        .map_err(|e| SerialError::new(SerialErrorKind::OutOfRange).with_source(e))?;
+    len.write(writer)
+}
+
+impl ReadFrom for Value {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Self::read_nested(data, 0)
+    }
+}
+
+impl WriteTo for Value {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        match self {
+            Self::Null => NULL.write(writer),
+            Self::Bool(false) => FALSE.write(writer),
+            Self::Bool(true) => TRUE.write(writer),
+            Self::Int(value) => {
+                INT.write(writer)?;
+                VarI64(*value).write(writer)
+            }
+            Self::UInt(value) => {
+                UINT.write(writer)?;
+                VarU64(*value).write(writer)
+            }
+            Self::Float(value) => {
+                FLOAT.write(writer)?;
+                value.write(writer)
+            }
+            Self::Bytes(bytes) => {
+                BYTES.write(writer)?;
+                bytes.write_prefixed_bound::<VarU32>(writer, MAX_LEN)
+            }
+            Self::String(string) => {
+                STRING.write(writer)?;
+                string.write_prefixed_bound::<VarU32>(writer, MAX_LEN)
+            }
+            Self::List(items) => {
+                LIST.write(writer)?;
+                write_count(items.len(), writer)?;
+                items.iter().try_for_each(|item| item.write(writer))
+            }
+            Self::Map(entries) => {
+                MAP.write(writer)?;
+                write_count(entries.len(), writer)?;
+                entries.iter().try_for_each(|(key, value)| {
+                    key.write(writer)?;
+                    value.write(writer)
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::UInt(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Bytes(bytes) => {
+                f.write_char('<')?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(' ')?;
+                    }
+                    write!(f, "{byte:02x}")?;
+                }
+                f.write_char('>')
+            }
+            Self::String(string) => write!(f, "{string:?}"),
+            Self::List(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Map(entries) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident, $target:ty, $($ty:ty),*) => {$(
+        impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Self::$variant(value as $target)
+            }
+        }
+    )*};
+}
+
+impl_from!(Int, i64, i8, i16, i32, i64);
+impl_from!(UInt, u64, u8, u16, u32, u64);
+impl_from!(Float, f64, f32, f64);
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::List(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+/// Renders data in the self-describing encoding as a hex dump, with every value annotated
+/// next to its bytes:
+///
+/// ```text
+/// 0000  09 01                                            map (1)
+/// 0002  07 02 69 64                                        "id"
+/// 0006  04 07                                              uint 7
+/// ```
+///
+/// Invalid data is annotated up to the error, followed by the error and the unread bytes.
+pub fn annotate(data: &[u8]) -> String {
+    let mut annotator = Annotator {
+        out: String::new(),
+        data,
+        rest: data,
+        start: 0,
+    };
+
+    while !annotator.rest.is_empty() {
+        if let Err(e) = annotator.value(0) {
+            let error = SerialError::from_io(e).at(annotator.offset() as u64);
+            let _ = writeln!(annotator.out, "!! {error}");
+            annotator.rest = &[];
+            annotator.line(annotator.start, 0, "unread".into());
+            break;
+        }
+    }
+    annotator.out
+}
+
+struct Annotator<'a> {
+    out: String,
+    data: &'a [u8],
+    rest: &'a [u8],
+    /// Where the innermost value that is being read starts.
+    start: usize,
+}
+
+impl Annotator<'_> {
+    const BYTES_PER_LINE: usize = 16;
+
+    fn offset(&self) -> usize {
+        self.data.len() - self.rest.len()
+    }
+
+    fn value(&mut self, depth: usize) -> Result<()> {
+        let start = self.offset();
+        self.start = start;
+        let tag = u8::read(&mut self.rest)?;
+
+        let description = match tag {
+            NULL => "null".into(),
+            FALSE => "false".into(),
+            TRUE => "true".into(),
+            INT => format!("int {}", VarI64::read(&mut self.rest)?.0),
+            UINT => format!("uint {}", VarU64::read(&mut self.rest)?.0),
+            FLOAT => format!("float {:?}", f64::read(&mut self.rest)?),
+            BYTES => {
+                let bytes = Vec::<u8>::read_prefixed_bound::<VarU32>(&mut self.rest, MAX_LEN)?;
+                format!("bytes ({})", bytes.len())
+            }
+            STRING => {
+                let string = String::read_prefixed_bound::<VarU32>(&mut self.rest, MAX_LEN)?;
+                format!("{string:?}")
+            }
+            LIST | MAP => {
+                let len = read_count(&mut self.rest, depth)?;
+                let kind = if tag == LIST { "list" } else { "map" };
+                self.line(start, depth, format!("{kind} ({len})"));
+
+                let items = if tag == LIST { len } else { len * 2 };
+                for _ in 0..items {
+                    self.value(depth + 1)?;
+                }
+                return Ok(());
+            }
+            _ => Err(invalid_tag(tag))?,
+        };
+
+        self.line(start, depth, description);
+        Ok(())
+    }
+
+    /// Writes the bytes from `start` up to the current offset, with `description` on the first
+    /// line.
+    fn line(&mut self, start: usize, depth: usize, description: String) {
+        let end = self.offset();
+        let mut description = Some(description);
+
+        for (i, chunk) in self.data[start..end]
+            .chunks(Self::BYTES_PER_LINE)
+            .enumerate()
+        {
+            let mut hex = String::with_capacity(Self::BYTES_PER_LINE * 3);
+            for byte in chunk {
+                let _ = write!(hex, "{byte:02x} ");
+            }
+            let offset = start + i * Self::BYTES_PER_LINE;
+            let text = description.take().unwrap_or_default();
+            let line = format!(
+                "{offset:04x}  {hex:width$} {:indent$}{text}",
+                "",
+                width = Self::BYTES_PER_LINE * 3,
+                indent = depth * 2
+            );
+            self.out.push_str(line.trim_end());
+            self.out.push('\n');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::{decode_bytes, encode};
+
+    fn sample() -> Value {
+        Value::Map(vec![
+            ("id".into(), 7u32.into()),
+            ("name".into(), "ferris".into()),
+            (
+                "pos".into(),
+                vec![(-1.5f64).into(), Value::Int(-300)].into(),
+            ),
+            ("raw".into(), Value::Bytes((0..20).collect())),
+            (Value::Bool(true), Value::Null),
+        ])
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = sample();
+        let buf = encode(&value).unwrap();
+        assert_eq!(decode_bytes::<Value>(&buf).unwrap(), value);
+        assert_eq!(value.get("name"), Some(&Value::String("ferris".into())));
+        assert_eq!(
+            value.to_string(),
+            "{\"id\": 7, \"name\": \"ferris\", \"pos\": [-1.5, -300], \
+             \"raw\": <00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f 10 11 12 13>, true: null}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        let error = decode_bytes::<Value>(&[42]).unwrap_err();
+        assert_eq!(error.kind(), SerialErrorKind::InvalidTag);
+
+        let mut nested = [LIST, 1].repeat(MAX_DEPTH + 1);
+        nested.push(NULL);
+        let error = decode_bytes::<Value>(&nested).unwrap_err();
+        assert_eq!(error.kind(), SerialErrorKind::BoundExceeded);
+        assert!(decode_bytes::<Value>(&nested[2..]).is_ok());
+    }
+
+    #[test]
+    fn annotates_hex_dump() {
+        let buf = encode(&sample()).unwrap();
+        let expected = "\
+0000  09 05                                            map (5)
+0002  07 02 69 64                                        \"id\"
+0006  04 07                                              uint 7
+0008  07 04 6e 61 6d 65                                  \"name\"
+000e  07 06 66 65 72 72 69 73                            \"ferris\"
+0016  07 03 70 6f 73                                     \"pos\"
+001b  08 02                                              list (2)
+001d  05 00 00 00 00 00 00 f8 bf                           float -1.5
+0026  03 d7 04                                             int -300
+0029  07 03 72 61 77                                     \"raw\"
+002e  06 14 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d    bytes (20)
+003e  0e 0f 10 11 12 13
+0044  02                                                 true
+0045  00                                                 null
+";
+        assert_eq!(annotate(&buf), expected);
+
+        let dump = annotate(&[NULL, LIST, 2, INT, 0x80, 0x80]);
+        assert_eq!(
+            dump,
+            "\
+0000  00                                               null
+0001  08 02                                            list (2)
+!! unexpected end of data at byte 6: failed to fill whole buffer
+0003  03 80 80                                         unread
+"
+        );
+    }
+}
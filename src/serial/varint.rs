@@ -0,0 +1,242 @@
+use std::{
+    io::{Read, Result, Write},
+    num::TryFromIntError,
+};
+
+use crate::serial::{ReadFrom, SerialError, SerialErrorKind, WriteTo};
+
+/// A `u32` encoded as LEB128, taking one to five bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarU32(pub u32);
+
+/// A `u64` encoded as LEB128, taking one to ten bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarU64(pub u64);
+
+/// An `i32` encoded as zigzag LEB128, so small negative values stay small.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarI32(pub i32);
+
+/// An `i64` encoded as zigzag LEB128, so small negative values stay small.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarI64(pub i64);
+
+/// Reads an unsigned LEB128 value that has to fit into `bits` bits.
+///
+/// Only the shortest encoding of a value is accepted.
+fn read_unsigned(data: &mut impl Read, bits: u32) -> Result<u64> {
+    let max_bytes = bits.div_ceil(7);
+    let mut value = 0;
+
+    for i in 0..max_bytes {
+        let byte = u8::read(data)?;
+        let shift = i * 7;
+        let payload = (byte & 0x7f) as u64;
+
+        if i == max_bytes - 1 && (byte & 0x80 != 0 || payload >> (bits - shift) != 0) {
+            break;
+        }
+
+        value |= payload << shift;
+
+        if byte & 0x80 == 0 {
+            if byte == 0 && i != 0 {
+                return Err(SerialError::new(SerialErrorKind::InvalidVarInt)
+                    .with_source("overlong encoding")
+                    .into());
+            }
+            return Ok(value);
+        }
+    }
+
+    Err(SerialError::new(SerialErrorKind::InvalidVarInt)
+        .with_source(format!("overflows {bits} bits"))
+        .into())
+}
+
+fn write_unsigned(mut value: u64, writer: &mut impl Write) -> Result<()> {
+    let mut buf = [0; 10];
+    let mut len = 0;
+
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+
+    writer.write_all(&buf[..len])
+}
+
+const fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+const fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+impl ReadFrom for VarU32 {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Ok(Self(read_unsigned(data, 32)? as u32))
+    }
+}
+
+impl WriteTo for VarU32 {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        write_unsigned(self.0 as u64, writer)
+    }
+}
+
+impl ReadFrom for VarU64 {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Ok(Self(read_unsigned(data, 64)?))
+    }
+}
+
+impl WriteTo for VarU64 {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        write_unsigned(self.0, writer)
+    }
+}
+
+impl ReadFrom for VarI32 {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Ok(Self(unzigzag(read_unsigned(data, 32)?) as i32))
+    }
+}
+
+impl WriteTo for VarI32 {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        write_unsigned(zigzag(self.0 as i64), writer)
+    }
+}
+
+impl ReadFrom for VarI64 {
+    fn read(data: &mut impl Read) -> Result<Self> {
+        Ok(Self(unzigzag(read_unsigned(data, 64)?)))
+    }
+}
+
+impl WriteTo for VarI64 {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        write_unsigned(zigzag(self.0), writer)
+    }
+}
+
+macro_rules! impl_conversions {
+    ($($var:ident($int:ty)),*) => {$(
+        impl From<$int> for $var {
+            fn from(value: $int) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$var> for $int {
+            fn from(value: $var) -> Self {
+                value.0
+            }
+        }
+
+        impl TryFrom<usize> for $var {
+            type Error = TryFromIntError;
+
+            fn try_from(value: usize) -> std::result::Result<Self, Self::Error> {
+                <$int>::try_from(value).map(Self)
+            }
+        }
+
+        impl TryFrom<$var> for usize {
+            type Error = TryFromIntError;
+
+            fn try_from(value: $var) -> std::result::Result<Self, Self::Error> {
+                usize::try_from(value.0)
+            }
+        }
+    )*};
+}
+
+impl_conversions!(VarU32(u32), VarU64(u64), VarI32(i32), VarI64(i64));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::{PrefixedRead, PrefixedWrite};
+
+    fn encode(value: impl WriteTo) -> Vec<u8> {
+        let mut buf = Vec::new();
+        value.write(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn encodings() {
+        assert_eq!(encode(VarU32(0)), [0]);
+        assert_eq!(encode(VarU32(127)), [0x7f]);
+        assert_eq!(encode(VarU32(128)), [0x80, 0x01]);
+        assert_eq!(encode(VarU32(300)), [0xac, 0x02]);
+        assert_eq!(encode(VarU32(u32::MAX)), [0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert_eq!(encode(VarU64(u64::MAX)).len(), 10);
+        assert_eq!(encode(VarI32(0)), [0]);
+        assert_eq!(encode(VarI32(-1)), [1]);
+        assert_eq!(encode(VarI32(1)), [2]);
+        assert_eq!(encode(VarI32(-64)), [0x7f]);
+        assert_eq!(encode(VarI64(i64::MIN)).len(), 10);
+    }
+
+    #[test]
+    fn round_trip() {
+        for value in [0, 1, 127, 128, 16383, 16384, u32::MAX / 2, u32::MAX] {
+            let buf = encode(VarU32(value));
+            assert_eq!(VarU32::read(&mut buf.as_slice()).unwrap().0, value);
+        }
+        for value in [0, 1 << 35, u64::MAX - 1, u64::MAX] {
+            let buf = encode(VarU64(value));
+            assert_eq!(VarU64::read(&mut buf.as_slice()).unwrap().0, value);
+        }
+        for value in [0, -1, 1, -64, 64, i32::MIN, i32::MAX] {
+            let buf = encode(VarI32(value));
+            assert_eq!(VarI32::read(&mut buf.as_slice()).unwrap().0, value);
+        }
+        for value in [0, -1, i64::MIN, i64::MAX] {
+            let buf = encode(VarI64(value));
+            assert_eq!(VarI64::read(&mut buf.as_slice()).unwrap().0, value);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        // Overlong encodings of 0 and 1.
+        assert!(VarU32::read(&mut [0x80, 0x00].as_slice()).is_err());
+        assert!(VarU64::read(&mut [0x81, 0x80, 0x00].as_slice()).is_err());
+        // Values that do not fit.
+        assert!(VarU32::read(&mut [0xff, 0xff, 0xff, 0xff, 0x1f].as_slice()).is_err());
+        assert!(VarU32::read(&mut [0x80, 0x80, 0x80, 0x80, 0x80, 0x01].as_slice()).is_err());
+        let mut too_big = [0xff; 10];
+        too_big[9] = 0x02;
+        assert!(VarU64::read(&mut too_big.as_slice()).is_err());
+        // Truncated input.
+        assert!(VarU32::read(&mut [0x80].as_slice()).is_err());
+    }
+
+    #[test]
+    fn as_prefix() {
+        let text = "a".repeat(200);
+        let mut buf = Vec::new();
+        text.write_prefixed::<VarU32>(&mut buf).unwrap();
+        assert_eq!(buf.len(), 2 + 200);
+
+        let read = String::read_prefixed::<VarU32>(&mut buf.as_slice()).unwrap();
+        assert_eq!(read, text);
+
+        let mut buf = Vec::new();
+        vec![1u8, 2].write_prefixed::<VarU64>(&mut buf).unwrap();
+        assert_eq!(buf, [2, 1, 2]);
+        assert!(String::read_prefixed::<VarI32>(&mut [1, b'a'].as_slice()).is_err());
+    }
+}
@@ -1,11 +1,11 @@
 #![allow(missing_docs, clippy::disallowed_types)]
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     hash::BuildHasher,
     io::{Result, Write},
 };
 
-use crate::serial::{PrefixedWrite, WriteTo};
+use crate::serial::{Be, Le, PrefixedWrite, SerialError, SerialErrorKind, WriteTo};
 
 impl WriteTo for bool {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
@@ -14,106 +14,180 @@ impl WriteTo for bool {
     }
 }
 
-impl WriteTo for u8 {
+macro_rules! impl_write {
+    ($($ty:ty),*) => {$(
+        impl WriteTo for $ty {
+            fn write(&self, writer: &mut impl Write) -> Result<()> {
+                Le(*self).write(writer)
+            }
+        }
+
+        impl WriteTo for Le<$ty> {
+            fn write(&self, writer: &mut impl Write) -> Result<()> {
+                writer.write_all(&self.0.to_le_bytes())
+            }
+        }
+
+        impl WriteTo for Be<$ty> {
+            fn write(&self, writer: &mut impl Write) -> Result<()> {
+                writer.write_all(&self.0.to_be_bytes())
+            }
+        }
+    )*};
+}
+
+impl_write!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl WriteTo for usize {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        u64::try_from(*self)
+            .map_err(|e| SerialError::new(SerialErrorKind::OutOfRange).with_source(e))?
+            .write(writer)
     }
 }
 
-impl WriteTo for u16 {
+impl WriteTo for isize {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        i64::try_from(*self)
+            .map_err(|e| SerialError::new(SerialErrorKind::OutOfRange).with_source(e))?
+            .write(writer)
     }
 }
 
-impl WriteTo for u32 {
+impl WriteTo for char {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        u32::from(*self).write(writer)
     }
 }
 
-impl WriteTo for u64 {
+impl WriteTo for () {
+    fn write(&self, _: &mut impl Write) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: WriteTo> WriteTo for Option<T> {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        if let Some(value) = self {
+            true.write(writer)?;
+            value.write(writer)
+        } else {
+            false.write(writer)
+        }
     }
 }
 
-impl WriteTo for i8 {
+impl<T: WriteTo, const N: usize> WriteTo for [T; N] {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        for i in self {
+            i.write(writer)?;
+        }
+        Ok(())
     }
 }
 
-impl WriteTo for i16 {
+impl<T: WriteTo, E: WriteTo> WriteTo for std::result::Result<T, E> {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        match self {
+            Ok(value) => {
+                0u8.write(writer)?;
+                value.write(writer)
+            }
+            Err(error) => {
+                1u8.write(writer)?;
+                error.write(writer)
+            }
+        }
     }
 }
 
-impl WriteTo for i32 {
+impl<T: WriteTo + ?Sized> WriteTo for Box<T> {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        (**self).write(writer)
     }
 }
 
-impl WriteTo for i64 {
+impl<T: WriteTo + ?Sized> WriteTo for &T {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        (**self).write(writer)
     }
 }
 
-impl WriteTo for f32 {
+macro_rules! impl_write_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: WriteTo),+> WriteTo for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn write(&self, writer: &mut impl Write) -> Result<()> {
+                let ($($name,)+) = self;
+                $($name.write(writer)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_write_tuple!(A);
+impl_write_tuple!(A B);
+impl_write_tuple!(A B C);
+impl_write_tuple!(A B C D);
+impl_write_tuple!(A B C D E);
+impl_write_tuple!(A B C D E F);
+impl_write_tuple!(A B C D E F G);
+impl_write_tuple!(A B C D E F G H);
+impl_write_tuple!(A B C D E F G H I);
+impl_write_tuple!(A B C D E F G H I J);
+impl_write_tuple!(A B C D E F G H I J K);
+impl_write_tuple!(A B C D E F G H I J K L);
+
+impl WriteTo for str {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        self.write_prefixed::<u32>(writer)
     }
 }
 
-impl WriteTo for f64 {
+impl WriteTo for String {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        writer.write_all(&self.to_le_bytes())
+        self.write_prefixed::<u32>(writer)
     }
 }
 
-impl<T: WriteTo> WriteTo for Option<T> {
+impl<T: WriteTo> WriteTo for [T] {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        if let Some(value) = self {
-            true.write(writer)?;
-            value.write(writer)
-        } else {
-            false.write(writer)
-        }
+        self.write_prefixed::<u32>(writer)
     }
 }
 
-impl<T: WriteTo, const N: usize> WriteTo for [T; N] {
+impl<T: WriteTo> WriteTo for Vec<T> {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        for i in self {
-            i.write(writer)?;
-        }
-        Ok(())
+        self.write_prefixed::<u32>(writer)
     }
 }
 
-impl<T: WriteTo, Z: WriteTo> WriteTo for (T, Z) {
+impl<T: WriteTo> WriteTo for VecDeque<T> {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        self.0.write(writer)?;
-        self.1.write(writer)
+        self.write_prefixed::<u32>(writer)
+    }
+}
+
+impl<T: WriteTo, S: BuildHasher> WriteTo for HashSet<T, S> {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        self.write_prefixed::<u32>(writer)
+    }
+}
+
+impl<T: WriteTo> WriteTo for BTreeSet<T> {
+    fn write(&self, writer: &mut impl Write) -> Result<()> {
+        self.write_prefixed::<u32>(writer)
     }
 }
 
-#[allow(missing_docs)]
 impl<K: WriteTo, V: WriteTo, S: BuildHasher> WriteTo for HashMap<K, V, S> {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
-        (self.len() as u32).write(writer)?;
-        for (key, value) in self {
-            key.write(writer)?;
-            value.write(writer)?;
-        }
-        Ok(())
+        self.write_prefixed::<u32>(writer)
     }
 }
 
-impl<T: WriteTo> WriteTo for Vec<T> {
+impl<K: WriteTo, V: WriteTo> WriteTo for BTreeMap<K, V> {
     fn write(&self, writer: &mut impl Write) -> Result<()> {
         self.write_prefixed::<u32>(writer)
     }
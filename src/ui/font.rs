@@ -1,6 +1,6 @@
 use std::{collections::HashMap, fmt};
 
-use crate::{primitives::Vec2, ui::materials::MatType};
+use crate::{json::JsonValue, primitives::Vec2, ui::materials::MatType};
 
 pub struct Font {
     glyphs: [RawGlyph; 127],
@@ -14,52 +14,44 @@ pub struct Font {
 }
 
 impl Font {
+    /// Parses the JSON layout written by msdf-atlas-gen.
+    ///
+    /// # Panics
+    /// If the data is not valid JSON or misses the metrics or a glyph advance.
     pub fn parse_msdf_from_bytes(data: &[u8]) -> Self {
-        let text = std::str::from_utf8(data).unwrap();
+        let json = JsonValue::parse_bytes(data).unwrap_or_else(|e| panic!("invalid font: {e}"));
 
-        let line_height = extract_float(text, "\"lineHeight\"");
-        let ascender = extract_float(text, "\"ascender\"");
-        let descender = extract_float(text, "\"descender\"");
+        let metrics = &json["metrics"];
+        let line_height = float(metrics, "lineHeight");
+        let ascender = float(metrics, "ascender");
+        let descender = float(metrics, "descender");
 
         let mut glyphs = [RawGlyph::default(); 127];
         let mut utf8 = HashMap::new();
 
-        let glyphs_start = text.find("\"glyphs\"").unwrap();
-        let mut rest = &text[glyphs_start..];
-
-        while let Some(pos) = rest.find("\"unicode\"") {
-            // zum Anfang des Objekts gehen
-            let start = rest[..pos].rfind('{').unwrap();
-            let obj = try_extract_block(&rest[start..], "{").unwrap();
-
-            let unicode = extract_number(obj, "\"unicode\"") as u32;
-            let unicode = char::from_u32(unicode).unwrap();
-            let advance = extract_float(obj, "\"advance\"");
-
-            let plane_block = try_extract_block(obj, "\"planeBounds\"");
-            let atlas_block = try_extract_block(obj, "\"atlasBounds\"");
-
-            let (left, right, bottom, top) = if let Some(pb) = plane_block {
-                (
-                    extract_float(pb, "\"left\""),
-                    extract_float(pb, "\"right\""),
-                    extract_float(pb, "\"bottom\""),
-                    extract_float(pb, "\"top\""),
-                )
-            } else {
-                (0.0, 0.0, 0.0, 0.0)
+        for entry in json["glyphs"].as_array().unwrap_or_default() {
+            let Some(unicode) = entry["unicode"].as_u64() else {
+                continue;
+            };
+            let unicode = char::from_u32(unicode as u32).unwrap();
+            let advance = float(entry, "advance");
+
+            let (left, right, bottom, top) = match &entry["planeBounds"] {
+                JsonValue::Null => (0.0, 0.0, 0.0, 0.0),
+                pb => (
+                    float(pb, "left"),
+                    float(pb, "right"),
+                    float(pb, "bottom"),
+                    float(pb, "top"),
+                ),
             };
 
-            let (atlas_start, atlas_end) = if let Some(ab) = atlas_block {
-                (
-                    Vec2::new(extract_float(ab, "\"left\""), extract_float(ab, "\"top\"")),
-                    Vec2::new(
-                        extract_float(ab, "\"right\""),
-                        extract_float(ab, "\"bottom\""),
-                    ),
-                )
-            } else {
-                (Vec2::zero(), Vec2::zero())
+            let (atlas_start, atlas_end) = match &entry["atlasBounds"] {
+                JsonValue::Null => (Vec2::zero(), Vec2::zero()),
+                ab => (
+                    Vec2::new(float(ab, "left"), float(ab, "top")),
+                    Vec2::new(float(ab, "right"), float(ab, "bottom")),
+                ),
             };
 
             let glyph = RawGlyph {
@@ -77,10 +69,6 @@ impl Font {
             } else {
                 utf8.insert(unicode, glyph);
             }
-
-            // nach diesem Objekt weitermachen
-            let offset = start + obj.len();
-            rest = &rest[offset..];
         }
 
         Self {
@@ -165,51 +153,8 @@ pub struct RawGlyph {
     pub advance: f32,
 }
 
-fn extract_number(source: &str, key: &str) -> u16 {
-    let start = if let Some(key) = source.find(key) {
-        key
-    } else {
-        return 0;
-    };
-    let sub = &source[start + key.len()..];
-
-    let colon = sub.find(':').unwrap();
-    let sub = &sub[colon + 1..];
-
-    let end = sub.find(|c: char| !c.is_numeric()).unwrap();
-    sub[..end].trim().parse().unwrap_or_default()
-}
-
-fn extract_float(source: &str, key: &str) -> f32 {
-    let start = source.find(key).unwrap();
-    let sub = &source[start + key.len()..];
-
-    let colon = sub.find(':').unwrap();
-    let sub = &sub[colon + 1..];
-
-    let end = sub
-        .find(|c: char| !(c.is_numeric() || c == '.' || c == '-'))
-        .unwrap();
-
-    sub[..end].trim().parse().unwrap()
-}
-
-fn try_extract_block<'a>(source: &'a str, key: &str) -> Option<&'a str> {
-    let start = source.find(key)?;
-    let sub = &source[start..];
-
-    let open = sub.find('{')?;
-    let mut depth = 1;
-    let mut i = open + 1;
-
-    while depth > 0 {
-        match sub.as_bytes()[i] {
-            b'{' => depth += 1,
-            b'}' => depth -= 1,
-            _ => {}
-        }
-        i += 1;
-    }
-
-    Some(&sub[open..i])
+fn float(object: &JsonValue, key: &str) -> f32 {
+    object[key]
+        .as_f32()
+        .unwrap_or_else(|| panic!("invalid font: {key:?} is not a number"))
 }